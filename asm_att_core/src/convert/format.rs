@@ -0,0 +1,336 @@
+//! Formatting of the templates of `asm_att` macro calls in Rust source.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::syntax::{Instruction, Statement, Template};
+
+use super::source::{self, MacroCall, macro_calls, parse_template, span, string_literal_for};
+use super::{Edit, Skipped, SourceError};
+
+/// The macros whose templates are formatted.
+const MACROS: &[&str] = &["asm_att", "global_asm_att", "naked_asm_att"];
+
+/// One level of indentation.
+const INDENT: &str = "    ";
+
+/// The outcome of [`format_templates`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Formatting {
+    /// The edits, in order and without overlaps.
+    pub edits: Vec<Edit>,
+    /// How many calls were reformatted.
+    pub formatted: usize,
+    /// The calls left alone.
+    pub skipped: Vec<Skipped>,
+}
+
+impl Formatting {
+    /// The source with the edits applied.
+    pub fn apply(&self, source: &str) -> String {
+        super::apply_edits(source, &self.edits)
+    }
+}
+
+/// Reformats the templates of the `asm_att!`, `global_asm_att!` and
+/// `naked_asm_att!` calls of a Rust source file.
+///
+/// Each statement gets a string literal of its own, the operands of the
+/// instructions of a call start in the same column, and whitespace within
+/// statements is normalized. Labels are never indented; the other statements
+/// of `global_asm_att!` are indented by four spaces, and those of the other
+/// macros are not. A call written on one line is spread over several when
+/// its template gets more literals.
+///
+/// Calls whose templates are not all string literals, hold comments or do
+/// not parse are skipped.
+///
+/// ```
+/// use asm_att_core::convert::format_templates;
+///
+/// let source = r#"asm_att!("xorl %eax,%eax; incq  {n}", n = inout(reg) n);"#;
+/// let formatting = format_templates(source).unwrap();
+/// assert_eq!(
+///     formatting.apply(source),
+///     r#"asm_att!(
+///     "xorl %eax, %eax",
+///     "incq {n}",
+///     n = inout(reg) n,
+/// );"#
+/// );
+/// ```
+pub fn format_templates(source: &str) -> Result<Formatting, SourceError> {
+    let tokens = source::tokenize(source)?;
+    let mut formatting = Formatting::default();
+    for call in macro_calls(source, &tokens, MACROS) {
+        match format_call(source, &call) {
+            Ok(Some(edit)) => {
+                formatting.edits.push(edit);
+                formatting.formatted += 1;
+            }
+            Ok(None) => {}
+            Err(reason) => formatting.skipped.push(Skipped {
+                offset: call.path.start,
+                reason,
+            }),
+        }
+    }
+    Ok(formatting)
+}
+
+/// The edit reformatting a call, or `None` if it is already formatted.
+fn format_call(source: &str, call: &MacroCall<'_>) -> Result<Option<Edit>, String> {
+    let (literals, values) = call.templates(source)?;
+    if values
+        .iter()
+        .any(|value| value.contains('#') || value.contains("/*"))
+    {
+        return Err("has comments, which formatting would drop".to_string());
+    }
+    let template = parse_template(source, call, &values)?;
+    let indent = if call.name.text(source) == "global_asm_att" {
+        INDENT
+    } else {
+        ""
+    };
+    let like = literals[0].text(source);
+    let formatted: Vec<String> = lines(&template, indent)
+        .iter()
+        .map(|line| literal(line, like))
+        .collect();
+    if formatted.is_empty() {
+        return Ok(None);
+    }
+
+    let (first, last) = (literals[0], literals[literals.len() - 1]);
+    let multiline = source[call.open.end..first.start].contains('\n');
+    let edit = if multiline || formatted.len() == literals.len() {
+        let separator = if multiline {
+            format!(",\n{}", indentation(source, first.start))
+        } else {
+            ", ".to_string()
+        };
+        Edit {
+            range: first.start..last.end,
+            replacement: formatted.join(&separator),
+        }
+    } else {
+        // Spread the call over several lines, one argument per line.
+        let outer = indentation(source, call.path.start);
+        let inner = format!("{outer}{INDENT}");
+        let rest = &call.args[literals.len()..];
+        let mut args = formatted;
+        args.extend(rest.iter().map(|arg| source[span(arg)].to_string()));
+        // No comma may follow the `; else { ... }` of `asm_att!`.
+        let comma = match rest.last() {
+            Some(arg) if arg.iter().any(|token| token.is_punct(source, ';')) => "",
+            _ => ",",
+        };
+        Edit {
+            range: call.open.end..call.close.start,
+            replacement: format!(
+                "\n{inner}{}{comma}\n{outer}",
+                args.join(&format!(",\n{inner}"))
+            ),
+        }
+    };
+    if source[edit.range.clone()] == edit.replacement {
+        return Ok(None);
+    }
+    if has_comments(source, call, &edit.range) {
+        return Err("has comments, which formatting would drop".to_string());
+    }
+    Ok(Some(edit))
+}
+
+/// Whether there are Rust comments between the arguments of a call within
+/// `range`, which the tokens skip.
+fn has_comments(source: &str, call: &MacroCall<'_>, range: &Range<usize>) -> bool {
+    let mut boundaries = vec![range.start];
+    for token in call.args.iter().flat_map(|arg| arg.iter()) {
+        if range.contains(&token.start) {
+            boundaries.extend([token.start, token.end]);
+        }
+    }
+    boundaries.push(range.end);
+    boundaries.sort_unstable();
+    boundaries.chunks(2).any(|gap| {
+        let text = &source[gap[0]..gap[1]];
+        text.contains("//") || text.contains("/*")
+    })
+}
+
+/// The statements of a template, one per line, with the operands of the
+/// instructions aligned.
+fn lines(template: &Template, indent: &str) -> Vec<String> {
+    let width = template
+        .instructions()
+        .filter(|(_, instruction)| !instruction.operands.is_empty())
+        .map(|(_, instruction)| head(instruction).len())
+        .max()
+        .unwrap_or(0);
+    template
+        .statements
+        .iter()
+        .map(|(_, statement)| match statement {
+            Statement::Label(label) => label.to_string(),
+            Statement::Directive(directive) => format!("{indent}{directive}"),
+            Statement::Instruction(instruction) if instruction.operands.is_empty() => {
+                format!("{indent}{}", head(instruction))
+            }
+            Statement::Instruction(instruction) => {
+                let operands: Vec<String> = instruction
+                    .operands
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                format!(
+                    "{indent}{:width$} {}",
+                    head(instruction),
+                    operands.join(", ")
+                )
+            }
+        })
+        .collect()
+}
+
+/// The prefixes and mnemonic of an instruction.
+fn head(instruction: &Instruction) -> String {
+    let mut head = String::new();
+    for prefix in &instruction.prefixes {
+        head.push_str(prefix);
+        head.push(' ');
+    }
+    head.push_str(&instruction.mnemonic);
+    head
+}
+
+/// A string literal for a line, raw like `like` only if it needs escapes.
+fn literal(line: &str, like: &str) -> String {
+    if line.contains(['"', '\\']) {
+        string_literal_for(line, like)
+    } else {
+        format!("\"{line}\"")
+    }
+}
+
+/// The whitespace that starts the line holding `offset`.
+fn indentation(source: &str, offset: usize) -> &str {
+    let start = source[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = &source[start..offset];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(source: &str) -> String {
+        format_templates(source).unwrap().apply(source)
+    }
+
+    #[test]
+    fn aligns_statements() {
+        let source = r#"
+            asm_att!(
+                "cmpq  $0,{n}; je 2f", "1:  lock incq ({p})",
+                "decq {n}",
+                "jne   1b",
+                "2:",
+                n = inout(reg) n,
+                p = in(reg) p,
+            );
+        "#;
+        let expected = r#"
+            asm_att!(
+                "cmpq      $0, {n}",
+                "je        2f",
+                "1:",
+                "lock incq ({p})",
+                "decq      {n}",
+                "jne       1b",
+                "2:",
+                n = inout(reg) n,
+                p = in(reg) p,
+            );
+        "#;
+        assert_eq!(formatted(source), expected);
+        assert!(format_templates(expected).unwrap().edits.is_empty());
+    }
+
+    #[test]
+    fn spreads_calls() {
+        assert_eq!(
+            formatted(
+                "    asm_att!(\"pause;pause\", options(nomem); else { core::hint::spin_loop() });"
+            ),
+            "    asm_att!(\n        \"pause\",\n        \"pause\",\n        \
+             options(nomem); else { core::hint::spin_loop() }\n    );"
+        );
+        assert_eq!(
+            formatted(r#"naked_asm_att!("movl  $1, %eax", "ret", options(raw));"#),
+            r#"naked_asm_att!("movl $1, %eax", "ret", options(raw));"#
+        );
+    }
+
+    #[test]
+    fn indents_global_templates() {
+        let source = r##"
+        global_asm_att!(
+            r#"
+            .global f
+            f:
+            movl %edi,%eax
+            vpaddd (%rdi){1to16}, %zmm0, %zmm1{%k1}{z}
+            ret
+            "#
+        );
+        "##;
+        let expected = r#"
+        global_asm_att!(
+            "    .global f",
+            "f:",
+            "    movl   %edi, %eax",
+            "    vpaddd (%rdi){{1to16}}, %zmm0, %zmm1{{%k1}}{{z}}",
+            "    ret"
+        );
+        "#;
+        assert_eq!(formatted(source), expected);
+    }
+
+    #[test]
+    fn skips_calls() {
+        let source = r#"
+            asm_att!(intel_to_att!("nop"));
+            asm_att!("nop # wait");
+            asm_att!("movl (%rax, %eax");
+            asm_att!(
+                "movq {src},%rsi", // source
+                "nop", /* wait */ src = in(reg) src,
+            );
+            asm_att!("nop;nop", /* both */ options(nomem));
+        "#;
+        let formatting = format_templates(source).unwrap();
+        assert!(formatting.edits.is_empty());
+        let reasons: Vec<&str> = formatting
+            .skipped
+            .iter()
+            .map(|skipped| skipped.reason.as_str())
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "has a template that is not a string literal",
+                "has comments, which formatting would drop",
+                "cannot parse line 1 of template 1: unclosed `(` in `movl (%rax, %eax`",
+                "has comments, which formatting would drop",
+                "has comments, which formatting would drop",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1068 @@
+//! Lowering of GCC extended `asm` statements to `asm_att!` arguments.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::Peekable;
+use core::str::Chars;
+
+use crate::isa;
+use crate::syntax::{Instruction, PREFIXES, Size};
+
+/// A GCC `asm` statement, `template : outputs : inputs : clobbers`, whose
+/// operand expressions are of type `E`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GccAsm<E> {
+    /// The template, with its string literals concatenated and unescaped.
+    pub template: String,
+    /// Whether the statement is a basic `asm` without any `:`, whose template
+    /// is taken verbatim: `%` does not start operand references there.
+    pub basic: bool,
+    /// The output operands, numbered from `%0`.
+    pub outputs: Vec<GccOperand<E>>,
+    /// The input operands, numbered after the outputs.
+    pub inputs: Vec<GccOperand<E>>,
+    /// The clobbers, such as `cc`, `memory` or `rcx`.
+    pub clobbers: Vec<String>,
+}
+
+/// An operand of a GCC `asm` statement, such as `[sum] "+r" (sum)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GccOperand<E> {
+    /// The symbolic name, referred to as `%[name]` in the template.
+    pub name: Option<String>,
+    /// The constraint, such as `=r`, `+m` or `a`.
+    pub constraint: String,
+    /// The expression between parentheses.
+    pub expr: E,
+}
+
+/// The arguments of the `asm_att!` invocation a [`GccAsm`] lowers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmAtt<E> {
+    /// The template strings, one per line of the GCC template.
+    pub templates: Vec<String>,
+    /// The operands, explicit registers and clobbers last.
+    pub operands: Vec<AsmOperand<E>>,
+    /// `nomem` or `readonly`, unless the statement clobbers `memory`.
+    pub options: Vec<&'static str>,
+}
+
+/// An operand of `asm_att!`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmOperand<E> {
+    /// `in(reg) value`.
+    In(RegSpec, Value<E>),
+    /// `lateout(reg) place`, `out(reg) place` for an early clobber, or
+    /// `out("ecx") _` for a clobber.
+    Out {
+        /// The register.
+        reg: RegSpec,
+        /// Whether the output may share a register with an input.
+        late: bool,
+        /// The place written, or `None` for `_`.
+        place: Option<E>,
+    },
+    /// `inout(reg) value`, or `inout(reg) value => place` for an input that
+    /// matches an output.
+    InOut {
+        /// The register.
+        reg: RegSpec,
+        /// The value read.
+        value: E,
+        /// The place written, if it is not `value` itself.
+        place: Option<E>,
+    },
+    /// `const value`.
+    Const(E),
+}
+
+/// The register of an [`AsmOperand`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegSpec {
+    /// A register class such as `reg` or `xmm_reg`.
+    Class(&'static str),
+    /// An explicit register such as `eax`.
+    Explicit(String),
+}
+
+/// The value of an [`AsmOperand::In`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value<E> {
+    /// The expression itself.
+    Expr(E),
+    /// `&raw const expr`: the address of a memory input.
+    Address(E),
+    /// `&raw mut expr`: the address of a memory output.
+    AddressMut(E),
+}
+
+/// The part of a GCC `asm` statement a [`GccError`] is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GccPart {
+    /// The statement as a whole.
+    Statement,
+    /// The template.
+    Template,
+    /// The output operand at this index.
+    Output(usize),
+    /// The input operand at this index, counted from the first input.
+    Input(usize),
+    /// The clobber at this index.
+    Clobber(usize),
+}
+
+/// An error lowering a GCC `asm` statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GccError {
+    part: GccPart,
+    message: String,
+}
+
+impl GccError {
+    fn new(part: GccPart, message: impl Into<String>) -> Self {
+        GccError {
+            part,
+            message: message.into(),
+        }
+    }
+
+    /// The part of the statement at fault.
+    pub fn part(&self) -> GccPart {
+        self.part
+    }
+
+    /// A description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for GccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl core::error::Error for GccError {}
+
+/// The registers of the `a`, `c`, `d`, `S` and `D` constraints: the
+/// constraint, the names from 8 to 64 bits, and the high byte if any.
+const REGISTERS: &[(char, [&str; 4], Option<&str>)] = &[
+    ('a', ["al", "ax", "eax", "rax"], Some("ah")),
+    ('c', ["cl", "cx", "ecx", "rcx"], Some("ch")),
+    ('d', ["dl", "dx", "edx", "rdx"], Some("dh")),
+    ('S', ["sil", "si", "esi", "rsi"], None),
+    ('D', ["dil", "di", "edi", "rdi"], None),
+];
+
+/// Registers that `asm!` reserves for the compiler.
+const RESERVED: &[&str] = &[
+    "rbx", "ebx", "bx", "bl", "bh", "rbp", "ebp", "bp", "bpl", "rsp", "esp", "sp", "spl",
+];
+
+/// How a constraint passes its operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    /// In a register of a class such as `reg`.
+    Class(&'static str),
+    /// In the register of [`REGISTERS`] at this index.
+    Register(usize),
+    /// In memory, whose address is passed in a `reg`.
+    Memory,
+    /// As a `const`.
+    Immediate,
+    /// In the register of the output at this index.
+    Matching(usize),
+}
+
+/// What `%N` refers to once lowered.
+#[derive(Clone, Copy, Debug)]
+enum Ref {
+    /// The positional operand at this index, of this register class.
+    Class(usize, &'static str),
+    /// The positional operand at this index, holding a memory address.
+    Memory(usize),
+    /// The positional `const` operand at this index.
+    Immediate(usize),
+    /// The register of [`REGISTERS`] at this index.
+    Register(usize),
+}
+
+impl<E> GccAsm<E> {
+    /// Lowers the statement to `asm_att!` arguments.
+    ///
+    /// `%N` and `%[name]` become placeholders, `%%` becomes `%`, and the
+    /// `{att|intel}` dialect alternatives keep their AT&T side. References to
+    /// the `a`, `c`, `d`, `S` and `D` registers are spelled out, as `asm!`
+    /// cannot refer to explicit registers.
+    ///
+    /// General-purpose registers take the width of a modifier such as `%k0`,
+    /// else of the suffix of the instruction, as GCC sizes them by the type
+    /// of their operand. Operands that are thereby bytes take `reg_byte`.
+    /// Otherwise placeholders are left to the type of the operand, and
+    /// explicit registers are 64 bits wide.
+    ///
+    /// `m` operands pass their address in a register. Without a `memory`
+    /// clobber, the statement gets `nomem`, or `readonly` if it only has
+    /// memory inputs. Flags are always clobbered by `asm!`, so that `cc` is
+    /// dropped.
+    pub fn lower(self) -> Result<AsmAtt<E>, GccError> {
+        let GccAsm {
+            template,
+            basic,
+            outputs,
+            inputs,
+            clobbers,
+        } = self;
+        let output_kinds = outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let part = GccPart::Output(index);
+                let (write, constraint) = match output.constraint.split_at_checked(1) {
+                    Some(("=", rest)) => (true, rest),
+                    Some(("+", rest)) => (false, rest),
+                    _ => {
+                        return Err(GccError::new(
+                            part,
+                            format!(
+                                "output constraint `{}` must start with `=` or `+`",
+                                output.constraint
+                            ),
+                        ));
+                    }
+                };
+                match parse_constraint(constraint, &outputs) {
+                    Ok(Kind::Immediate) => Err(GccError::new(
+                        part,
+                        format!("output constraint `{}` is an immediate", output.constraint),
+                    )),
+                    Ok(Kind::Matching(_)) => {
+                        Err(GccError::new(part, "only inputs can match another operand"))
+                    }
+                    Ok(kind) => Ok((write, kind)),
+                    Err(message) => Err(GccError::new(part, message)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let input_kinds = inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let part = GccPart::Input(index);
+                if input.constraint.starts_with(['=', '+']) {
+                    return Err(GccError::new(
+                        part,
+                        format!(
+                            "input constraint `{}` cannot start with `=` or `+`",
+                            input.constraint
+                        ),
+                    ));
+                }
+                parse_constraint(&input.constraint, &outputs)
+                    .map_err(|message| GccError::new(part, message))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let names: Vec<Option<String>> = outputs
+            .iter()
+            .chain(&inputs)
+            .map(|operand| operand.name.clone())
+            .collect();
+        let output_count = outputs.len();
+        let mut refs: Vec<Option<Ref>> = vec![None; names.len()];
+        let mut positional = Vec::new();
+        let mut explicit = Vec::new();
+        let mut inputs: Vec<Option<GccOperand<E>>> = inputs.into_iter().map(Some).collect();
+        let (mut reads_memory, mut writes_memory) = (false, false);
+
+        for (index, output) in outputs.into_iter().enumerate() {
+            let (write, kind) = output_kinds[index];
+            let matching = input_kinds
+                .iter()
+                .position(|input| *input == Kind::Matching(index));
+            let reg = match kind {
+                Kind::Class(class) => RegSpec::Class(class),
+                Kind::Register(register) => RegSpec::Explicit(REGISTERS[register].1[2].to_string()),
+                _ => {
+                    if let Some(input) = matching {
+                        return Err(GccError::new(
+                            GccPart::Input(input),
+                            format!("`%{index}` is a memory operand, which inputs cannot match"),
+                        ));
+                    }
+                    writes_memory = true;
+                    refs[index] = Some(Ref::Memory(positional.len()));
+                    positional.push(AsmOperand::In(
+                        RegSpec::Class("reg"),
+                        Value::AddressMut(output.expr),
+                    ));
+                    continue;
+                }
+            };
+            let operand = match (matching, write) {
+                (Some(input), true) => AsmOperand::InOut {
+                    reg,
+                    value: inputs[input].take().expect("matched once").expr,
+                    place: Some(output.expr),
+                },
+                (Some(input), false) => {
+                    return Err(GccError::new(
+                        GccPart::Input(input),
+                        format!("`%{index}` is already read by its `+` constraint"),
+                    ));
+                }
+                (None, true) => AsmOperand::Out {
+                    reg,
+                    late: !output.constraint.contains('&'),
+                    place: Some(output.expr),
+                },
+                (None, false) => AsmOperand::InOut {
+                    reg,
+                    value: output.expr,
+                    place: None,
+                },
+            };
+            let target = match kind {
+                Kind::Register(register) => {
+                    explicit.push(operand);
+                    Ref::Register(register)
+                }
+                Kind::Class(class) => {
+                    positional.push(operand);
+                    Ref::Class(positional.len() - 1, class)
+                }
+                _ => unreachable!(),
+            };
+            refs[index] = Some(target);
+            if let Some(input) = matching {
+                refs[output_count + input] = Some(target);
+            }
+        }
+
+        for (index, input) in inputs.into_iter().enumerate() {
+            let Some(input) = input else {
+                continue;
+            };
+            let target = match input_kinds[index] {
+                Kind::Class(class) => {
+                    positional.push(AsmOperand::In(
+                        RegSpec::Class(class),
+                        Value::Expr(input.expr),
+                    ));
+                    Ref::Class(positional.len() - 1, class)
+                }
+                Kind::Register(register) => {
+                    explicit.push(AsmOperand::In(
+                        RegSpec::Explicit(REGISTERS[register].1[2].to_string()),
+                        Value::Expr(input.expr),
+                    ));
+                    Ref::Register(register)
+                }
+                Kind::Memory => {
+                    reads_memory = true;
+                    positional.push(AsmOperand::In(
+                        RegSpec::Class("reg"),
+                        Value::Address(input.expr),
+                    ));
+                    Ref::Memory(positional.len() - 1)
+                }
+                Kind::Immediate => {
+                    positional.push(AsmOperand::Const(input.expr));
+                    Ref::Immediate(positional.len() - 1)
+                }
+                Kind::Matching(output) => {
+                    return Err(GccError::new(
+                        GccPart::Input(index),
+                        format!("`%{output}` is already matched by another input"),
+                    ));
+                }
+            };
+            refs[output_count + index] = Some(target);
+        }
+
+        let mut clobbers_memory = false;
+        for (index, clobber) in clobbers.iter().enumerate() {
+            let name = clobber.trim().trim_start_matches('%');
+            match name {
+                "memory" => clobbers_memory = true,
+                "cc" | "flags" | "fpsr" | "dirflag" => {}
+                _ if RESERVED.contains(&name) => {
+                    return Err(GccError::new(
+                        GccPart::Clobber(index),
+                        format!(
+                            "`%{name}` is reserved by the compiler and cannot be clobbered; \
+                             save and restore it in the template instead"
+                        ),
+                    ));
+                }
+                _ => explicit.push(AsmOperand::Out {
+                    reg: RegSpec::Explicit(name.to_string()),
+                    late: false,
+                    place: None,
+                }),
+            }
+        }
+
+        let mut refs: Vec<Ref> = refs.into_iter().map(|r| r.expect("lowered")).collect();
+        let templates = if basic {
+            template_lines(&template.replace('{', "{{").replace('}', "}}"))
+        } else {
+            let lower = |refs: &[Ref], bytes: &mut Vec<usize>| {
+                lower_template(&template, refs, &names, bytes)
+                    .map_err(|message| GccError::new(GccPart::Template, message))
+            };
+            let mut bytes = Vec::new();
+            let mut templates = lower(&refs, &mut bytes)?;
+            // Operands whose type GCC would size as a byte can only be held
+            // by the byte registers of `asm!`.
+            if !bytes.is_empty() {
+                for target in &mut refs {
+                    if let Ref::Class(index, class) = target
+                        && bytes.contains(index)
+                    {
+                        *class = "reg_byte";
+                    }
+                }
+                for index in bytes {
+                    let (AsmOperand::In(reg, _)
+                    | AsmOperand::Out { reg, .. }
+                    | AsmOperand::InOut { reg, .. }) = &mut positional[index]
+                    else {
+                        continue;
+                    };
+                    *reg = RegSpec::Class("reg_byte");
+                }
+                templates = lower(&refs, &mut Vec::new())?;
+            }
+            templates
+        };
+        let options = match (clobbers_memory, writes_memory, reads_memory) {
+            (false, false, false) => vec!["nomem"],
+            (false, false, true) => vec!["readonly"],
+            _ => Vec::new(),
+        };
+        positional.append(&mut explicit);
+        Ok(AsmAtt {
+            templates,
+            operands: positional,
+            options,
+        })
+    }
+}
+
+/// Parses a constraint without its `=` or `+`, picking the first register
+/// alternative, else memory, else an immediate.
+fn parse_constraint<E>(constraint: &str, outputs: &[GccOperand<E>]) -> Result<Kind, String> {
+    if let Some(name) = constraint
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return outputs
+            .iter()
+            .position(|output| output.name.as_deref() == Some(name))
+            .map(Kind::Matching)
+            .ok_or_else(|| format!("no output operand is named `{name}`"));
+    }
+    if !constraint.is_empty() && constraint.bytes().all(|b| b.is_ascii_digit()) {
+        return match constraint.parse::<usize>() {
+            Ok(index) if index < outputs.len() => Ok(Kind::Matching(index)),
+            _ => Err(format!(
+                "matching constraint `{constraint}` does not refer to an output operand"
+            )),
+        };
+    }
+    if constraint.starts_with('@') {
+        return Err(format!(
+            "flag output constraint `{constraint}` has no `asm!` equivalent; \
+             use a `set` instruction such as `setc` instead"
+        ));
+    }
+    let letters: Vec<char> = constraint
+        .chars()
+        .filter(|c| !matches!(c, '&' | '%' | '*' | '?' | '!' | ',' | ' '))
+        .collect();
+    if let [letter] = letters[..] {
+        if let Some(index) = REGISTERS.iter().position(|(c, ..)| *c == letter) {
+            return Ok(Kind::Register(index));
+        }
+        match letter {
+            'b' => {
+                return Err(
+                    "`%rbx` is reserved by the compiler; use `r` and move the value \
+                     in and out of `%rbx` in the template"
+                        .to_string(),
+                );
+            }
+            'A' => {
+                return Err("`A` (the `%edx:%eax` pair) has no `asm!` equivalent; \
+                     use separate `a` and `d` operands"
+                    .to_string());
+            }
+            _ => {}
+        }
+    }
+    let has = |set: &[char]| letters.iter().any(|c| set.contains(c));
+    if has(&['r', 'g', 'R', 'l']) {
+        Ok(Kind::Class("reg"))
+    } else if has(&['q', 'Q']) {
+        Ok(Kind::Class("reg_abcd"))
+    } else if has(&['x', 'v']) {
+        Ok(Kind::Class("xmm_reg"))
+    } else if has(&['k']) {
+        Ok(Kind::Class("kreg"))
+    } else if has(&['m', 'o', 'V']) {
+        Ok(Kind::Memory)
+    } else if has(&[
+        'i', 'n', 's', 'e', 'E', 'F', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'Z',
+    ]) {
+        Ok(Kind::Immediate)
+    } else if has(&['f', 't', 'u', 'y']) {
+        Err(format!(
+            "`{constraint}` selects x87 or MMX registers, which `asm!` only supports as clobbers"
+        ))
+    } else {
+        Err(format!("unsupported constraint `{constraint}`"))
+    }
+}
+
+/// Rewrites the operand references, `%%` and the dialect alternatives of an
+/// extended template, and splits it into lines. The positional operands that
+/// are referred to as bytes by the suffix of an instruction are added to
+/// `bytes`.
+fn lower_template(
+    template: &str,
+    refs: &[Ref],
+    names: &[Option<String>],
+    bytes: &mut Vec<usize>,
+) -> Result<Vec<String>, String> {
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    // The alternative of `{att|intel}` being read.
+    let mut dialect: Option<usize> = None;
+    while let Some(c) = chars.next() {
+        match (c, dialect) {
+            ('{', None) => dialect = Some(0),
+            ('{', Some(_)) => return Err("nested `{` in a dialect alternative".to_string()),
+            ('|', Some(alternative)) => dialect = Some(alternative + 1),
+            ('}', Some(_)) => dialect = None,
+            (_, Some(1..)) => {}
+            ('}', None) => text.push_str("}}"),
+            ('%', _) => reference(&mut chars, &mut text, refs, names, bytes)?,
+            _ => text.push(c),
+        }
+    }
+    if dialect.is_some() {
+        return Err("unterminated `{` dialect alternative".to_string());
+    }
+    Ok(template_lines(&text))
+}
+
+/// The non-blank lines of a template, trimmed.
+fn template_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Lowers the `%` escape or operand reference that follows a `%`.
+fn reference(
+    chars: &mut Peekable<Chars<'_>>,
+    text: &mut String,
+    refs: &[Ref],
+    names: &[Option<String>],
+    bytes: &mut Vec<usize>,
+) -> Result<(), String> {
+    let escaped = match chars.peek() {
+        Some('%') => "%",
+        Some('{') => "{{",
+        Some('}') => "}}",
+        Some('|') => "|",
+        Some('=') => {
+            return Err(
+                "`%=` has no `asm!` equivalent; use numeric local labels such as `1:` and `1b`"
+                    .to_string(),
+            );
+        }
+        Some(_) => "",
+        None => return Err("`%` at the end of the template; write `%%` for a `%`".to_string()),
+    };
+    if !escaped.is_empty() {
+        chars.next();
+        text.push_str(escaped);
+        return Ok(());
+    }
+
+    let modifier = chars.next_if(char::is_ascii_alphabetic);
+    let written = |rest: &str| format!("%{}{rest}", modifier.map(String::from).unwrap_or_default());
+    let index = if chars.next_if_eq(&'[').is_some() {
+        let name: String = chars.by_ref().take_while(|c| *c != ']').collect();
+        names
+            .iter()
+            .position(|n| n.as_deref() == Some(name.as_str()))
+            .ok_or_else(|| {
+                format!(
+                    "no operand is named `{name}` in `{}`",
+                    written(&format!("[{name}]"))
+                )
+            })?
+    } else {
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        if digits.is_empty() {
+            return Err(format!(
+                "expected an operand number or `[name]` after `{}`; \
+                 write `%%` for a register such as `%%eax`",
+                written("")
+            ));
+        }
+        let index: usize = digits
+            .parse()
+            .map_err(|_| format!("invalid operand `{}`", written(&digits)))?;
+        if index >= refs.len() {
+            return Err(format!(
+                "`{}` refers to operand {index}, but there are only {}",
+                written(&digits),
+                refs.len()
+            ));
+        }
+        index
+    };
+
+    let unsupported = || {
+        format!(
+            "unsupported operand modifier in `{}`",
+            written(&index.to_string())
+        )
+    };
+    // GCC sizes general-purpose registers by the type of their operand, which
+    // the suffix of the instruction stands in for.
+    let explicit = modifier.is_some();
+    let modifier = match (refs[index], modifier) {
+        (Ref::Class(_, "reg" | "reg_abcd") | Ref::Register(_), None) => {
+            operand_size(text).map(|size| match size {
+                Size::Byte => 'b',
+                Size::Word => 'w',
+                Size::Long => 'k',
+                Size::Quad => 'q',
+            })
+        }
+        _ => modifier,
+    };
+    if let (Ref::Class(position, _), Some('b')) = (refs[index], modifier)
+        && !explicit
+    {
+        bytes.push(position);
+    }
+    let lowered = match (refs[index], modifier) {
+        (Ref::Class(index, _), None | Some('c' | 'P' | 'p'))
+        | (Ref::Class(index, "reg_byte"), Some('b')) => format!("{{{index}}}"),
+        (Ref::Class(index, _), Some('a')) => format!("({{{index}}})"),
+        (Ref::Class(index, class), Some(modifier)) => {
+            let rust = match (class, modifier) {
+                ("reg" | "reg_abcd", 'b') => "l",
+                ("reg_abcd", 'h') => "h",
+                ("reg" | "reg_abcd", 'w') | ("xmm_reg", 'x') => "x",
+                ("reg" | "reg_abcd", 'k') => "e",
+                ("reg" | "reg_abcd", 'q') => "r",
+                ("xmm_reg", 't') => "y",
+                ("xmm_reg", 'g') => "z",
+                _ => return Err(unsupported()),
+            };
+            format!("{{{index}:{rust}}}")
+        }
+        (Ref::Memory(index), None) => format!("({{{index}}})"),
+        (Ref::Immediate(index), None) => format!("${{{index}}}"),
+        (Ref::Immediate(index), Some('c' | 'P' | 'p')) => format!("{{{index}}}"),
+        (Ref::Register(register), modifier) => {
+            let (_, sized, high) = REGISTERS[register];
+            let name = match modifier {
+                Some('b') => sized[0],
+                Some('w') => sized[1],
+                Some('k') => sized[2],
+                None | Some('q' | 'a') => sized[3],
+                Some('h') => high.ok_or_else(unsupported)?,
+                _ => return Err(unsupported()),
+            };
+            match modifier {
+                Some('a') => format!("(%{name})"),
+                _ => format!("%{name}"),
+            }
+        }
+        _ => return Err(unsupported()),
+    };
+    text.push_str(&lowered);
+    Ok(())
+}
+
+/// The size of the next operand of the statement `text` ends with, from the
+/// suffix of its mnemonic.
+fn operand_size(text: &str) -> Option<Size> {
+    let statement = text.rsplit(['\n', ';']).next().unwrap_or_default();
+    let mut rest = statement.trim_start();
+    let mnemonic = loop {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        rest = tail.trim_start();
+        if word.is_empty()
+            || !(word.ends_with(':') || PREFIXES.contains(&word.to_ascii_lowercase().as_str()))
+        {
+            break word;
+        }
+    };
+    let mut depth = 0_usize;
+    let mut commas = 0;
+    for c in rest.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => commas += 1,
+            _ => {}
+        }
+    }
+    let instruction = Instruction {
+        prefixes: Vec::new(),
+        mnemonic: mnemonic.to_string(),
+        operands: Vec::new(),
+    };
+    // `setc %0` and the other conditional sets only take a byte.
+    if isa::lookup(mnemonic).is_some_and(|entry| entry.name.starts_with("set")) {
+        return Some(Size::Byte);
+    }
+    let (source, destination) = instruction.operand_sizes()?;
+    Some(if commas == 0 { source } else { destination })
+}
+
+/// Parses the text of a GCC `asm` statement, such as
+/// `asm volatile ("incl %0" : "+r" (x) :: "cc");`. The `asm` keyword, its
+/// qualifiers and the parentheses around the arguments may be left out.
+pub(crate) fn parse_statement(text: &str) -> Result<GccAsm<String>, GccError> {
+    let statement = |message: &str| GccError::new(GccPart::Statement, message);
+    let mut body = text.trim().trim_end_matches(';').trim_end();
+    let (word, rest) = split_word(body);
+    if matches!(word, "asm" | "__asm" | "__asm__") {
+        let mut rest = rest;
+        loop {
+            let (word, tail) = split_word(rest);
+            match word {
+                "volatile" | "__volatile" | "__volatile__" | "inline" | "__inline"
+                | "__inline__" => rest = tail,
+                "goto" => return Err(statement("`asm goto` is not supported")),
+                _ => break,
+            }
+        }
+        body = rest
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| statement("expected `(...)` after `asm`"))?;
+    }
+
+    let sections = split_top_level(body, ':');
+    if sections.len() > 4 {
+        return Err(statement("expected at most three `:`"));
+    }
+    let template =
+        c_strings(sections[0]).map_err(|message| GccError::new(GccPart::Template, message))?;
+    let operands = |section: usize, part: fn(usize) -> GccPart| {
+        list(sections.get(section).copied())
+            .enumerate()
+            .map(|(index, text)| {
+                parse_operand(text).map_err(|message| GccError::new(part(index), message))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let outputs = operands(1, GccPart::Output)?;
+    let inputs = operands(2, GccPart::Input)?;
+    let clobbers = list(sections.get(3).copied())
+        .enumerate()
+        .map(|(index, text)| {
+            c_strings(text).map_err(|message| GccError::new(GccPart::Clobber(index), message))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(GccAsm {
+        template,
+        basic: sections.len() == 1,
+        outputs,
+        inputs,
+        clobbers,
+    })
+}
+
+/// The leading identifier of `text`, and the trimmed rest.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    (&text[..end], text[end..].trim_start())
+}
+
+/// The comma-separated items of an operand or clobber list.
+fn list(section: Option<&str>) -> impl Iterator<Item = &str> {
+    section
+        .filter(|section| !section.trim().is_empty())
+        .map(|section| split_top_level(section, ','))
+        .unwrap_or_default()
+        .into_iter()
+}
+
+/// Splits C text at `separator`, outside of literals and brackets.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut escaped) = (0_usize, None, false);
+    let mut start = 0;
+    for (offset, c) in text.char_indices() {
+        if let Some(open) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == open => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ if c == separator && depth == 0 => {
+                parts.push(&text[start..offset]);
+                start = offset + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Parses `[name] "constraint" (expression)`.
+fn parse_operand(text: &str) -> Result<GccOperand<String>, String> {
+    let mut rest = text.trim();
+    let name = match rest.strip_prefix('[') {
+        Some(tail) => {
+            let (name, tail) = tail.split_once(']').ok_or("unmatched `[`")?;
+            rest = tail.trim_start();
+            Some(name.trim().to_string())
+        }
+        None => None,
+    };
+    let open = rest.find('(').unwrap_or(rest.len());
+    let constraint = c_strings(&rest[..open])?;
+    let expr = rest[open..]
+        .strip_prefix('(')
+        .and_then(|expr| expr.strip_suffix(')'))
+        .ok_or_else(|| format!("expected `(expression)` after `\"{constraint}\"`"))?;
+    Ok(GccOperand {
+        name,
+        constraint,
+        expr: expr.trim().to_string(),
+    })
+}
+
+/// Concatenates and unescapes adjacent C string literals.
+fn c_strings(text: &str) -> Result<String, String> {
+    let mut value = String::new();
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return Err("expected a string literal".to_string());
+    }
+    while !rest.is_empty() {
+        let mut chars = rest
+            .strip_prefix('"')
+            .ok_or_else(|| format!("expected a string literal, found `{rest}`"))?
+            .char_indices();
+        let end = loop {
+            let Some((offset, c)) = chars.next() else {
+                return Err("unterminated string literal".to_string());
+            };
+            match c {
+                '"' => break offset,
+                '\\' => value.push(match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((_, '0')) => '\0',
+                    Some((_, c @ ('\\' | '"' | '\'' | '?'))) => c,
+                    Some((_, c)) => return Err(format!("unsupported escape sequence `\\{c}`")),
+                    None => return Err("unterminated string literal".to_string()),
+                }),
+                _ => value.push(c),
+            }
+        };
+        rest = rest[end + 2..].trim_start();
+    }
+    Ok(value)
+}
+
+impl<E: fmt::Display> fmt::Display for AsmAtt<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "asm_att!(")?;
+        for template in &self.templates {
+            writeln!(f, "    {template:?},")?;
+        }
+        for operand in &self.operands {
+            writeln!(f, "    {operand},")?;
+        }
+        if !self.options.is_empty() {
+            writeln!(f, "    options({}),", self.options.join(", "))?;
+        }
+        write!(f, ");")
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for AsmOperand<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmOperand::In(reg, value) => write!(f, "in({reg}) {value}"),
+            AsmOperand::Out { reg, late, place } => {
+                let keyword = if *late { "lateout" } else { "out" };
+                match place {
+                    Some(place) => write!(f, "{keyword}({reg}) {place}"),
+                    None => write!(f, "{keyword}({reg}) _"),
+                }
+            }
+            AsmOperand::InOut { reg, value, place } => {
+                write!(f, "inout({reg}) {value}")?;
+                match place {
+                    Some(place) => write!(f, " => {place}"),
+                    None => Ok(()),
+                }
+            }
+            AsmOperand::Const(value) => write!(f, "const {value}"),
+        }
+    }
+}
+
+impl fmt::Display for RegSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegSpec::Class(class) => f.write_str(class),
+            RegSpec::Explicit(register) => write!(f, "\"{register}\""),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Value<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Expr(expr) => write!(f, "{expr}"),
+            Value::Address(expr) => write!(f, "&raw const {expr}"),
+            Value::AddressMut(expr) => write!(f, "&raw mut {expr}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower(statement: &str) -> Result<String, String> {
+        parse_statement(statement)
+            .and_then(GccAsm::lower)
+            .map(|lowered| lowered.to_string())
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn lowers_operands() {
+        assert_eq!(
+            lower(
+                r#"__asm__ volatile ("movl %1, %0\n\taddl $1, %0" : "=r" (out) : "r" (in) : "cc");"#
+            )
+            .unwrap(),
+            "asm_att!(\n    \"movl {1:e}, {0:e}\",\n    \"addl $1, {0:e}\",\n    \
+             lateout(reg) out,\n    in(reg) in,\n    options(nomem),\n);"
+        );
+        assert_eq!(
+            lower(r#""xaddl %0, %1" : "+r" (v), "+m" (*p) :: "memory""#).unwrap(),
+            "asm_att!(\n    \"xaddl {0:e}, ({1})\",\n    inout(reg) v,\n    \
+             in(reg) &raw mut *p,\n);"
+        );
+        assert_eq!(
+            lower(r#""mull %3" : "=a" (lo), "=&d" (hi) : "0" (a), "rm" (b)"#).unwrap(),
+            "asm_att!(\n    \"mull {0:e}\",\n    in(reg) b,\n    \
+             inout(\"eax\") a => lo,\n    out(\"edx\") hi,\n    options(nomem),\n);"
+        );
+        assert_eq!(
+            lower(r#""addl %[n], %[x]" : [x] "+r" (x) : [n] "i" (4), "m" (y)"#).unwrap(),
+            "asm_att!(\n    \"addl ${1}, {0:e}\",\n    inout(reg) x,\n    const 4,\n    \
+             in(reg) &raw const y,\n    options(readonly),\n);"
+        );
+        assert_eq!(
+            lower(r#""incb %0" : "+r" (z)"#).unwrap(),
+            "asm_att!(\n    \"incb {0}\",\n    inout(reg_byte) z,\n    options(nomem),\n);"
+        );
+        assert_eq!(
+            lower(r#""btl %2, %1; setc %0" : "=q" (flag) : "r" (x), "r" (n)"#).unwrap(),
+            "asm_att!(\n    \"btl {2:e}, {1:e}; setc {0}\",\n    \
+             lateout(reg_byte) flag,\n    in(reg) x,\n    in(reg) n,\n    options(nomem),\n);"
+        );
+    }
+
+    #[test]
+    fn lowers_templates() {
+        let template = |statement: &str| {
+            parse_statement(statement)
+                .and_then(GccAsm::lower)
+                .map(|lowered| lowered.templates.join("\n"))
+                .map_err(|error| error.to_string())
+        };
+        assert_eq!(
+            template(r#""movl %%eax, %0; movb %b1, %h1" : "=r" (x) : "Q" (y)"#).unwrap(),
+            "movl %eax, {0:e}; movb {1:l}, {1:h}"
+        );
+        assert_eq!(
+            template(r#""movzbl %b1, %0\n\tmovq %1, %%rdx\n\tmov %k1, %%ecx" : "=a" (x) : "c" (y) : "rdx""#)
+                .unwrap(),
+            "movzbl %cl, %eax\nmovq %rcx, %rdx\nmov %ecx, %ecx"
+        );
+        assert_eq!(
+            template(r#""{movl|mov} %0, %%eax" :: "r" (x)"#).unwrap(),
+            "movl {0:e}, %eax"
+        );
+        assert_eq!(
+            template(r#""vaddps %1, %2, %0 %{%%k1%}" : "=v" (x) : "v" (y), "v" (z)"#).unwrap(),
+            "vaddps {1}, {2}, {0} {{%k1}}"
+        );
+        assert_eq!(template(r#""movl %eax, %ebx""#).unwrap(), "movl %eax, %ebx");
+    }
+
+    #[test]
+    fn rejects_unsupported_statements() {
+        assert_eq!(
+            lower(r#""movl %eax, %0" : "=r" (x)"#).unwrap_err(),
+            "expected an operand number or `[name]` after `%e`; \
+             write `%%` for a register such as `%%eax`"
+        );
+        assert_eq!(
+            lower(r#""incl %1" : "+r" (x)"#).unwrap_err(),
+            "`%1` refers to operand 1, but there are only 1"
+        );
+        assert_eq!(
+            lower(r#""cpuid" : "=a" (a), "=b" (b) : "0" (leaf)"#).unwrap_err(),
+            "`%rbx` is reserved by the compiler; use `r` and move the value \
+             in and out of `%rbx` in the template"
+        );
+        assert_eq!(
+            lower(r#""xorl %%ebx, %%ebx" ::: "ebx""#).unwrap_err(),
+            "`%ebx` is reserved by the compiler and cannot be clobbered; \
+             save and restore it in the template instead"
+        );
+        let error = parse_statement(r#""" : "r" (x)"#)
+            .and_then(GccAsm::lower)
+            .unwrap_err();
+        assert_eq!(error.part(), GccPart::Output(0));
+        assert_eq!(
+            error.message(),
+            "output constraint `r` must start with `=` or `+`"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Migration of the `core::arch` assembly macro calls of Rust source to the
+//! macros of `asm_att`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::syntax::{PlaceholderArg, Template};
+
+use super::source::{self, MacroCall, is_group, macro_calls, operand, span, string_literal_for};
+use super::{Edit, OperandClass, Skipped, SourceError, adapt_placeholders};
+
+/// The macros of `core::arch` and their `asm_att` counterparts.
+const MACROS: &[(&str, &str)] = &[
+    ("asm", "asm_att::asm_att"),
+    ("global_asm", "asm_att::global_asm_att"),
+    ("naked_asm", "asm_att::naked_asm_att"),
+];
+
+/// How [`migrate`] treats the calls it finds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrateOptions {
+    /// Whether to convert calls written in Intel syntax to AT&T syntax,
+    /// instead of leaving them alone.
+    pub intel: bool,
+}
+
+/// The outcome of [`migrate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Migration {
+    /// The edits, in order and without overlaps.
+    pub edits: Vec<Edit>,
+    /// How many calls were migrated.
+    pub migrated: usize,
+    /// The calls left alone.
+    pub skipped: Vec<Skipped>,
+}
+
+/// Finds the `asm!`, `global_asm!` and `naked_asm!` calls of a Rust source
+/// file and rewrites them to `asm_att::asm_att!`, `asm_att::global_asm_att!`
+/// and `asm_att::naked_asm_att!`.
+///
+/// Calls with `options(att_syntax)` lose that option. Calls in Intel syntax
+/// are skipped, unless [`MigrateOptions::intel`] is set: their templates are
+/// then converted to AT&T syntax, with the placeholders of `const`, `sym` and
+/// register operands adapted as by [`adapt_placeholders`]. Only macros named
+/// directly or through `core::arch` or `std::arch` are considered, and code in
+/// comments is left alone.
+///
+/// ```
+/// use asm_att_core::convert::{MigrateOptions, migrate};
+///
+/// let source = r#"unsafe { core::arch::asm!("mov eax, {n}", n = const 1, out("eax") _) }"#;
+/// let migration = migrate(source, MigrateOptions { intel: true }).unwrap();
+/// assert_eq!(
+///     migration.apply(source),
+///     r#"unsafe { asm_att::asm_att!("mov ${n}, %eax", n = const 1, out("eax") _) }"#
+/// );
+/// ```
+pub fn migrate(source: &str, options: MigrateOptions) -> Result<Migration, SourceError> {
+    let tokens = source::tokenize(source)?;
+    let names: Vec<&str> = MACROS.iter().map(|(name, _)| *name).collect();
+    let mut migration = Migration::default();
+    for call in macro_calls(source, &tokens, &names) {
+        if !is_core_arch(&source[call.path.clone()]) {
+            continue;
+        }
+        match migrate_call(source, &call, options) {
+            Ok(edits) => {
+                migration.edits.extend(edits);
+                migration.migrated += 1;
+            }
+            Err(reason) => migration.skipped.push(Skipped {
+                offset: call.path.start,
+                reason,
+            }),
+        }
+    }
+    Ok(migration)
+}
+
+impl Migration {
+    /// The source with the edits applied.
+    pub fn apply(&self, source: &str) -> String {
+        super::apply_edits(source, &self.edits)
+    }
+}
+
+/// Whether a macro path names a macro of `core::arch`, possibly imported.
+fn is_core_arch(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_start_matches("::").split("::").collect();
+    match segments[..] {
+        [_] => true,
+        [.., "arch", _] => segments[..segments.len() - 2]
+            .iter()
+            .all(|segment| matches!(*segment, "core" | "std")),
+        _ => false,
+    }
+}
+
+fn migrate_call(
+    source: &str,
+    call: &MacroCall<'_>,
+    options: MigrateOptions,
+) -> Result<Vec<Edit>, String> {
+    let name = call.name.text(source);
+    let replacement = MACROS
+        .iter()
+        .find(|(macro_name, _)| *macro_name == name)
+        .map(|(_, replacement)| *replacement)
+        .expect("found by name");
+    let mut edits = vec![Edit {
+        range: call.path.clone(),
+        replacement: replacement.to_string(),
+    }];
+
+    let mut att = false;
+    for (index, arg) in call.args.iter().enumerate() {
+        if !is_group(source, arg, "options") {
+            continue;
+        }
+        let Some(position) = arg
+            .iter()
+            .position(|token| token.text(source) == "att_syntax")
+        else {
+            continue;
+        };
+        att = true;
+        let inner = &arg[2..arg.len() - 1];
+        let range = if inner.len() <= 2 {
+            // `options(att_syntax)` goes, with the comma before it.
+            call.args[index - 1].last().expect("template").end..span(arg).end
+        } else if position + 1 < arg.len() - 1 && arg[position + 1].is_punct(source, ',') {
+            arg[position].start..arg[position + 2].start
+        } else {
+            arg[position - 2].end..arg[position].end
+        };
+        edits.push(Edit {
+            range,
+            replacement: String::new(),
+        });
+    }
+
+    if !att {
+        if !options.intel {
+            return Err("uses Intel syntax".to_string());
+        }
+        edits.extend(convert_templates(source, call)?);
+    }
+    edits.sort_by_key(|edit| edit.range.start);
+    Ok(edits)
+}
+
+/// Converts the Intel template literals of a call to AT&T syntax.
+fn convert_templates(source: &str, call: &MacroCall<'_>) -> Result<Vec<Edit>, String> {
+    let (literals, values) = call.templates(source)?;
+    let texts: Vec<&str> = values.iter().map(String::as_str).collect();
+    let mut template = Template::parse_intel(&texts).map_err(|error| {
+        format!(
+            "cannot convert line {} of template {}: {}",
+            error.location().line + 1,
+            error.location().template + 1,
+            error.message()
+        )
+    })?;
+
+    let operands: Vec<(Option<&str>, OperandClass)> = call.args[literals.len()..]
+        .iter()
+        .filter_map(|arg| operand(source, arg))
+        .collect();
+    adapt_placeholders(&mut template, |placeholder| {
+        let operand = match &placeholder.arg {
+            PlaceholderArg::Next => None,
+            PlaceholderArg::Index(index) => operands.get(*index),
+            PlaceholderArg::Name(name) => operands
+                .iter()
+                .find(|(operand, _)| *operand == Some(name.as_str())),
+        }?;
+        Some(operand.1)
+    });
+
+    Ok(literals
+        .iter()
+        .zip(&values)
+        .enumerate()
+        .map(|(index, (literal, original))| {
+            let lines: Vec<String> = template
+                .statements
+                .iter()
+                .filter(|(location, _)| location.template == index)
+                .map(|(_, statement)| statement.to_string())
+                .collect();
+            Edit {
+                range: literal.start..literal.end,
+                replacement: string_literal_for(&reindent(original, &lines), literal.text(source)),
+            }
+        })
+        .collect())
+}
+
+/// Lays out converted lines like the template they come from: on one line,
+/// or indented and between the same line breaks.
+fn reindent(original: &str, lines: &[String]) -> String {
+    if !original.contains('\n') {
+        return lines.join("\n");
+    }
+    let indent: String = original
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).collect())
+        .unwrap_or_default();
+    let mut text = String::new();
+    if original.trim_start_matches([' ', '\t']).starts_with('\n') {
+        text.push('\n');
+    }
+    let body: Vec<String> = lines.iter().map(|line| format!("{indent}{line}")).collect();
+    text.push_str(&body.join("\n"));
+    if let Some((_, last)) = original.rsplit_once('\n')
+        && last.trim().is_empty()
+    {
+        text.push('\n');
+        text.push_str(last);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated(source: &str, intel: bool) -> String {
+        migrate(source, MigrateOptions { intel })
+            .unwrap()
+            .apply(source)
+    }
+
+    #[test]
+    fn migrates_att_calls() {
+        assert_eq!(
+            migrated(
+                r#"asm!("movl $1, %eax", out("eax") _, options(att_syntax, nostack));"#,
+                false
+            ),
+            r#"asm_att::asm_att!("movl $1, %eax", out("eax") _, options(nostack));"#
+        );
+        assert_eq!(
+            migrated(
+                "std::arch::global_asm!(\n    \".text\",\n    options(att_syntax),\n);",
+                false
+            ),
+            "asm_att::global_asm_att!(\n    \".text\",\n);"
+        );
+        assert_eq!(
+            migrated(r#"naked_asm!("ret", options(raw, att_syntax))"#, false),
+            r#"asm_att::naked_asm_att!("ret", options(raw))"#
+        );
+    }
+
+    #[test]
+    fn converts_intel_calls() {
+        let source = r#"
+            asm!("mov {x}, qword ptr [{p} + {n}]", "call {f}", p = in(reg) p, x = out(reg) x, n = const 8, f = in(reg) f);
+            global_asm!(r"
+                .global f
+                f:
+                    mov eax, 1
+                    ret
+            ");
+        "#;
+        let migration = migrate(source, MigrateOptions::default()).unwrap();
+        assert!(migration.edits.is_empty());
+        assert_eq!(migration.skipped.len(), 2);
+        assert_eq!(migration.skipped[0].reason, "uses Intel syntax");
+
+        let expected = r#"
+            asm_att::asm_att!("movq {n}({p}), {x}", "call *{f}", p = in(reg) p, x = out(reg) x, n = const 8, f = in(reg) f);
+            asm_att::global_asm_att!(r"
+                .global f
+                f:
+                mov $1, %eax
+                ret
+            ");
+        "#;
+        assert_eq!(migrated(source, true), expected);
+    }
+
+    #[test]
+    fn skips_other_calls() {
+        let source = r#"
+            my_crate::asm!("mov eax, 1");
+            asm!(concat!("mov eax, ", "1"));
+            asm!("mov eax, [rax*3]");
+        "#;
+        let migration = migrate(source, MigrateOptions { intel: true }).unwrap();
+        assert!(migration.edits.is_empty());
+        let reasons: Vec<&str> = migration
+            .skipped
+            .iter()
+            .map(|skipped| skipped.reason.as_str())
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "has a template that is not a string literal",
+                "cannot convert line 1 of template 1: invalid scale `3` in `[rax*3]`; expected 1, 2, 4 or 8",
+            ]
+        );
+    }
+}
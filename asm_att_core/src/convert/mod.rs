@@ -0,0 +1,227 @@
+//! Conversion of templates between Intel and AT&T syntax, of GCC extended
+//! `asm` statements to `asm_att!`, and of the `core::arch` assembly macro
+//! calls of Rust source files to `asm_att`, and formatting of the templates
+//! of `asm_att` macro calls.
+
+mod format;
+mod gcc;
+mod migrate;
+pub(crate) mod source;
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::ops::Range;
+
+use crate::syntax::{MemoryOperand, Operand, ParseError, Placeholder, Statement, Template};
+
+pub use format::{Formatting, format_templates};
+pub use gcc::{AsmAtt, AsmOperand, GccAsm, GccError, GccOperand, GccPart, RegSpec, Value};
+pub use migrate::{MigrateOptions, Migration, migrate};
+
+/// Rewrites a template written in Intel syntax into AT&T syntax, for use in
+/// `asm_att!`.
+///
+/// Operands are reversed, registers and immediates get their `%` and `$`
+/// prefixes, `[base + index*scale + disp]` becomes `disp(base,index,scale)`
+/// and size keywords such as `dword ptr` become mnemonic suffixes. Comments
+/// and blank lines are dropped.
+///
+/// Placeholders are taken for registers: see [`Template::parse_intel`].
+///
+/// ```
+/// use asm_att_core::convert::intel_to_att;
+///
+/// let att = intel_to_att("mov eax, dword ptr [rdi + rsi*4]\nadd eax, 1").unwrap();
+/// assert_eq!(att, "movl (%rdi,%rsi,4), %eax\nadd $1, %eax");
+/// ```
+pub fn intel_to_att(template: &str) -> Result<String, ParseError> {
+    Template::parse_intel(&[template]).map(|template| template.to_string())
+}
+
+/// Rewrites a template written in AT&T syntax into Intel syntax, as accepted
+/// by `core::arch::asm!` without `options(att_syntax)`.
+///
+/// Operands are reversed, size suffixes become size keywords such as
+/// `dword ptr` on memory operands, and `disp(base,index,scale)` becomes
+/// `[base + index*scale + disp]`. Comments and blank lines are dropped.
+///
+/// ```
+/// use asm_att_core::convert::att_to_intel;
+///
+/// let intel = att_to_intel("movl (%rdi,%rsi,4), %eax\naddl ${n}, %eax").unwrap();
+/// assert_eq!(intel, "mov eax, dword ptr [rdi + rsi*4]\nadd eax, {n}");
+/// ```
+pub fn att_to_intel(template: &str) -> Result<String, ParseError> {
+    Template::parse(&[template]).map(|template| template.intel().to_string())
+}
+
+/// What a placeholder stands for, as far as [`adapt_placeholders`] is
+/// concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandClass {
+    /// A register operand such as `in(reg) x`.
+    Register,
+    /// A `const` operand.
+    Const,
+    /// A `sym` or `label` operand.
+    Symbol,
+}
+
+/// Rewrites the placeholders of a template parsed by
+/// [`Template::parse_intel`] according to the operands they refer to, which
+/// `class` gives, or `None` if it is unknown.
+///
+/// The placeholders of `const` operands become immediates, those of `const`
+/// and `sym` operands in the base or index of a memory reference move to its
+/// displacement, and register operands that are called or jumped through
+/// become indirect branches.
+pub fn adapt_placeholders(
+    template: &mut Template,
+    class: impl Fn(&Placeholder) -> Option<OperandClass>,
+) {
+    for (_, statement) in &mut template.statements {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let branch = instruction.is_branch();
+        for operand in &mut instruction.operands {
+            if let Operand::Memory(memory) = operand {
+                displace(memory, &class);
+                continue;
+            }
+            let Operand::Placeholder(placeholder) = &*operand else {
+                continue;
+            };
+            *operand = match class(placeholder) {
+                Some(OperandClass::Const) if !branch => Operand::Immediate(placeholder.to_string()),
+                Some(OperandClass::Register) if branch => {
+                    Operand::Indirect(Box::new(operand.clone()))
+                }
+                _ => continue,
+            };
+        }
+    }
+}
+
+/// Moves the placeholders of `const` and `sym` operands out of the base and
+/// index of a memory reference, into its displacement.
+fn displace(memory: &mut MemoryOperand, class: &impl Fn(&Placeholder) -> Option<OperandClass>) {
+    for register in [&mut memory.base, &mut memory.index] {
+        let Some(Operand::Placeholder(placeholder)) = register.as_deref() else {
+            continue;
+        };
+        if !matches!(
+            class(placeholder),
+            Some(OperandClass::Const | OperandClass::Symbol)
+        ) {
+            continue;
+        }
+        if !memory.displacement.is_empty() {
+            memory.displacement.push('+');
+        }
+        memory.displacement.push_str(&placeholder.to_string());
+        *register = None;
+    }
+    if memory.base.is_none() && memory.scale.is_none() {
+        memory.base = memory.index.take();
+    }
+}
+
+/// Rewrites a GCC extended `asm` statement written in AT&T syntax into an
+/// `asm_att!` invocation, as done by `gcc_asm_att!`.
+///
+/// The statement may keep its `asm volatile (...);` around the template and
+/// the operand lists. The C expressions of the operands are copied as they
+/// are, to be adapted by hand. See [`GccAsm::lower`] for the lowering.
+///
+/// ```
+/// use asm_att_core::convert::gcc_to_asm_att;
+///
+/// let rust = gcc_to_asm_att(
+///     r#"__asm__ volatile ("addl %2, %0\n\tadcl $0, %%edx" : "+a" (lo), "+r" (hi) : "r" (n) : "cc", "edx");"#,
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     rust,
+///     r#"asm_att!(
+///     "addl {1:e}, %eax",
+///     "adcl $0, %edx",
+///     inout(reg) hi,
+///     in(reg) n,
+///     inout("eax") lo,
+///     out("edx") _,
+///     options(nomem),
+/// );"#
+/// );
+/// ```
+pub fn gcc_to_asm_att(statement: &str) -> Result<String, GccError> {
+    gcc::parse_statement(statement)?
+        .lower()
+        .map(|lowered| lowered.to_string())
+}
+
+/// A replacement of part of a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    /// The byte range replaced.
+    pub range: Range<usize>,
+    /// The text replacing it.
+    pub replacement: String,
+}
+
+/// Applies edits that are in order and do not overlap.
+pub fn apply_edits(source: &str, edits: &[Edit]) -> String {
+    let mut text = String::with_capacity(source.len());
+    let mut offset = 0;
+    for edit in edits {
+        text.push_str(&source[offset..edit.range.start]);
+        text.push_str(&edit.replacement);
+        offset = edit.range.end;
+    }
+    text.push_str(&source[offset..]);
+    text
+}
+
+/// A macro call left alone by [`migrate`] or [`format_templates`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skipped {
+    /// Where the call starts in the source.
+    pub offset: usize,
+    /// Why it was left alone.
+    pub reason: String,
+}
+
+/// Rust source that cannot be read, such as an unterminated string literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceError {
+    offset: usize,
+    message: String,
+}
+
+impl SourceError {
+    pub(crate) fn new(offset: usize, message: impl Into<String>) -> Self {
+        SourceError {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// Where the error occurred in the source.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// A description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl core::error::Error for SourceError {}
@@ -0,0 +1,516 @@
+//! A lexer for Rust source, just good enough to find macro calls and split
+//! their arguments.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::syntax::{Template, escape_decorators};
+
+use super::{OperandClass, SourceError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Ident,
+    /// A string literal, `"..."` or `r#"..."#`.
+    Str,
+    /// Any other literal: numbers, characters, byte and C strings.
+    Literal,
+    Lifetime,
+    Punct,
+    Open,
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub fn text(self, source: &str) -> &str {
+        &source[self.start..self.end]
+    }
+
+    pub fn is_punct(self, source: &str, punct: char) -> bool {
+        self.kind == TokenKind::Punct && self.text(source).starts_with(punct)
+    }
+}
+
+/// Splits Rust source into tokens, skipping whitespace and comments.
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Token>, SourceError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while let Some(c) = source[offset..].chars().next() {
+        let start = offset;
+        let rest = &source[offset..];
+        let (kind, end) = if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        } else if rest.starts_with("//") {
+            offset += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        } else if rest.starts_with("/*") {
+            offset += block_comment(rest)
+                .ok_or_else(|| SourceError::new(start, "unterminated block comment"))?;
+            continue;
+        } else if let Some((kind, len)) = string_literal(rest) {
+            let len = len.ok_or_else(|| SourceError::new(start, "unterminated string literal"))?;
+            (kind, start + len)
+        } else if c == '\'' {
+            quote(rest, start)?
+        } else if c.is_ascii_digit() {
+            let mut end = start + 1;
+            while end < bytes.len()
+                && (bytes[end].is_ascii_alphanumeric()
+                    || bytes[end] == b'_'
+                    || (bytes[end] == b'.' && bytes.get(end + 1).is_some_and(u8::is_ascii_digit)))
+            {
+                end += 1;
+            }
+            (TokenKind::Literal, end)
+        } else if is_ident_start(c) {
+            let prefix = if rest.starts_with("r#") { 2 } else { 0 };
+            (
+                TokenKind::Ident,
+                start + prefix + ident_len(&rest[prefix..]),
+            )
+        } else {
+            let kind = match c {
+                '(' | '[' | '{' => TokenKind::Open,
+                ')' | ']' | '}' => TokenKind::Close,
+                _ => TokenKind::Punct,
+            };
+            (kind, start + c.len_utf8())
+        };
+        tokens.push(Token { kind, start, end });
+        offset = end;
+    }
+    Ok(tokens)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn ident_len(text: &str) -> usize {
+    text.find(|c: char| !(c == '_' || c.is_alphanumeric()))
+        .unwrap_or(text.len())
+}
+
+/// The length of the nested block comment `text` starts with.
+fn block_comment(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut offset = 0;
+    while offset < text.len() {
+        let rest = &text[offset..];
+        if rest.starts_with("/*") {
+            depth += 1;
+            offset += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            offset += 2;
+            if depth == 0 {
+                return Some(offset);
+            }
+        } else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// The kind and length of the string literal `text` starts with, if it
+/// starts with one; the length is `None` if it is unterminated.
+fn string_literal(text: &str) -> Option<(TokenKind, Option<usize>)> {
+    let (kind, prefix) = ["br", "cr", "r", "b", "c", ""]
+        .into_iter()
+        .find_map(|prefix| {
+            let rest = text.strip_prefix(prefix)?;
+            let raw = prefix.ends_with('r');
+            let quoted =
+                rest.starts_with('"') || (raw && rest.trim_start_matches('#').starts_with('"'));
+            let kind = if matches!(prefix, "" | "r") {
+                TokenKind::Str
+            } else {
+                TokenKind::Literal
+            };
+            quoted.then_some((kind, prefix))
+        })?;
+    let rest = &text[prefix.len()..];
+    let len = if prefix.ends_with('r') {
+        let hashes = rest.len() - rest.trim_start_matches('#').len();
+        let body = &rest[hashes + 1..];
+        let mut closing = String::from("\"");
+        closing.extend(core::iter::repeat_n('#', hashes));
+        body.find(&closing)
+            .map(|end| prefix.len() + hashes + 1 + end + closing.len())
+    } else {
+        let mut escaped = false;
+        rest[1..]
+            .char_indices()
+            .find(|&(_, c)| {
+                let end = !escaped && c == '"';
+                escaped = !escaped && c == '\\';
+                end
+            })
+            .map(|(end, _)| prefix.len() + 1 + end + 1)
+    };
+    Some((kind, len))
+}
+
+/// A character literal or a lifetime, starting at the `'` of `text`.
+fn quote(text: &str, start: usize) -> Result<(TokenKind, usize), SourceError> {
+    let mut chars = text[1..].char_indices();
+    let unterminated = || SourceError::new(start, "unterminated character literal");
+    match chars.next() {
+        Some((_, '\\')) => {
+            chars.next();
+            let (end, _) = chars.find(|&(_, c)| c == '\'').ok_or_else(unterminated)?;
+            Ok((TokenKind::Literal, start + 1 + end + 1))
+        }
+        Some((_, c)) => match chars.next() {
+            Some((end, '\'')) => Ok((TokenKind::Literal, start + 1 + end + 1)),
+            _ if is_ident_start(c) => Ok((TokenKind::Lifetime, start + 1 + ident_len(&text[1..]))),
+            _ => Err(unterminated()),
+        },
+        None => Err(unterminated()),
+    }
+}
+
+/// A call of a macro found by [`macro_calls`].
+#[derive(Clone, Debug)]
+pub(crate) struct MacroCall<'t> {
+    /// The path of the macro, such as `core::arch::asm`, without the `!`.
+    pub path: Range<usize>,
+    /// The last segment of the path.
+    pub name: Token,
+    /// The delimiters around the arguments.
+    pub open: Token,
+    pub close: Token,
+    /// The tokens of each comma-separated argument, without the commas.
+    pub args: Vec<&'t [Token]>,
+}
+
+/// The calls of the macros whose last path segment is one of `names`.
+pub(crate) fn macro_calls<'t>(
+    source: &str,
+    tokens: &'t [Token],
+    names: &[&str],
+) -> Vec<MacroCall<'t>> {
+    let mut calls = Vec::new();
+    let mut index = 0;
+    while index + 2 < tokens.len() {
+        let name = tokens[index];
+        if !(name.kind == TokenKind::Ident
+            && names.contains(&name.text(source))
+            && tokens[index + 1].is_punct(source, '!')
+            && tokens[index + 2].kind == TokenKind::Open)
+        {
+            index += 1;
+            continue;
+        }
+        let mut path_start = index;
+        while path_start >= 2
+            && tokens[path_start - 1].is_punct(source, ':')
+            && tokens[path_start - 2].is_punct(source, ':')
+        {
+            path_start -= 2;
+            if path_start >= 1 && tokens[path_start - 1].kind == TokenKind::Ident {
+                path_start -= 1;
+            } else {
+                break;
+            }
+        }
+
+        let open = index + 2;
+        let mut depth = 0_usize;
+        let mut close = None;
+        let mut args = Vec::new();
+        let mut arg_start = open + 1;
+        for (offset, token) in tokens[open..].iter().enumerate() {
+            let position = open + offset;
+            match token.kind {
+                TokenKind::Open => depth += 1,
+                TokenKind::Close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(position);
+                        break;
+                    }
+                }
+                TokenKind::Punct if depth == 1 && token.is_punct(source, ',') => {
+                    args.push(&tokens[arg_start..position]);
+                    arg_start = position + 1;
+                }
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            break;
+        };
+        if arg_start < close {
+            args.push(&tokens[arg_start..close]);
+        }
+        calls.push(MacroCall {
+            path: tokens[path_start].start..name.end,
+            name,
+            open: tokens[open],
+            close: tokens[close],
+            args,
+        });
+        index = close + 1;
+    }
+    calls
+}
+
+impl MacroCall<'_> {
+    /// The string literals of the leading template arguments of an assembly
+    /// macro call, with their values.
+    pub fn templates(&self, source: &str) -> Result<(Vec<Token>, Vec<String>), String> {
+        let count = self
+            .args
+            .iter()
+            .position(|arg| {
+                operand(source, arg).is_some()
+                    || is_group(source, arg, "options")
+                    || is_group(source, arg, "clobber_abi")
+            })
+            .unwrap_or(self.args.len());
+        let mut literals = Vec::new();
+        for arg in &self.args[..count] {
+            match arg {
+                [literal] if literal.kind == TokenKind::Str => literals.push(*literal),
+                _ => return Err("has a template that is not a string literal".into()),
+            }
+        }
+        let values = literals
+            .iter()
+            .map(|literal| string_value(literal.text(source)))
+            .collect::<Option<Vec<String>>>()
+            .ok_or("has a template with an unsupported escape sequence")?;
+        Ok((literals, values))
+    }
+}
+
+/// An operand argument of an assembly macro call.
+#[derive(Clone, Debug)]
+pub(crate) struct OperandArg<'a> {
+    pub name: Option<&'a str>,
+    /// `in`, `out`, `lateout`, `inout`, `inlateout`, `const`, `sym` or
+    /// `label`.
+    pub keyword: &'a str,
+    /// The register of an explicit register operand such as `in("eax") x`.
+    pub register: Option<String>,
+}
+
+/// The name and class of an operand argument, or `None` if the argument is
+/// not an operand.
+pub(crate) fn operand<'a>(
+    source: &'a str,
+    arg: &[Token],
+) -> Option<(Option<&'a str>, OperandClass)> {
+    let operand = operand_arg(source, arg)?;
+    let class = match operand.keyword {
+        "const" => OperandClass::Const,
+        "sym" | "label" => OperandClass::Symbol,
+        _ => OperandClass::Register,
+    };
+    Some((operand.name, class))
+}
+
+/// The operand an argument is, or `None` if it is not an operand.
+pub(crate) fn operand_arg<'a>(source: &'a str, arg: &[Token]) -> Option<OperandArg<'a>> {
+    let (name, rest) = match arg {
+        [name, equals, rest @ ..]
+            if name.kind == TokenKind::Ident
+                && equals.is_punct(source, '=')
+                && rest.first().is_some_and(|next| {
+                    !next.is_punct(source, '=') && !next.is_punct(source, '>')
+                }) =>
+        {
+            (Some(name.text(source)), rest)
+        }
+        _ => (None, arg),
+    };
+    let keyword = rest.first()?.text(source);
+    let register = match rest {
+        [_, open, literal, close, ..]
+            if matches!(keyword, "in" | "out" | "lateout" | "inout" | "inlateout")
+                && open.kind == TokenKind::Open
+                && literal.kind == TokenKind::Str
+                && close.kind == TokenKind::Close =>
+        {
+            string_value(literal.text(source))
+        }
+        _ => None,
+    };
+    match keyword {
+        "in" | "out" | "lateout" | "inout" | "inlateout" | "const" | "sym" | "label" => {
+            Some(OperandArg {
+                name,
+                keyword,
+                register,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parses the template of an assembly macro call, given the values of its
+/// literals, escaping AVX-512 decorators written with single braces.
+pub(crate) fn parse_template(
+    source: &str,
+    call: &MacroCall<'_>,
+    values: &[String],
+) -> Result<Template, String> {
+    let names: Vec<&str> = call
+        .args
+        .iter()
+        .filter_map(|arg| operand(source, arg)?.0)
+        .collect();
+    let escaped: Vec<String> = values
+        .iter()
+        .map(|value| escape_decorators(value, &names).into_owned())
+        .collect();
+    let texts: Vec<&str> = escaped.iter().map(String::as_str).collect();
+    Template::parse(&texts).map_err(|error| {
+        alloc::format!(
+            "cannot parse line {} of template {}: {}",
+            error.location().line + 1,
+            error.location().template + 1,
+            error.message()
+        )
+    })
+}
+
+/// The offset of the text of a line of the value of a string literal in the
+/// source, or of the literal if the line breaks of its value are escapes.
+pub(crate) fn line_offset(source: &str, literal: Token, line: usize) -> usize {
+    let text = literal.text(source);
+    if line == 0 {
+        return literal.start;
+    }
+    text.match_indices('\n')
+        .nth(line - 1)
+        .map_or(literal.start, |(newline, _)| {
+            let rest = &text[newline + 1..];
+            literal.start + text.len() - rest.trim_start_matches([' ', '\t']).len()
+        })
+}
+
+/// Whether the argument is a group such as `options(...)`.
+pub(crate) fn is_group(source: &str, arg: &[Token], keyword: &str) -> bool {
+    matches!(arg, [first, open, ..] if first.text(source) == keyword && open.kind == TokenKind::Open)
+}
+
+/// The source text spanned by `tokens`.
+pub(crate) fn span(tokens: &[Token]) -> Range<usize> {
+    match tokens {
+        [] => 0..0,
+        [first, .., last] => first.start..last.end,
+        [only] => only.start..only.end,
+    }
+}
+
+/// The value of a string literal token, or `None` if it holds an escape that
+/// is not supported.
+pub(crate) fn string_value(literal: &str) -> Option<String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Some(String::from(&raw[hashes + 1..raw.len() - hashes - 1]));
+    }
+    let body = &literal[1..literal.len() - 1];
+    let mut value = String::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            '0' => value.push('\0'),
+            c @ ('\\' | '"' | '\'') => value.push(c),
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                value.push(char::from(u8::from_str_radix(&digits, 16).ok()?));
+            }
+            'u' => {
+                chars.next_if_eq(&'{')?;
+                let digits: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                value.push(char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?);
+            }
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// A string literal holding `value`, raw with the hashes of `like` if that is
+/// a raw literal that can hold it.
+pub(crate) fn string_literal_for(value: &str, like: &str) -> String {
+    if let Some(raw) = like.strip_prefix('r') {
+        let hashes = &raw[..raw.len() - raw.trim_start_matches('#').len()];
+        let mut closing = String::from("\"");
+        closing.push_str(hashes);
+        if !value.contains(&closing) {
+            return alloc::format!("r{hashes}\"{value}{closing}");
+        }
+    }
+    alloc::format!("{value:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_macro_calls() {
+        let source = r##"
+            // asm!("commented out");
+            let s = "asm!(\"in a string\")";
+            let r = r#"asm!("raw")"#;
+            fn f<'a>(c: char) -> bool { c == '(' || c == '\'' }
+            /* asm!( /* nested */ ) */
+            core::arch::asm!("nop", options(nomem, nostack));
+            global_asm! { r"ret" }
+        "##;
+        let tokens = tokenize(source).unwrap();
+        let calls = macro_calls(source, &tokens, &["asm", "global_asm"]);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(&source[calls[0].path.clone()], "core::arch::asm");
+        let args: Vec<&str> = calls[0]
+            .args
+            .iter()
+            .map(|tokens| &source[span(tokens)])
+            .collect();
+        assert_eq!(args, ["\"nop\"", "options(nomem, nostack)"]);
+        assert_eq!(&source[calls[1].path.clone()], "global_asm");
+        assert_eq!(calls[1].args.len(), 1);
+    }
+
+    #[test]
+    fn reads_string_literals() {
+        assert_eq!(
+            string_value(r#""a\n\"b\"\x41\u{263A}""#).unwrap(),
+            "a\n\"b\"A\u{263A}"
+        );
+        assert_eq!(string_value("\"a\\\n    b\"").unwrap(), "ab");
+        assert_eq!(string_value(r##"r#"a "b" c"#"##).unwrap(), r#"a "b" c"#);
+        assert_eq!(
+            string_literal_for("movl $1, %eax\n", r#"r"mov eax, 1""#),
+            "r\"movl $1, %eax\n\""
+        );
+        assert_eq!(
+            string_literal_for("say \"hi\"", r#"r"x""#),
+            r#""say \"hi\"""#
+        );
+    }
+}
@@ -0,0 +1,1603 @@
+//! A table of x86 and x86_64 instructions: their AT&T spellings, operand
+//! forms, implicit register reads and writes, the flags they read and write,
+//! the CPU features they need and the privilege level they run at.
+//!
+//! The table covers the general-purpose instructions, the system instructions
+//! a kernel uses, a few x87 ones, and the SSE to AVX-512 extensions most used
+//! in hand-written assembly. [`lookup`] finds an instruction by any of its
+//! AT&T spellings, with or without a size suffix, or by its Intel mnemonic.
+//! The effects of an [`Instruction`], such as the registers it writes, are
+//! derived from the table.
+//!
+//! ```
+//! use asm_att_core::isa::{Flags, Privilege, lookup};
+//!
+//! let cmps = lookup("cmpsb").unwrap();
+//! assert_eq!(cmps.name, "cmps");
+//! assert_eq!(cmps.reads, ["rsi", "rdi"]);
+//! assert!(cmps.flags_read.contains(Flags::DF));
+//! assert_eq!(lookup("pdep").unwrap().features, ["bmi2"]);
+//! assert_eq!(lookup("wrmsr").unwrap().privilege, Privilege::Kernel);
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::BitOr;
+
+use crate::syntax::{Instruction, Operand};
+
+/// A set of the status and direction flags of `EFLAGS`, with the bits they
+/// have in the register.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flags(u16);
+
+impl Flags {
+    /// No flag.
+    pub const NONE: Flags = Flags(0);
+    /// The carry flag.
+    pub const CF: Flags = Flags(1 << 0);
+    /// The parity flag.
+    pub const PF: Flags = Flags(1 << 2);
+    /// The auxiliary carry flag.
+    pub const AF: Flags = Flags(1 << 4);
+    /// The zero flag.
+    pub const ZF: Flags = Flags(1 << 6);
+    /// The sign flag.
+    pub const SF: Flags = Flags(1 << 7);
+    /// The direction flag.
+    pub const DF: Flags = Flags(1 << 10);
+    /// The overflow flag.
+    pub const OF: Flags = Flags(1 << 11);
+    /// The six status flags, which arithmetic instructions set.
+    pub const STATUS: Flags =
+        Flags(Flags::CF.0 | Flags::PF.0 | Flags::AF.0 | Flags::ZF.0 | Flags::SF.0 | Flags::OF.0);
+
+    /// Every flag, in the order of their bits, with its name.
+    const NAMES: [(Flags, &'static str); 7] = [
+        (Flags::CF, "CF"),
+        (Flags::PF, "PF"),
+        (Flags::AF, "AF"),
+        (Flags::ZF, "ZF"),
+        (Flags::SF, "SF"),
+        (Flags::DF, "DF"),
+        (Flags::OF, "OF"),
+    ];
+
+    /// The bits of the flags in `EFLAGS`.
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// The flags of both sets.
+    pub const fn union(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
+    }
+
+    /// Whether every flag of `other` is in the set.
+    pub const fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the set has no flag.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The names of the flags of the set, such as `ZF`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Flags::NAMES
+            .into_iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+    }
+}
+
+impl BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        self.union(other)
+    }
+}
+
+/// Prints the names of the flags separated by commas, such as `CF, ZF`.
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, name) in self.names().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The privilege level an instruction runs at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Privilege {
+    /// Any privilege level.
+    User,
+    /// Privilege level 0 only, or user code the kernel has given I/O
+    /// privileges, such as for `in` and `cli`: elsewhere the instruction
+    /// raises a general-protection fault.
+    Kernel,
+}
+
+/// Which explicit operands an instruction writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Destination {
+    /// The last one in AT&T order, as for `add`.
+    Last,
+    /// None: the instruction only reads them, as `cmp` does, or only writes
+    /// implicit registers, as `mul` does.
+    None,
+    /// Every one, as for `xchg`, and for gathers, which clear their mask.
+    All,
+}
+
+/// The memory an instruction accesses, besides through its memory operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Memory {
+    /// No other memory.
+    Operands,
+    /// No memory at all: the memory operand is only an address, as for `lea`.
+    Address,
+    /// Loads from an address in registers, as `xlat` does from `(%rbx,%al)`.
+    Loads,
+    /// Stores to an address in registers or to the stack, or hands control
+    /// to code that may access any memory, as `syscall` does. Fences count as
+    /// stores too, as they order the accesses around them.
+    Stores,
+}
+
+/// An instruction of the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mnemonic {
+    /// The Intel mnemonic, such as `cmps` or `movzx`.
+    pub name: &'static str,
+    /// The AT&T spellings, without the size suffixes of [`suffixes`], such as
+    /// `cmps` or `movzbl`.
+    ///
+    /// [`suffixes`]: Mnemonic::suffixes
+    pub att: &'static [&'static str],
+    /// The size suffixes the AT&T spellings take, such as `bwlq`.
+    pub suffixes: &'static str,
+    /// The operand forms, in AT&T order, such as `imm, r/m`. An empty form
+    /// takes no operand.
+    pub forms: &'static [&'static str],
+    /// The registers the instruction reads without naming them, by the name
+    /// of their family such as `rax`.
+    pub reads: &'static [&'static str],
+    /// The registers the instruction writes without naming them, by the name
+    /// of their family such as `rdx`.
+    pub writes: &'static [&'static str],
+    /// The flags the instruction reads.
+    pub flags_read: Flags,
+    /// The flags the instruction writes, including those it leaves undefined.
+    pub flags_written: Flags,
+    /// Whether the instruction may raise floating-point exceptions, which
+    /// sets their flags in `MXCSR` or in the x87 status word.
+    pub exceptions: bool,
+    /// The explicit operands the instruction writes.
+    pub destination: Destination,
+    /// The memory the instruction accesses besides its memory operands.
+    pub memory: Memory,
+    /// The CPU features the instruction needs, by their `#[target_feature]`
+    /// name such as `avx2`. For the vector instructions that AVX-512 extends,
+    /// these are the features of the VEX forms: the EVEX forms, on `%zmm`
+    /// registers or with masks, need `avx512f` and `evex_features` instead.
+    pub features: &'static [&'static str],
+    /// The features the EVEX forms need besides `avx512f`, such as `avx512bw`
+    /// for the instructions on bytes and words.
+    pub evex_features: &'static [&'static str],
+    /// The features the forms on `%ymm` registers need instead of `features`,
+    /// if they differ: AVX has the `%xmm` forms of the integer instructions,
+    /// and AVX2 their `%ymm` forms.
+    pub wide_features: &'static [&'static str],
+    /// The privilege level the instruction runs at.
+    pub privilege: Privilege,
+}
+
+impl Mnemonic {
+    /// Every AT&T spelling of the instruction, with and without its size
+    /// suffixes, such as `cmps`, `cmpsb`, `cmpsw`, `cmpsl` and `cmpsq`.
+    pub fn spellings(&self) -> Vec<String> {
+        let mut spellings = Vec::new();
+        for att in self.att {
+            spellings.push(att.to_string());
+            spellings.extend(self.suffixes.chars().map(|suffix| format!("{att}{suffix}")));
+        }
+        spellings
+    }
+
+    /// An instruction writing its last operand, with no implicit register,
+    /// flag, memory access or feature.
+    const fn new(
+        name: &'static str,
+        att: &'static [&'static str],
+        suffixes: &'static str,
+        forms: &'static [&'static str],
+    ) -> Mnemonic {
+        Mnemonic {
+            name,
+            att,
+            suffixes,
+            forms,
+            reads: &[],
+            writes: &[],
+            flags_read: Flags::NONE,
+            flags_written: Flags::NONE,
+            exceptions: false,
+            destination: Destination::Last,
+            memory: Memory::Operands,
+            features: &[],
+            evex_features: &[],
+            wide_features: &[],
+            privilege: Privilege::User,
+        }
+    }
+
+    const fn reads(mut self, reads: &'static [&'static str]) -> Mnemonic {
+        self.reads = reads;
+        self
+    }
+
+    const fn writes(mut self, writes: &'static [&'static str]) -> Mnemonic {
+        self.writes = writes;
+        self
+    }
+
+    const fn flags(mut self, read: Flags, written: Flags) -> Mnemonic {
+        self.flags_read = read;
+        self.flags_written = written;
+        self
+    }
+
+    const fn raises(mut self) -> Mnemonic {
+        self.exceptions = true;
+        self
+    }
+
+    const fn read_only(mut self) -> Mnemonic {
+        self.destination = Destination::None;
+        self
+    }
+
+    const fn writes_all(mut self) -> Mnemonic {
+        self.destination = Destination::All;
+        self
+    }
+
+    const fn address(mut self) -> Mnemonic {
+        self.memory = Memory::Address;
+        self
+    }
+
+    const fn loads(mut self) -> Mnemonic {
+        self.memory = Memory::Loads;
+        self
+    }
+
+    const fn stores(mut self) -> Mnemonic {
+        self.memory = Memory::Stores;
+        self
+    }
+
+    const fn features(mut self, features: &'static [&'static str]) -> Mnemonic {
+        self.features = features;
+        self
+    }
+
+    const fn evex_features(mut self, features: &'static [&'static str]) -> Mnemonic {
+        self.evex_features = features;
+        self
+    }
+
+    const fn wide_features(mut self, features: &'static [&'static str]) -> Mnemonic {
+        self.wide_features = features;
+        self
+    }
+
+    const fn kernel(mut self) -> Mnemonic {
+        self.privilege = Privilege::Kernel;
+        self
+    }
+}
+
+/// The instruction with the AT&T spelling or Intel mnemonic, in any case.
+///
+/// An AT&T spelling is looked up as written, then without a size suffix, so
+/// that `movsd` is the SSE2 move of a double and `movsl` the string move of
+/// a long, and `movq` the general-purpose `mov`.
+///
+/// ```
+/// use asm_att_core::isa::lookup;
+///
+/// assert_eq!(lookup("movsl").unwrap().name, "movs");
+/// assert_eq!(lookup("MOVSD").unwrap().name, "movsd");
+/// assert_eq!(lookup("cmovneq").unwrap().name, "cmovne");
+/// assert_eq!(lookup("movzx").unwrap().att, ["movzbw", "movzbl", "movzbq", "movzwl", "movzwq"]);
+/// assert!(lookup("movx").is_none());
+/// ```
+pub fn lookup(mnemonic: &str) -> Option<&'static Mnemonic> {
+    let mnemonic = mnemonic.to_ascii_lowercase();
+    let mnemonic = mnemonic.as_str();
+    mnemonics()
+        .find(|entry| entry.att.contains(&mnemonic))
+        .or_else(|| {
+            let suffix = mnemonic.chars().last()?;
+            let stem = &mnemonic[..mnemonic.len() - suffix.len_utf8()];
+            mnemonics().find(|entry| entry.suffixes.contains(suffix) && entry.att.contains(&stem))
+        })
+        .or_else(|| mnemonics().find(|entry| entry.name == mnemonic))
+}
+
+/// The CPU features an instruction needs, by their `#[target_feature]` name,
+/// or none if it is not in the table.
+///
+/// The integer vector instructions need AVX2 rather than AVX on `%ymm`
+/// registers. The vector instructions that AVX-512 extends need `avx512f`
+/// instead of the features of their VEX forms when they take AVX-512
+/// decorators, `%zmm` registers, mask registers, or `%xmm16` to `%xmm31` and
+/// their `%ymm` counterparts, along with `avx512bw` or `avx512dq` for some
+/// instructions, and `avx512vl` on `%xmm` and `%ymm` registers.
+///
+/// ```
+/// use asm_att_core::isa::features;
+/// use asm_att_core::syntax::Instruction;
+///
+/// let features = |text: &str| features(&text.parse::<Instruction>().unwrap());
+/// assert_eq!(features("vpaddd %xmm0, %xmm1, %xmm2"), ["avx"]);
+/// assert_eq!(features("vpaddd %ymm0, %ymm1, %ymm2"), ["avx2"]);
+/// assert_eq!(features("vpaddd %zmm0, %zmm1, %zmm2"), ["avx512f"]);
+/// assert_eq!(features("vpaddb %ymm16, %ymm1, %ymm2"), ["avx512f", "avx512bw", "avx512vl"]);
+/// assert!(features("addq $1, %rax").is_empty());
+/// ```
+pub fn features(instruction: &Instruction) -> Vec<&'static str> {
+    let Some(mnemonic) = lookup(&instruction.mnemonic) else {
+        return Vec::new();
+    };
+    let extended = mnemonic
+        .features
+        .iter()
+        .any(|feature| feature.starts_with("avx") || matches!(*feature, "fma" | "f16c"));
+    let native = mnemonic
+        .features
+        .iter()
+        .any(|feature| feature.starts_with("avx512"));
+    if native || extended && instruction.operands.iter().any(is_evex) {
+        let mut features = if native {
+            mnemonic.features.to_vec()
+        } else {
+            let mut features = vec!["avx512f"];
+            features.extend(mnemonic.evex_features);
+            features
+        };
+        // Packed instructions on `%xmm` and `%ymm` registers.
+        let packed = mnemonic
+            .forms
+            .iter()
+            .any(|form| form.contains("ymm") || form.contains("zmm"));
+        let vector = |prefix: &str| {
+            instruction.operands.iter().any(|operand| {
+                matches!(operand.undecorated(), Operand::Register(register)
+                    if register.name().to_ascii_lowercase().starts_with(prefix))
+            })
+        };
+        if packed && (vector("xmm") || vector("ymm")) && !vector("zmm") {
+            features.push("avx512vl");
+        }
+        return features;
+    }
+    let wide = instruction.operands.iter().any(|operand| {
+        matches!(operand.undecorated(), Operand::Register(register)
+            if register.name().to_ascii_lowercase().starts_with("ymm"))
+    });
+    if wide && !mnemonic.wide_features.is_empty() {
+        return mnemonic.wide_features.to_vec();
+    }
+    mnemonic.features.to_vec()
+}
+
+/// The features that enabling a feature with `#[target_feature]` enables as
+/// well, besides those they imply in turn.
+const IMPLIED: &[(&str, &[&str])] = &[
+    ("sse2", &["sse"]),
+    ("sse3", &["sse2"]),
+    ("ssse3", &["sse3"]),
+    ("sse4.1", &["ssse3"]),
+    ("sse4.2", &["sse4.1"]),
+    ("sse4a", &["sse3"]),
+    ("avx", &["sse4.2"]),
+    ("avx2", &["avx"]),
+    ("fma", &["avx"]),
+    ("f16c", &["avx"]),
+    ("aes", &["sse2"]),
+    ("pclmulqdq", &["sse2"]),
+    ("sha", &["sse2"]),
+    ("gfni", &["sse2"]),
+    ("vaes", &["avx2", "aes"]),
+    ("vpclmulqdq", &["avx", "pclmulqdq"]),
+    ("avx512f", &["avx2", "fma", "f16c"]),
+    ("avx512bw", &["avx512f"]),
+    ("avx512cd", &["avx512f"]),
+    ("avx512dq", &["avx512f"]),
+    ("avx512vl", &["avx512f"]),
+    ("avx512ifma", &["avx512f"]),
+    ("avx512vbmi", &["avx512bw"]),
+    ("avx512vbmi2", &["avx512bw"]),
+    ("avx512vnni", &["avx512f"]),
+    ("avx512bitalg", &["avx512bw"]),
+    ("avx512vpopcntdq", &["avx512f"]),
+    ("xsaveopt", &["xsave"]),
+    ("xsavec", &["xsave"]),
+    ("xsaves", &["xsave"]),
+];
+
+/// Whether enabling the feature `enabled` with `#[target_feature]` enables
+/// `feature` as well, as `avx2` does `avx`.
+///
+/// ```
+/// use asm_att_core::isa::implies;
+///
+/// assert!(implies("avx2", "avx"));
+/// assert!(implies("avx512bw", "sse2"));
+/// assert!(!implies("avx", "avx2"));
+/// ```
+pub fn implies(enabled: &str, feature: &str) -> bool {
+    enabled == feature
+        || IMPLIED.iter().any(|(name, implied)| {
+            *name == enabled && implied.iter().any(|next| implies(next, feature))
+        })
+}
+
+/// Whether an operand only exists in the EVEX encoding of AVX-512.
+fn is_evex(operand: &Operand) -> bool {
+    let Operand::Register(register) = operand else {
+        return matches!(operand, Operand::Decorated(..) | Operand::Decorator(_));
+    };
+    let name = register.name().to_ascii_lowercase();
+    if name.starts_with("zmm")
+        || name
+            .strip_prefix('k')
+            .is_some_and(|n| n.parse::<u8>().is_ok())
+    {
+        return true;
+    }
+    ["xmm", "ymm"].iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .and_then(|number| number.parse::<u8>().ok())
+            .is_some_and(|number| number >= 16)
+    })
+}
+
+/// Every instruction of the table.
+pub fn mnemonics() -> impl Iterator<Item = &'static Mnemonic> {
+    GENERAL
+        .iter()
+        .chain(JUMPS)
+        .chain(SETS)
+        .chain(MOVES)
+        .chain(SYSTEM)
+        .chain(X87)
+        .chain(VECTOR)
+}
+
+/// The size suffixes of an instruction that takes bytes, words, longs and
+/// quads.
+const SIZED: &str = "bwlq";
+
+/// The size suffixes of an instruction without a byte form.
+const WIDE: &str = "wlq";
+
+/// The general-purpose instructions, besides the conditional ones.
+const GENERAL: &[Mnemonic] = &[
+    Mnemonic::new("mov", &["mov"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"]),
+    Mnemonic::new(
+        "movabs",
+        &["movabs"],
+        SIZED,
+        &["imm64, r", "moffs, a", "a, moffs"],
+    ),
+    Mnemonic::new(
+        "movzx",
+        &["movzbw", "movzbl", "movzbq", "movzwl", "movzwq"],
+        "",
+        &["r/m, r"],
+    ),
+    Mnemonic::new(
+        "movsx",
+        &["movsbw", "movsbl", "movsbq", "movswl", "movswq"],
+        "",
+        &["r/m, r"],
+    ),
+    Mnemonic::new("movsxd", &["movslq"], "", &["r/m, r"]),
+    Mnemonic::new("lea", &["lea"], WIDE, &["m, r"]).address(),
+    Mnemonic::new("xchg", &["xchg"], SIZED, &["r, r/m", "r/m, r"]).writes_all(),
+    Mnemonic::new("push", &["push"], "wq", &["imm", "r/m"])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .read_only()
+        .stores(),
+    Mnemonic::new("pop", &["pop"], "wq", &["r/m"])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .stores(),
+    Mnemonic::new("pushf", &["pushf"], "wq", &[""])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .flags(Flags::STATUS.union(Flags::DF), Flags::NONE)
+        .stores(),
+    Mnemonic::new("popf", &["popf"], "wq", &[""])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .flags(Flags::NONE, Flags::STATUS.union(Flags::DF))
+        .stores(),
+    Mnemonic::new("add", &["add"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("adc", &["adc"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::CF, Flags::STATUS),
+    Mnemonic::new("sub", &["sub"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("sbb", &["sbb"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::CF, Flags::STATUS),
+    Mnemonic::new("cmp", &["cmp"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new("and", &["and"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("or", &["or"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("xor", &["xor"], SIZED, &["imm, r/m", "r, r/m", "r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("test", &["test"], SIZED, &["imm, r/m", "r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new("inc", &["inc"], SIZED, &["r/m"])
+        .flags(Flags::NONE, Flags(Flags::STATUS.0 & !Flags::CF.0)),
+    Mnemonic::new("dec", &["dec"], SIZED, &["r/m"])
+        .flags(Flags::NONE, Flags(Flags::STATUS.0 & !Flags::CF.0)),
+    Mnemonic::new("neg", &["neg"], SIZED, &["r/m"]).flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("not", &["not"], SIZED, &["r/m"]),
+    Mnemonic::new("mul", &["mul"], SIZED, &["r/m"])
+        .reads(&["rax"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    // The implicit registers are those of the one-operand form.
+    Mnemonic::new("imul", &["imul"], SIZED, &["r/m", "r/m, r", "imm, r/m, r"])
+        .reads(&["rax"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("div", &["div"], SIZED, &["r/m"])
+        .reads(&["rax", "rdx"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new("idiv", &["idiv"], SIZED, &["r/m"])
+        .reads(&["rax", "rdx"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new(
+        "shl",
+        &["shl", "sal"],
+        SIZED,
+        &["imm8, r/m", "%cl, r/m", "r/m"],
+    )
+    .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("shr", &["shr"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("sar", &["sar"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("rol", &["rol"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::NONE, Flags::CF.union(Flags::OF)),
+    Mnemonic::new("ror", &["ror"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::NONE, Flags::CF.union(Flags::OF)),
+    Mnemonic::new("rcl", &["rcl"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::CF, Flags::CF.union(Flags::OF)),
+    Mnemonic::new("rcr", &["rcr"], SIZED, &["imm8, r/m", "%cl, r/m", "r/m"])
+        .flags(Flags::CF, Flags::CF.union(Flags::OF)),
+    Mnemonic::new("shld", &["shld"], WIDE, &["imm8, r, r/m", "%cl, r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("shrd", &["shrd"], WIDE, &["imm8, r, r/m", "%cl, r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("bt", &["bt"], WIDE, &["imm8, r/m", "r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new("bts", &["bts"], WIDE, &["imm8, r/m", "r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("btr", &["btr"], WIDE, &["imm8, r/m", "r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("btc", &["btc"], WIDE, &["imm8, r/m", "r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("bsf", &["bsf"], WIDE, &["r/m, r"]).flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("bsr", &["bsr"], WIDE, &["r/m, r"]).flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("bswap", &["bswap"], "lq", &["r"]),
+    Mnemonic::new("cmpxchg", &["cmpxchg"], SIZED, &["r, r/m"])
+        .reads(&["rax"])
+        .writes(&["rax"])
+        .flags(Flags::NONE, Flags::STATUS),
+    Mnemonic::new("cmpxchg8b", &["cmpxchg8b"], "", &["m"])
+        .reads(&["rax", "rbx", "rcx", "rdx"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::ZF),
+    Mnemonic::new("cmpxchg16b", &["cmpxchg16b"], "", &["m"])
+        .reads(&["rax", "rbx", "rcx", "rdx"])
+        .writes(&["rax", "rdx"])
+        .flags(Flags::NONE, Flags::ZF)
+        .features(&["cmpxchg16b"]),
+    Mnemonic::new("xadd", &["xadd"], SIZED, &["r, r/m"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .writes_all(),
+    Mnemonic::new("cbw", &["cbtw", "cbw"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rax"]),
+    Mnemonic::new("cwde", &["cwtl", "cwde"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rax"]),
+    Mnemonic::new("cdqe", &["cltq", "cdqe"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rax"]),
+    Mnemonic::new("cwd", &["cwtd", "cwd"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rdx"]),
+    Mnemonic::new("cdq", &["cltd", "cdq"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rdx"]),
+    Mnemonic::new("cqo", &["cqto", "cqo"], "", &[""])
+        .reads(&["rax"])
+        .writes(&["rdx"]),
+    Mnemonic::new("lahf", &["lahf"], "", &[""])
+        .writes(&["rax"])
+        .flags(Flags(Flags::STATUS.0 & !Flags::OF.0), Flags::NONE),
+    Mnemonic::new("sahf", &["sahf"], "", &[""])
+        .reads(&["rax"])
+        .flags(Flags::NONE, Flags(Flags::STATUS.0 & !Flags::OF.0)),
+    Mnemonic::new("clc", &["clc"], "", &[""]).flags(Flags::NONE, Flags::CF),
+    Mnemonic::new("stc", &["stc"], "", &[""]).flags(Flags::NONE, Flags::CF),
+    Mnemonic::new("cmc", &["cmc"], "", &[""]).flags(Flags::CF, Flags::CF),
+    Mnemonic::new("cld", &["cld"], "", &[""]).flags(Flags::NONE, Flags::DF),
+    Mnemonic::new("std", &["std"], "", &[""]).flags(Flags::NONE, Flags::DF),
+    Mnemonic::new("jmp", &["jmp"], "q", &["rel", "*r/m"]).read_only(),
+    Mnemonic::new("call", &["call"], "q", &["rel", "*r/m"])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .read_only()
+        .stores(),
+    Mnemonic::new("ret", &["ret"], "q", &["", "imm16"])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .read_only()
+        .stores(),
+    Mnemonic::new("enter", &["enter"], "q", &["imm16, imm8"])
+        .reads(&["rsp", "rbp"])
+        .writes(&["rsp", "rbp"])
+        .stores(),
+    Mnemonic::new("leave", &["leave"], "q", &[""])
+        .reads(&["rsp", "rbp"])
+        .writes(&["rsp", "rbp"])
+        .stores(),
+    Mnemonic::new("loop", &["loop"], "", &["rel"])
+        .reads(&["rcx"])
+        .writes(&["rcx"]),
+    Mnemonic::new("loope", &["loope", "loopz"], "", &["rel"])
+        .reads(&["rcx"])
+        .writes(&["rcx"])
+        .flags(Flags::ZF, Flags::NONE),
+    Mnemonic::new("loopne", &["loopne", "loopnz"], "", &["rel"])
+        .reads(&["rcx"])
+        .writes(&["rcx"])
+        .flags(Flags::ZF, Flags::NONE),
+    Mnemonic::new("jrcxz", &["jrcxz", "jecxz"], "", &["rel"]).reads(&["rcx"]),
+    Mnemonic::new("movs", &["movs"], SIZED, &["", "m, m"])
+        .reads(&["rsi", "rdi"])
+        .writes(&["rsi", "rdi"])
+        .flags(Flags::DF, Flags::NONE)
+        .stores(),
+    Mnemonic::new("cmps", &["cmps"], SIZED, &["", "m, m"])
+        .reads(&["rsi", "rdi"])
+        .writes(&["rsi", "rdi"])
+        .flags(Flags::DF, Flags::STATUS)
+        .read_only()
+        .loads(),
+    Mnemonic::new("scas", &["scas"], SIZED, &["", "m"])
+        .reads(&["rax", "rdi"])
+        .writes(&["rdi"])
+        .flags(Flags::DF, Flags::STATUS)
+        .read_only()
+        .loads(),
+    Mnemonic::new("lods", &["lods"], SIZED, &["", "m"])
+        .reads(&["rsi"])
+        .writes(&["rax", "rsi"])
+        .flags(Flags::DF, Flags::NONE)
+        .loads(),
+    Mnemonic::new("stos", &["stos"], SIZED, &["", "m"])
+        .reads(&["rax", "rdi"])
+        .writes(&["rdi"])
+        .flags(Flags::DF, Flags::NONE)
+        .stores(),
+    Mnemonic::new("xlat", &["xlat"], "b", &["", "m"])
+        .reads(&["rax", "rbx"])
+        .writes(&["rax"])
+        .loads(),
+    Mnemonic::new("nop", &["nop"], "wl", &["", "r/m"])
+        .read_only()
+        .address(),
+    Mnemonic::new("pause", &["pause"], "", &[""]),
+    Mnemonic::new("ud2", &["ud2"], "", &[""]),
+    Mnemonic::new("int3", &["int3"], "", &[""]),
+    Mnemonic::new("int", &["int"], "", &["imm8"]).stores(),
+    // The kernel returns its result in `%rax`.
+    Mnemonic::new("syscall", &["syscall"], "", &[""])
+        .writes(&["rax", "rcx", "r11"])
+        .stores(),
+    Mnemonic::new("cpuid", &["cpuid"], "", &[""])
+        .reads(&["rax", "rcx"])
+        .writes(&["rax", "rbx", "rcx", "rdx"]),
+    Mnemonic::new("rdtsc", &["rdtsc"], "", &[""]).writes(&["rax", "rdx"]),
+    Mnemonic::new("rdtscp", &["rdtscp"], "", &[""]).writes(&["rax", "rcx", "rdx"]),
+    Mnemonic::new("rdrand", &["rdrand"], WIDE, &["r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["rdrand"]),
+    Mnemonic::new("rdseed", &["rdseed"], WIDE, &["r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["rdseed"]),
+    Mnemonic::new("popcnt", &["popcnt"], WIDE, &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["popcnt"]),
+    Mnemonic::new("lzcnt", &["lzcnt"], WIDE, &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["lzcnt"]),
+    Mnemonic::new("tzcnt", &["tzcnt"], WIDE, &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("andn", &["andn"], "lq", &["r/m, r, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("bextr", &["bextr"], "lq", &["r, r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("blsi", &["blsi"], "lq", &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("blsmsk", &["blsmsk"], "lq", &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("blsr", &["blsr"], "lq", &["r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi1"]),
+    Mnemonic::new("bzhi", &["bzhi"], "lq", &["r, r/m, r"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["bmi2"]),
+    Mnemonic::new("mulx", &["mulx"], "lq", &["r/m, r, r"])
+        .reads(&["rdx"])
+        .features(&["bmi2"]),
+    Mnemonic::new("pdep", &["pdep"], "lq", &["r/m, r, r"]).features(&["bmi2"]),
+    Mnemonic::new("pext", &["pext"], "lq", &["r/m, r, r"]).features(&["bmi2"]),
+    Mnemonic::new("rorx", &["rorx"], "lq", &["imm8, r/m, r"]).features(&["bmi2"]),
+    Mnemonic::new("sarx", &["sarx"], "lq", &["r, r/m, r"]).features(&["bmi2"]),
+    Mnemonic::new("shlx", &["shlx"], "lq", &["r, r/m, r"]).features(&["bmi2"]),
+    Mnemonic::new("shrx", &["shrx"], "lq", &["r, r/m, r"]).features(&["bmi2"]),
+    Mnemonic::new("adcx", &["adcx"], "lq", &["r/m, r"])
+        .flags(Flags::CF, Flags::CF)
+        .features(&["adx"]),
+    Mnemonic::new("adox", &["adox"], "lq", &["r/m, r"])
+        .flags(Flags::OF, Flags::OF)
+        .features(&["adx"]),
+    Mnemonic::new("movbe", &["movbe"], WIDE, &["m, r", "r, m"]).features(&["movbe"]),
+    Mnemonic::new("crc32", &["crc32"], SIZED, &["r/m, r"]).features(&["sse4.2"]),
+    Mnemonic::new("lfence", &["lfence"], "", &[""])
+        .features(&["sse2"])
+        .stores(),
+    Mnemonic::new("mfence", &["mfence"], "", &[""])
+        .features(&["sse2"])
+        .stores(),
+    Mnemonic::new("sfence", &["sfence"], "", &[""])
+        .features(&["sse"])
+        .stores(),
+    Mnemonic::new("clflush", &["clflush"], "", &["m"])
+        .features(&["sse2"])
+        .read_only(),
+    Mnemonic::new(
+        "prefetch",
+        &["prefetcht0", "prefetcht1", "prefetcht2", "prefetchnta"],
+        "",
+        &["m"],
+    )
+    .features(&["sse"])
+    .read_only()
+    .address(),
+    Mnemonic::new("fxsave", &["fxsave", "fxsave64"], "", &["m"]).features(&["fxsr"]),
+    Mnemonic::new("fxrstor", &["fxrstor", "fxrstor64"], "", &["m"])
+        .features(&["fxsr"])
+        .read_only(),
+    Mnemonic::new("xsave", &["xsave", "xsave64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsave"]),
+    Mnemonic::new("xrstor", &["xrstor", "xrstor64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsave"])
+        .read_only(),
+    Mnemonic::new("xsaveopt", &["xsaveopt", "xsaveopt64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsaveopt"]),
+    Mnemonic::new("xsavec", &["xsavec", "xsavec64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsavec"]),
+    Mnemonic::new("xgetbv", &["xgetbv"], "", &[""])
+        .reads(&["rcx"])
+        .writes(&["rax", "rdx"])
+        .features(&["xsave"]),
+    Mnemonic::new("xbegin", &["xbegin"], "", &["rel"])
+        .writes(&["rax"])
+        .features(&["rtm"]),
+    Mnemonic::new("xend", &["xend"], "", &[""]).features(&["rtm"]),
+    Mnemonic::new("xabort", &["xabort"], "", &["imm8"]).features(&["rtm"]),
+    Mnemonic::new("xtest", &["xtest"], "", &[""])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["rtm"]),
+    // Enabled by the kernel rather than a target feature, as on Linux 5.9 on.
+    Mnemonic::new("rdfsbase", &["rdfsbase", "rdgsbase"], "lq", &["r"]),
+    Mnemonic::new("wrfsbase", &["wrfsbase", "wrgsbase"], "lq", &["r"]).read_only(),
+];
+
+/// Declares the conditional jumps, sets and moves, from the condition codes
+/// and their aliases with the flags they read.
+macro_rules! conditional {
+    ($($code:literal $(| $alias:literal)* => $flags:expr;)*) => {
+        /// The conditional jumps.
+        const JUMPS: &[Mnemonic] = &[$(
+            Mnemonic::new(
+                concat!("j", $code),
+                &[concat!("j", $code) $(, concat!("j", $alias))*],
+                "",
+                &["rel"],
+            )
+            .flags($flags, Flags::NONE),
+        )*];
+
+        /// The conditional sets of a byte.
+        const SETS: &[Mnemonic] = &[$(
+            Mnemonic::new(
+                concat!("set", $code),
+                &[concat!("set", $code) $(, concat!("set", $alias))*],
+                "b",
+                &["r/m"],
+            )
+            .flags($flags, Flags::NONE),
+        )*];
+
+        /// The conditional moves.
+        const MOVES: &[Mnemonic] = &[$(
+            Mnemonic::new(
+                concat!("cmov", $code),
+                &[concat!("cmov", $code) $(, concat!("cmov", $alias))*],
+                WIDE,
+                &["r/m, r"],
+            )
+            .flags($flags, Flags::NONE),
+        )*];
+    };
+}
+
+conditional! {
+    "o" => Flags::OF;
+    "no" => Flags::OF;
+    "b" | "c" | "nae" => Flags::CF;
+    "ae" | "nb" | "nc" => Flags::CF;
+    "e" | "z" => Flags::ZF;
+    "ne" | "nz" => Flags::ZF;
+    "be" | "na" => Flags::CF.union(Flags::ZF);
+    "a" | "nbe" => Flags::CF.union(Flags::ZF);
+    "s" => Flags::SF;
+    "ns" => Flags::SF;
+    "p" | "pe" => Flags::PF;
+    "np" | "po" => Flags::PF;
+    "l" | "nge" => Flags::SF.union(Flags::OF);
+    "ge" | "nl" => Flags::SF.union(Flags::OF);
+    "le" | "ng" => Flags::ZF.union(Flags::SF).union(Flags::OF);
+    "g" | "nle" => Flags::ZF.union(Flags::SF).union(Flags::OF);
+}
+
+/// The system instructions.
+const SYSTEM: &[Mnemonic] = &[
+    Mnemonic::new("hlt", &["hlt"], "", &[""]).kernel(),
+    Mnemonic::new("cli", &["cli"], "", &[""]).kernel(),
+    Mnemonic::new("sti", &["sti"], "", &[""]).kernel(),
+    Mnemonic::new("in", &["in"], "bwl", &["imm8, a", "%dx, a"])
+        .writes(&["rax"])
+        .kernel(),
+    Mnemonic::new("out", &["out"], "bwl", &["a, imm8", "a, %dx"])
+        .reads(&["rax"])
+        .kernel()
+        .read_only(),
+    Mnemonic::new("ins", &["ins"], "bwl", &["", "%dx, m"])
+        .reads(&["rdx", "rdi"])
+        .writes(&["rdi"])
+        .flags(Flags::DF, Flags::NONE)
+        .kernel()
+        .stores(),
+    Mnemonic::new("outs", &["outs"], "bwl", &["", "m, %dx"])
+        .reads(&["rdx", "rsi"])
+        .writes(&["rsi"])
+        .flags(Flags::DF, Flags::NONE)
+        .kernel()
+        .read_only()
+        .loads(),
+    Mnemonic::new("lgdt", &["lgdt"], "q", &["m"])
+        .kernel()
+        .read_only(),
+    Mnemonic::new("lidt", &["lidt"], "q", &["m"])
+        .kernel()
+        .read_only(),
+    Mnemonic::new("sgdt", &["sgdt"], "q", &["m"]),
+    Mnemonic::new("sidt", &["sidt"], "q", &["m"]),
+    Mnemonic::new("lldt", &["lldt"], "", &["r/m"])
+        .kernel()
+        .read_only(),
+    Mnemonic::new("ltr", &["ltr"], "", &["r/m"])
+        .kernel()
+        .read_only(),
+    Mnemonic::new("invlpg", &["invlpg"], "", &["m"])
+        .kernel()
+        .read_only()
+        .address(),
+    Mnemonic::new("invd", &["invd"], "", &[""]).kernel(),
+    Mnemonic::new("wbinvd", &["wbinvd"], "", &[""]).kernel(),
+    Mnemonic::new("clts", &["clts"], "", &[""]).kernel(),
+    Mnemonic::new("swapgs", &["swapgs"], "", &[""]).kernel(),
+    Mnemonic::new("sysret", &["sysret"], "lq", &[""])
+        .reads(&["rcx", "r11"])
+        .kernel(),
+    Mnemonic::new("iret", &["iret"], WIDE, &[""])
+        .reads(&["rsp"])
+        .writes(&["rsp"])
+        .flags(Flags::NONE, Flags::STATUS.union(Flags::DF))
+        .stores(),
+    Mnemonic::new("rdmsr", &["rdmsr"], "", &[""])
+        .reads(&["rcx"])
+        .writes(&["rax", "rdx"])
+        .kernel(),
+    Mnemonic::new("wrmsr", &["wrmsr"], "", &[""])
+        .reads(&["rax", "rcx", "rdx"])
+        .kernel(),
+    // User code may run `rdpmc` only if the kernel sets `CR4.PCE`.
+    Mnemonic::new("rdpmc", &["rdpmc"], "", &[""])
+        .reads(&["rcx"])
+        .writes(&["rax", "rdx"])
+        .kernel(),
+    Mnemonic::new("xsetbv", &["xsetbv"], "", &[""])
+        .reads(&["rax", "rcx", "rdx"])
+        .features(&["xsave"])
+        .kernel(),
+    Mnemonic::new("xsaves", &["xsaves", "xsaves64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsaves"])
+        .kernel(),
+    Mnemonic::new("xrstors", &["xrstors", "xrstors64"], "", &["m"])
+        .reads(&["rax", "rdx"])
+        .features(&["xsaves"])
+        .kernel()
+        .read_only(),
+];
+
+/// The x87 instructions most used outside of floating-point libraries.
+const X87: &[Mnemonic] = &[
+    Mnemonic::new("fld", &["fld"], "slt", &["m", "%st(i)"])
+        .read_only()
+        .raises(),
+    Mnemonic::new("fstp", &["fstp"], "slt", &["m", "%st(i)"]).raises(),
+    Mnemonic::new("fild", &["fild"], "sl", &["m"])
+        .read_only()
+        .raises(),
+    Mnemonic::new("fistp", &["fistp"], "sl", &["m"]).raises(),
+    Mnemonic::new("fadd", &["fadd"], "sl", &["m", "%st(i), %st"])
+        .read_only()
+        .raises(),
+    Mnemonic::new("fmul", &["fmul"], "sl", &["m", "%st(i), %st"])
+        .read_only()
+        .raises(),
+    Mnemonic::new("fninit", &["fninit"], "", &[""]).raises(),
+    Mnemonic::new("fldcw", &["fldcw"], "", &["m"]).read_only(),
+    Mnemonic::new("fnstcw", &["fnstcw"], "", &["m"]),
+];
+
+/// The `%ymm` registers that AVX can address, which `vzeroupper` and
+/// `vzeroall` clear.
+const YMM: &[&str] = &[
+    "ymm0", "ymm1", "ymm2", "ymm3", "ymm4", "ymm5", "ymm6", "ymm7", "ymm8", "ymm9", "ymm10",
+    "ymm11", "ymm12", "ymm13", "ymm14", "ymm15",
+];
+
+/// An SSE instruction on `%xmm` registers.
+const fn sse(
+    name: &'static str,
+    att: &'static [&'static str],
+    feature: &'static [&'static str],
+) -> Mnemonic {
+    Mnemonic::new(name, att, "", &["xmm/m, xmm"]).features(feature)
+}
+
+/// An AVX instruction on `%xmm` or `%ymm` registers, with a separate
+/// destination.
+const fn avx(
+    name: &'static str,
+    att: &'static [&'static str],
+    feature: &'static [&'static str],
+) -> Mnemonic {
+    Mnemonic::new(name, att, "", &["xmm/m, xmm, xmm", "ymm/m, ymm, ymm"]).features(feature)
+}
+
+/// An integer instruction of AVX on `%xmm` registers and of AVX2 on `%ymm`
+/// registers, with a separate destination.
+const fn avx2(name: &'static str, att: &'static [&'static str]) -> Mnemonic {
+    avx(name, att, &["avx"]).wide_features(&["avx2"])
+}
+
+/// The vector instructions, from SSE to AVX-512.
+// The SSE2 `movq` is spelled like the general-purpose `movq`, which it is
+// looked up as.
+const VECTOR: &[Mnemonic] = &[
+    sse("movaps", &["movaps"], &["sse"]),
+    sse("movups", &["movups"], &["sse"]),
+    sse("movss", &["movss"], &["sse"]),
+    sse("addps", &["addps"], &["sse"]).raises(),
+    sse("addss", &["addss"], &["sse"]).raises(),
+    sse("subps", &["subps"], &["sse"]).raises(),
+    sse("mulps", &["mulps"], &["sse"]).raises(),
+    sse("mulss", &["mulss"], &["sse"]).raises(),
+    sse("divps", &["divps"], &["sse"]).raises(),
+    sse("sqrtps", &["sqrtps"], &["sse"]).raises(),
+    sse("minps", &["minps"], &["sse"]).raises(),
+    sse("maxps", &["maxps"], &["sse"]).raises(),
+    sse("andps", &["andps"], &["sse"]),
+    sse("andnps", &["andnps"], &["sse"]),
+    sse("orps", &["orps"], &["sse"]),
+    sse("xorps", &["xorps"], &["sse"]),
+    sse("unpcklps", &["unpcklps"], &["sse"]),
+    sse("unpckhps", &["unpckhps"], &["sse"]),
+    Mnemonic::new("shufps", &["shufps"], "", &["imm8, xmm/m, xmm"]).features(&["sse"]),
+    Mnemonic::new("cmpps", &["cmpps"], "", &["imm8, xmm/m, xmm"])
+        .features(&["sse"])
+        .raises(),
+    Mnemonic::new("cvtsi2ss", &["cvtsi2ss"], "lq", &["r/m, xmm"])
+        .features(&["sse"])
+        .raises(),
+    Mnemonic::new("cvttss2si", &["cvttss2si"], "lq", &["xmm/m, r"])
+        .features(&["sse"])
+        .raises(),
+    sse("comiss", &["comiss"], &["sse"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .raises(),
+    sse("ucomiss", &["ucomiss"], &["sse"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .raises(),
+    Mnemonic::new("movmskps", &["movmskps"], "", &["xmm, r"]).features(&["sse"]),
+    Mnemonic::new("ldmxcsr", &["ldmxcsr"], "", &["m"])
+        .features(&["sse"])
+        .read_only()
+        .raises(),
+    Mnemonic::new("stmxcsr", &["stmxcsr"], "", &["m"]).features(&["sse"]),
+    sse("movapd", &["movapd"], &["sse2"]),
+    sse("movupd", &["movupd"], &["sse2"]),
+    sse("movsd", &["movsd"], &["sse2"]),
+    sse("movdqa", &["movdqa"], &["sse2"]),
+    sse("movdqu", &["movdqu"], &["sse2"]),
+    Mnemonic::new("movd", &["movd"], "", &["r/m, xmm", "xmm, r/m"]).features(&["sse2"]),
+    sse("addpd", &["addpd"], &["sse2"]).raises(),
+    sse("addsd", &["addsd"], &["sse2"]).raises(),
+    sse("subpd", &["subpd"], &["sse2"]).raises(),
+    sse("mulpd", &["mulpd"], &["sse2"]).raises(),
+    sse("mulsd", &["mulsd"], &["sse2"]).raises(),
+    sse("divpd", &["divpd"], &["sse2"]).raises(),
+    sse("sqrtpd", &["sqrtpd"], &["sse2"]).raises(),
+    sse("sqrtsd", &["sqrtsd"], &["sse2"]).raises(),
+    sse("andpd", &["andpd"], &["sse2"]),
+    sse("orpd", &["orpd"], &["sse2"]),
+    sse("xorpd", &["xorpd"], &["sse2"]),
+    Mnemonic::new("cmpsd", &["cmpsd"], "", &["imm8, xmm/m, xmm"])
+        .features(&["sse2"])
+        .raises(),
+    Mnemonic::new("cvtsi2sd", &["cvtsi2sd"], "lq", &["r/m, xmm"])
+        .features(&["sse2"])
+        .raises(),
+    Mnemonic::new("cvttsd2si", &["cvttsd2si"], "lq", &["xmm/m, r"])
+        .features(&["sse2"])
+        .raises(),
+    sse("cvtps2pd", &["cvtps2pd"], &["sse2"]).raises(),
+    sse("cvtpd2ps", &["cvtpd2ps"], &["sse2"]).raises(),
+    sse("comisd", &["comisd"], &["sse2"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .raises(),
+    sse("ucomisd", &["ucomisd"], &["sse2"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .raises(),
+    Mnemonic::new("movmskpd", &["movmskpd"], "", &["xmm, r"]).features(&["sse2"]),
+    sse("paddb", &["paddb"], &["sse2"]),
+    sse("paddw", &["paddw"], &["sse2"]),
+    sse("paddd", &["paddd"], &["sse2"]),
+    sse("paddq", &["paddq"], &["sse2"]),
+    sse("psubb", &["psubb"], &["sse2"]),
+    sse("psubw", &["psubw"], &["sse2"]),
+    sse("psubd", &["psubd"], &["sse2"]),
+    sse("psubq", &["psubq"], &["sse2"]),
+    sse("pmullw", &["pmullw"], &["sse2"]),
+    sse("pmuludq", &["pmuludq"], &["sse2"]),
+    sse("pand", &["pand"], &["sse2"]),
+    sse("pandn", &["pandn"], &["sse2"]),
+    sse("por", &["por"], &["sse2"]),
+    sse("pxor", &["pxor"], &["sse2"]),
+    sse("pcmpeqb", &["pcmpeqb"], &["sse2"]),
+    sse("pcmpeqw", &["pcmpeqw"], &["sse2"]),
+    sse("pcmpeqd", &["pcmpeqd"], &["sse2"]),
+    sse("pcmpgtb", &["pcmpgtb"], &["sse2"]),
+    sse("pcmpgtd", &["pcmpgtd"], &["sse2"]),
+    sse("punpcklbw", &["punpcklbw"], &["sse2"]),
+    sse("punpcklqdq", &["punpcklqdq"], &["sse2"]),
+    sse("punpckhqdq", &["punpckhqdq"], &["sse2"]),
+    Mnemonic::new("psllq", &["psllq"], "", &["imm8, xmm", "xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("psrlq", &["psrlq"], "", &["imm8, xmm", "xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("pslld", &["pslld"], "", &["imm8, xmm", "xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("psrld", &["psrld"], "", &["imm8, xmm", "xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("psrad", &["psrad"], "", &["imm8, xmm", "xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("pslldq", &["pslldq"], "", &["imm8, xmm"]).features(&["sse2"]),
+    Mnemonic::new("psrldq", &["psrldq"], "", &["imm8, xmm"]).features(&["sse2"]),
+    Mnemonic::new("pshufd", &["pshufd"], "", &["imm8, xmm/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("pmovmskb", &["pmovmskb"], "", &["xmm, r"]).features(&["sse2"]),
+    Mnemonic::new("pextrw", &["pextrw"], "", &["imm8, xmm, r"]).features(&["sse2"]),
+    Mnemonic::new("pinsrw", &["pinsrw"], "", &["imm8, r/m, xmm"]).features(&["sse2"]),
+    Mnemonic::new("movntdq", &["movntdq"], "", &["xmm, m"]).features(&["sse2"]),
+    Mnemonic::new("movnti", &["movnti"], "lq", &["r, m"]).features(&["sse2"]),
+    Mnemonic::new("maskmovdqu", &["maskmovdqu"], "", &["xmm, xmm"])
+        .reads(&["rdi"])
+        .read_only()
+        .stores()
+        .features(&["sse2"]),
+    sse("addsubps", &["addsubps"], &["sse3"]).raises(),
+    sse("haddps", &["haddps"], &["sse3"]).raises(),
+    sse("haddpd", &["haddpd"], &["sse3"]).raises(),
+    sse("movddup", &["movddup"], &["sse3"]),
+    Mnemonic::new("lddqu", &["lddqu"], "", &["m, xmm"]).features(&["sse3"]),
+    sse("pshufb", &["pshufb"], &["ssse3"]),
+    sse("pabsb", &["pabsb"], &["ssse3"]),
+    sse("pabsd", &["pabsd"], &["ssse3"]),
+    sse("phaddd", &["phaddd"], &["ssse3"]),
+    sse("pmaddubsw", &["pmaddubsw"], &["ssse3"]),
+    sse("pmulhrsw", &["pmulhrsw"], &["ssse3"]),
+    Mnemonic::new("palignr", &["palignr"], "", &["imm8, xmm/m, xmm"]).features(&["ssse3"]),
+    sse("pmulld", &["pmulld"], &["sse4.1"]),
+    sse("pminsd", &["pminsd"], &["sse4.1"]),
+    sse("pmaxsd", &["pmaxsd"], &["sse4.1"]),
+    sse("pminud", &["pminud"], &["sse4.1"]),
+    sse("pmaxud", &["pmaxud"], &["sse4.1"]),
+    sse("pcmpeqq", &["pcmpeqq"], &["sse4.1"]),
+    sse("pmovzxbw", &["pmovzxbw"], &["sse4.1"]),
+    sse("pmovzxwd", &["pmovzxwd"], &["sse4.1"]),
+    sse("pmovsxbw", &["pmovsxbw"], &["sse4.1"]),
+    sse("ptest", &["ptest"], &["sse4.1"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only(),
+    Mnemonic::new(
+        "pblendvb",
+        &["pblendvb"],
+        "",
+        &["%xmm0, xmm/m, xmm", "xmm/m, xmm"],
+    )
+    .reads(&["xmm0"])
+    .features(&["sse4.1"]),
+    Mnemonic::new(
+        "blendvps",
+        &["blendvps"],
+        "",
+        &["%xmm0, xmm/m, xmm", "xmm/m, xmm"],
+    )
+    .reads(&["xmm0"])
+    .features(&["sse4.1"]),
+    Mnemonic::new("blendps", &["blendps"], "", &["imm8, xmm/m, xmm"]).features(&["sse4.1"]),
+    Mnemonic::new("pblendw", &["pblendw"], "", &["imm8, xmm/m, xmm"]).features(&["sse4.1"]),
+    Mnemonic::new("dpps", &["dpps"], "", &["imm8, xmm/m, xmm"])
+        .features(&["sse4.1"])
+        .raises(),
+    Mnemonic::new("roundps", &["roundps"], "", &["imm8, xmm/m, xmm"])
+        .features(&["sse4.1"])
+        .raises(),
+    Mnemonic::new("roundsd", &["roundsd"], "", &["imm8, xmm/m, xmm"])
+        .features(&["sse4.1"])
+        .raises(),
+    Mnemonic::new("pextrb", &["pextrb"], "", &["imm8, xmm, r/m"]).features(&["sse4.1"]),
+    Mnemonic::new("pextrd", &["pextrd"], "", &["imm8, xmm, r/m"]).features(&["sse4.1"]),
+    Mnemonic::new("pextrq", &["pextrq"], "", &["imm8, xmm, r/m"]).features(&["sse4.1"]),
+    Mnemonic::new("pinsrb", &["pinsrb"], "", &["imm8, r/m, xmm"]).features(&["sse4.1"]),
+    Mnemonic::new("pinsrd", &["pinsrd"], "", &["imm8, r/m, xmm"]).features(&["sse4.1"]),
+    Mnemonic::new("pinsrq", &["pinsrq"], "", &["imm8, r/m, xmm"]).features(&["sse4.1"]),
+    Mnemonic::new("movntdqa", &["movntdqa"], "", &["m, xmm"]).features(&["sse4.1"]),
+    sse("pcmpgtq", &["pcmpgtq"], &["sse4.2"]),
+    Mnemonic::new("pcmpestri", &["pcmpestri"], "lq", &["imm8, xmm/m, xmm"])
+        .reads(&["rax", "rdx"])
+        .writes(&["rcx"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .features(&["sse4.2"]),
+    Mnemonic::new("pcmpestrm", &["pcmpestrm"], "lq", &["imm8, xmm/m, xmm"])
+        .reads(&["rax", "rdx"])
+        .writes(&["xmm0"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .features(&["sse4.2"]),
+    Mnemonic::new("pcmpistri", &["pcmpistri"], "", &["imm8, xmm/m, xmm"])
+        .writes(&["rcx"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .features(&["sse4.2"]),
+    Mnemonic::new("pcmpistrm", &["pcmpistrm"], "", &["imm8, xmm/m, xmm"])
+        .writes(&["xmm0"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .read_only()
+        .features(&["sse4.2"]),
+    sse("aesenc", &["aesenc"], &["aes"]),
+    sse("aesenclast", &["aesenclast"], &["aes"]),
+    sse("aesdec", &["aesdec"], &["aes"]),
+    sse("aesdeclast", &["aesdeclast"], &["aes"]),
+    sse("aesimc", &["aesimc"], &["aes"]),
+    Mnemonic::new(
+        "aeskeygenassist",
+        &["aeskeygenassist"],
+        "",
+        &["imm8, xmm/m, xmm"],
+    )
+    .features(&["aes"]),
+    Mnemonic::new("pclmulqdq", &["pclmulqdq"], "", &["imm8, xmm/m, xmm"]).features(&["pclmulqdq"]),
+    sse("sha1nexte", &["sha1nexte"], &["sha"]),
+    sse("sha1msg1", &["sha1msg1"], &["sha"]),
+    sse("sha1msg2", &["sha1msg2"], &["sha"]),
+    Mnemonic::new("sha1rnds4", &["sha1rnds4"], "", &["imm8, xmm/m, xmm"]).features(&["sha"]),
+    Mnemonic::new(
+        "sha256rnds2",
+        &["sha256rnds2"],
+        "",
+        &["%xmm0, xmm/m, xmm", "xmm/m, xmm"],
+    )
+    .reads(&["xmm0"])
+    .features(&["sha"]),
+    sse("sha256msg1", &["sha256msg1"], &["sha"]),
+    sse("sha256msg2", &["sha256msg2"], &["sha"]),
+    Mnemonic::new(
+        "vmovaps",
+        &["vmovaps"],
+        "",
+        &["xmm/m, xmm", "ymm/m, ymm", "xmm, m", "ymm, m"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new(
+        "vmovups",
+        &["vmovups"],
+        "",
+        &["xmm/m, xmm", "ymm/m, ymm", "xmm, m", "ymm, m"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new(
+        "vmovdqa",
+        &["vmovdqa"],
+        "",
+        &["xmm/m, xmm", "ymm/m, ymm", "xmm, m", "ymm, m"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new(
+        "vmovdqu",
+        &["vmovdqu"],
+        "",
+        &["xmm/m, xmm", "ymm/m, ymm", "xmm, m", "ymm, m"],
+    )
+    .features(&["avx"]),
+    avx("vaddps", &["vaddps"], &["avx"]).raises(),
+    avx("vaddpd", &["vaddpd"], &["avx"]).raises(),
+    avx("vsubps", &["vsubps"], &["avx"]).raises(),
+    avx("vmulps", &["vmulps"], &["avx"]).raises(),
+    avx("vmulpd", &["vmulpd"], &["avx"]).raises(),
+    avx("vdivps", &["vdivps"], &["avx"]).raises(),
+    avx("vandps", &["vandps"], &["avx"]).evex_features(&["avx512dq"]),
+    avx("vorps", &["vorps"], &["avx"]).evex_features(&["avx512dq"]),
+    avx("vxorps", &["vxorps"], &["avx"]).evex_features(&["avx512dq"]),
+    avx("vminps", &["vminps"], &["avx"]).raises(),
+    avx("vmaxps", &["vmaxps"], &["avx"]).raises(),
+    Mnemonic::new("vsqrtps", &["vsqrtps"], "", &["xmm/m, xmm", "ymm/m, ymm"])
+        .features(&["avx"])
+        .raises(),
+    Mnemonic::new(
+        "vshufps",
+        &["vshufps"],
+        "",
+        &["imm8, xmm/m, xmm, xmm", "imm8, ymm/m, ymm, ymm"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new("vbroadcastss", &["vbroadcastss"], "", &["m, xmm", "m, ymm"]).features(&["avx"]),
+    Mnemonic::new(
+        "vperm2f128",
+        &["vperm2f128"],
+        "",
+        &["imm8, ymm/m, ymm, ymm"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new(
+        "vinsertf128",
+        &["vinsertf128"],
+        "",
+        &["imm8, xmm/m, ymm, ymm"],
+    )
+    .features(&["avx"]),
+    Mnemonic::new("vextractf128", &["vextractf128"], "", &["imm8, ymm, xmm/m"]).features(&["avx"]),
+    Mnemonic::new("vtestps", &["vtestps"], "", &["xmm/m, xmm", "ymm/m, ymm"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["avx"])
+        .read_only(),
+    Mnemonic::new("vtestpd", &["vtestpd"], "", &["xmm/m, xmm", "ymm/m, ymm"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["avx"])
+        .read_only(),
+    Mnemonic::new("vptest", &["vptest"], "", &["xmm/m, xmm", "ymm/m, ymm"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["avx"])
+        .read_only(),
+    Mnemonic::new(
+        "vcomiss",
+        &["vcomiss", "vcomisd", "vucomiss", "vucomisd"],
+        "",
+        &["xmm/m, xmm"],
+    )
+    .flags(Flags::NONE, Flags::STATUS)
+    .features(&["avx"])
+    .read_only()
+    .raises(),
+    Mnemonic::new("vmovmskps", &["vmovmskps"], "", &["xmm, r", "ymm, r"]).features(&["avx"]),
+    Mnemonic::new("vzeroupper", &["vzeroupper"], "", &[""])
+        .writes(YMM)
+        .features(&["avx"]),
+    Mnemonic::new("vzeroall", &["vzeroall"], "", &[""])
+        .writes(YMM)
+        .features(&["avx"]),
+    avx2("vpaddb", &["vpaddb"]).evex_features(&["avx512bw"]),
+    avx2("vpaddd", &["vpaddd"]),
+    avx2("vpaddq", &["vpaddq"]),
+    avx2("vpsubd", &["vpsubd"]),
+    avx2("vpmulld", &["vpmulld"]),
+    avx2("vpand", &["vpand"]),
+    avx2("vpandn", &["vpandn"]),
+    avx2("vpor", &["vpor"]),
+    avx2("vpxor", &["vpxor"]),
+    avx2("vpcmpeqb", &["vpcmpeqb"]).evex_features(&["avx512bw"]),
+    avx2("vpcmpeqd", &["vpcmpeqd"]),
+    avx2("vpshufb", &["vpshufb"]).evex_features(&["avx512bw"]),
+    avx("vpsllvd", &["vpsllvd"], &["avx2"]),
+    avx("vpsllvq", &["vpsllvq"], &["avx2"]),
+    avx("vpsrlvd", &["vpsrlvd"], &["avx2"]),
+    avx("vpermd", &["vpermd"], &["avx2"]),
+    Mnemonic::new("vpermq", &["vpermq"], "", &["imm8, ymm/m, ymm"]).features(&["avx2"]),
+    Mnemonic::new(
+        "vperm2i128",
+        &["vperm2i128"],
+        "",
+        &["imm8, ymm/m, ymm, ymm"],
+    )
+    .features(&["avx2"]),
+    Mnemonic::new(
+        "vinserti128",
+        &["vinserti128"],
+        "",
+        &["imm8, xmm/m, ymm, ymm"],
+    )
+    .features(&["avx2"]),
+    Mnemonic::new("vextracti128", &["vextracti128"], "", &["imm8, ymm, xmm/m"]).features(&["avx2"]),
+    Mnemonic::new(
+        "vpbroadcast",
+        &["vpbroadcastd", "vpbroadcastq"],
+        "",
+        &["xmm/m, xmm", "xmm/m, ymm"],
+    )
+    .features(&["avx2"]),
+    Mnemonic::new(
+        "vpbroadcastb",
+        &["vpbroadcastb", "vpbroadcastw"],
+        "",
+        &["xmm/m, xmm", "xmm/m, ymm"],
+    )
+    .features(&["avx2"])
+    .evex_features(&["avx512bw"]),
+    Mnemonic::new("vpmovmskb", &["vpmovmskb"], "", &["xmm, r", "ymm, r"])
+        .features(&["avx"])
+        .wide_features(&["avx2"]),
+    // The AVX-512 forms mask their destination with `{%k}` instead.
+    Mnemonic::new(
+        "vpgatherdd",
+        &["vpgatherdd", "vpgatherdq", "vpgatherqd", "vpgatherqq"],
+        "",
+        &["xmm, m, xmm", "ymm, m, ymm", "m, zmm"],
+    )
+    .features(&["avx2"])
+    .writes_all(),
+    Mnemonic::new(
+        "vgatherdps",
+        &["vgatherdps", "vgatherdpd", "vgatherqps", "vgatherqpd"],
+        "",
+        &["xmm, m, xmm", "ymm, m, ymm", "m, zmm"],
+    )
+    .features(&["avx2"])
+    .writes_all(),
+    avx("vfmadd132ps", &["vfmadd132ps"], &["fma"]).raises(),
+    avx("vfmadd213ps", &["vfmadd213ps"], &["fma"]).raises(),
+    avx("vfmadd231ps", &["vfmadd231ps"], &["fma"]).raises(),
+    avx("vfmadd231pd", &["vfmadd231pd"], &["fma"]).raises(),
+    Mnemonic::new("vfmadd231ss", &["vfmadd231ss"], "", &["xmm/m, xmm, xmm"])
+        .features(&["fma"])
+        .raises(),
+    Mnemonic::new("vfmadd231sd", &["vfmadd231sd"], "", &["xmm/m, xmm, xmm"])
+        .features(&["fma"])
+        .raises(),
+    Mnemonic::new(
+        "vcvtph2ps",
+        &["vcvtph2ps"],
+        "",
+        &["xmm/m, xmm", "xmm/m, ymm"],
+    )
+    .features(&["f16c"])
+    .raises(),
+    Mnemonic::new(
+        "vcvtps2ph",
+        &["vcvtps2ph"],
+        "",
+        &["imm8, xmm, xmm/m", "imm8, ymm, xmm/m"],
+    )
+    .features(&["f16c"])
+    .raises(),
+    Mnemonic::new(
+        "vmovdqa32",
+        &["vmovdqa32", "vmovdqa64", "vmovdqu32", "vmovdqu64"],
+        "",
+        &["zmm/m, zmm", "zmm, m"],
+    )
+    .features(&["avx512f"]),
+    Mnemonic::new(
+        "vmovdqu8",
+        &["vmovdqu8", "vmovdqu16"],
+        "",
+        &["zmm/m, zmm", "zmm, m"],
+    )
+    .features(&["avx512bw"]),
+    Mnemonic::new(
+        "vpternlogd",
+        &["vpternlogd", "vpternlogq"],
+        "",
+        &["imm8, zmm/m, zmm, zmm"],
+    )
+    .features(&["avx512f"]),
+    Mnemonic::new(
+        "vpcompressd",
+        &["vpcompressd", "vpcompressq"],
+        "",
+        &["zmm, zmm/m"],
+    )
+    .features(&["avx512f"]),
+    Mnemonic::new(
+        "vpexpandd",
+        &["vpexpandd", "vpexpandq"],
+        "",
+        &["zmm/m, zmm"],
+    )
+    .features(&["avx512f"]),
+    Mnemonic::new("vpermb", &["vpermb"], "", &["zmm/m, zmm, zmm"]).features(&["avx512vbmi"]),
+    Mnemonic::new("vpdpbusd", &["vpdpbusd"], "", &["zmm/m, zmm, zmm"]).features(&["avx512vnni"]),
+    Mnemonic::new("kmovw", &["kmovw"], "", &["k/m, k", "k, m", "r, k", "k, r"])
+        .features(&["avx512f"]),
+    Mnemonic::new("kmovb", &["kmovb"], "", &["k/m, k", "k, m", "r, k", "k, r"])
+        .features(&["avx512dq"]),
+    Mnemonic::new(
+        "kmovd",
+        &["kmovd", "kmovq"],
+        "",
+        &["k/m, k", "k, m", "r, k", "k, r"],
+    )
+    .features(&["avx512bw"]),
+    Mnemonic::new("kandw", &["kandw"], "", &["k, k, k"]).features(&["avx512f"]),
+    Mnemonic::new("korw", &["korw"], "", &["k, k, k"]).features(&["avx512f"]),
+    Mnemonic::new("kxorw", &["kxorw"], "", &["k, k, k"]).features(&["avx512f"]),
+    Mnemonic::new("knotw", &["knotw"], "", &["k, k"]).features(&["avx512f"]),
+    Mnemonic::new("kortestw", &["kortestw"], "", &["k, k"])
+        .flags(Flags::NONE, Flags::STATUS)
+        .features(&["avx512f"])
+        .read_only(),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_spellings() {
+        let cmps = lookup("cmpsb").unwrap();
+        assert_eq!(cmps.name, "cmps");
+        assert_eq!(cmps.writes, ["rsi", "rdi"]);
+        assert_eq!(cmps.flags_written, Flags::STATUS);
+        assert_eq!(lookup("movq").unwrap().name, "mov");
+        assert_eq!(lookup("salq").unwrap().name, "shl");
+        assert_eq!(lookup("cltq").unwrap().name, "cdqe");
+        assert_eq!(lookup("cdqe").unwrap().name, "cdqe");
+        assert_eq!(lookup("jnae").unwrap().name, "jb");
+        assert_eq!(lookup("setnzb").unwrap().name, "setne");
+        assert_eq!(lookup("cmovl").unwrap().name, "cmovl");
+        assert_eq!(lookup("cmovll").unwrap().name, "cmovl");
+        assert_eq!(lookup("cmpsd").unwrap().features, ["sse2"]);
+        assert_eq!(lookup("cmpsl").unwrap().name, "cmps");
+        assert_eq!(lookup("vpbroadcastd").unwrap().name, "vpbroadcast");
+        assert!(lookup("addd").is_none());
+        assert!(lookup("").is_none());
+    }
+
+    #[test]
+    fn records_effects() {
+        let jg = lookup("jg").unwrap();
+        assert_eq!(jg.flags_read, Flags::ZF | Flags::SF | Flags::OF);
+        assert_eq!(jg.flags_read.to_string(), "ZF, SF, OF");
+        let inc = lookup("incq").unwrap();
+        assert!(!inc.flags_written.contains(Flags::CF));
+        assert!(inc.flags_written.contains(Flags::ZF));
+        assert_eq!(lookup("mulq").unwrap().writes, ["rax", "rdx"]);
+        assert_eq!(lookup("cpuid").unwrap().reads, ["rax", "rcx"]);
+        assert_eq!(lookup("hlt").unwrap().privilege, Privilege::Kernel);
+        assert_eq!(lookup("rdtsc").unwrap().privilege, Privilege::User);
+        assert_eq!(lookup("vpaddd").unwrap().features, ["avx"]);
+        assert_eq!(lookup("vpaddd").unwrap().wide_features, ["avx2"]);
+        assert!(lookup("add").unwrap().features.is_empty());
+    }
+
+    #[test]
+    fn finds_features() {
+        let features = |text: &str| features(&text.parse::<Instruction>().unwrap());
+        assert_eq!(features("pdep %rax, %rbx, %rcx"), ["bmi2"]);
+        assert_eq!(features("vfmadd231ps %ymm0, %ymm1, %ymm2"), ["fma"]);
+        assert_eq!(features("vpxor %xmm0, %xmm0, %xmm0"), ["avx"]);
+        assert_eq!(features("vpshufb (%rdi), %ymm1, %ymm2"), ["avx2"]);
+        assert_eq!(features("vpmovmskb %xmm0, %eax"), ["avx"]);
+        assert_eq!(
+            features("vaddps %xmm16, %xmm1, %xmm2"),
+            ["avx512f", "avx512vl"]
+        );
+        assert_eq!(
+            features("vmovups (%rdi), %ymm0{{%k1}}"),
+            ["avx512f", "avx512vl"]
+        );
+        assert_eq!(
+            features("vpaddb %zmm0, %zmm1, %zmm2"),
+            ["avx512f", "avx512bw"]
+        );
+        assert_eq!(
+            features("vxorps %zmm0, %zmm1, %zmm2"),
+            ["avx512f", "avx512dq"]
+        );
+        assert_eq!(
+            features("vpbroadcastw %xmm0, %zmm1"),
+            ["avx512f", "avx512bw"]
+        );
+        assert_eq!(features("vmovdqu8 %ymm0, %ymm1"), ["avx512bw", "avx512vl"]);
+        assert_eq!(features("vfmadd231ss %xmm16, %xmm1, %xmm2"), ["avx512f"]);
+        assert_eq!(features("kmovd %k1, %eax"), ["avx512bw"]);
+        assert_eq!(features("pshufb %xmm1, %xmm0"), ["ssse3"]);
+        assert!(features("vfoo %zmm0, %zmm1").is_empty());
+    }
+
+    #[test]
+    fn spellings_are_unambiguous() {
+        for mnemonic in mnemonics() {
+            for spelling in mnemonic.spellings() {
+                assert_eq!(lookup(&spelling), Some(mnemonic), "{spelling}");
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+#![deny(missing_docs)]
+#![no_std]
+
+//!
+//! AT&T assembly model shared by [`asm_att`](https://docs.rs/asm_att) and its
+//! procedural backend. Use it through the re-exports of `asm_att`.
+
+extern crate alloc;
+
+pub mod convert;
+pub mod isa;
+pub mod lint;
+pub mod syntax;
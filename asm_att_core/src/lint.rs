@@ -0,0 +1,448 @@
+//! Lints over the `asm_att!`, `global_asm_att!` and `naked_asm_att!` calls of
+//! Rust source.
+//!
+//! The lints look for mistakes the compiler and the assembler let through:
+//! see [`Lint`]. Like the inference of `asm_att_auto!`, they rely on what is
+//! known of the instructions, and say nothing of the ones they do not know.
+//!
+//! ```
+//! use asm_att_core::lint::{Lint, lint};
+//!
+//! let source = r#"asm_att!("incq {x}", "movl $0, %ecx", x = in(reg) x);"#;
+//! let report = lint(source).unwrap();
+//! let lints: Vec<Lint> = report.diagnostics.iter().map(|d| d.lint).collect();
+//! assert_eq!(lints, [Lint::WriteToInput, Lint::MissingClobber]);
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::convert::source::{
+    self, MacroCall, OperandArg, Token, is_group, line_offset, macro_calls, operand_arg,
+    parse_template,
+};
+use crate::convert::{Skipped, SourceError};
+use crate::syntax::{
+    Instruction, Location, MemoryOperand, Operand, Placeholder, PlaceholderArg, Register,
+    Statement, Template,
+};
+
+/// The macros whose calls are linted.
+const MACROS: &[&str] = &["asm_att", "global_asm_att", "naked_asm_att"];
+
+/// Registers the compiler relies on, which can be neither operands nor
+/// clobbers, besides the stack pointer.
+const RESERVED: &[&str] = &["rbx", "rbp"];
+
+/// Registers a function must restore before returning, besides the stack
+/// pointer.
+const CALLEE_SAVED: &[&str] = &["rbx", "rbp", "r12", "r13", "r14", "r15"];
+
+/// The mistakes [`lint`] looks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// An instruction of `asm_att!` writes an `in` operand, which the
+    /// compiler assumes keeps its value.
+    WriteToInput,
+    /// An instruction of `asm_att!` writes a register named in the template
+    /// that is neither an output nor clobbered, or `%rbx` or `%rbp`, which
+    /// cannot be clobbered, without restoring it.
+    MissingClobber,
+    /// An instruction of `asm_att!` uses the stack despite
+    /// `options(nostack)`.
+    Nostack,
+    /// `asm_att!` defines a named label, which is defined twice if the
+    /// compiler duplicates the code, such as by inlining it.
+    NamedLabel,
+    /// An instruction of `naked_asm_att!` modifies a callee-saved register
+    /// that the function does not push and pop.
+    CalleeSaved,
+}
+
+impl Lint {
+    /// The name of the lint, such as `write-to-input`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::WriteToInput => "write-to-input",
+            Lint::MissingClobber => "missing-clobber",
+            Lint::Nostack => "nostack",
+            Lint::NamedLabel => "named-label",
+            Lint::CalleeSaved => "callee-saved",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A mistake found by [`lint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Where the offending statement, or its template literal, starts in the
+    /// source.
+    pub offset: usize,
+    /// The lint that found it.
+    pub lint: Lint,
+    /// A description of the mistake.
+    pub message: String,
+}
+
+/// The outcome of [`lint`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The mistakes found, in order.
+    pub diagnostics: Vec<Diagnostic>,
+    /// How many calls were checked.
+    pub checked: usize,
+    /// The calls that could not be checked, such as because their template
+    /// is built by a macro.
+    pub skipped: Vec<Skipped>,
+}
+
+/// Checks the `asm_att!`, `global_asm_att!` and `naked_asm_att!` calls of a
+/// Rust source file.
+pub fn lint(source: &str) -> Result<Report, SourceError> {
+    let tokens = source::tokenize(source)?;
+    let mut report = Report::default();
+    for call in macro_calls(source, &tokens, MACROS) {
+        let parsed = call
+            .templates(source)
+            .and_then(|(literals, values)| Ok((literals, parse_template(source, &call, &values)?)));
+        let (literals, template) = match parsed {
+            Ok(parsed) => parsed,
+            Err(reason) => {
+                report.skipped.push(Skipped {
+                    offset: call.path.start,
+                    reason,
+                });
+                continue;
+            }
+        };
+        report.checked += 1;
+        let mut lints = Lints {
+            source,
+            literals: &literals,
+            diagnostics: &mut report.diagnostics,
+        };
+        match call.name.text(source) {
+            "asm_att" => lints.inline(&call, &template),
+            "naked_asm_att" => lints.naked(&template),
+            _ => {}
+        }
+    }
+    Ok(report)
+}
+
+/// The diagnostics of one call.
+struct Lints<'a> {
+    source: &'a str,
+    literals: &'a [Token],
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Lints<'_> {
+    fn report(&mut self, location: Location, lint: Lint, message: String) {
+        let literal = self.literals[location.template];
+        self.diagnostics.push(Diagnostic {
+            offset: line_offset(self.source, literal, location.line),
+            lint,
+            message,
+        });
+    }
+
+    fn inline(&mut self, call: &MacroCall<'_>, template: &Template) {
+        let source = self.source;
+        let operands: Vec<OperandArg<'_>> = call
+            .args
+            .iter()
+            .filter_map(|arg| operand_arg(source, arg))
+            .collect();
+        let clobber_abi = call
+            .args
+            .iter()
+            .any(|arg| is_group(source, arg, "clobber_abi"));
+        let nostack = call.args.iter().any(|arg| {
+            is_group(source, arg, "options")
+                && arg.iter().any(|token| token.text(source) == "nostack")
+        });
+        let bound = |family: &str| {
+            operands.iter().find(|operand| {
+                operand
+                    .register
+                    .as_ref()
+                    .and_then(|register| Register::new(register.as_str()).family())
+                    .is_some_and(|bound| bound == family)
+            })
+        };
+
+        let mut next = 0;
+        let mut reported: Vec<String> = Vec::new();
+        let mut stack = false;
+        for (location, statement) in &template.statements {
+            let instruction = match statement {
+                Statement::Label(label) if !label.name.bytes().all(|b| b.is_ascii_digit()) => {
+                    let message = format!(
+                        "the named label `{0}:` is defined twice if the code is duplicated, \
+                         such as by inlining; use `{{label:{0}}}:` or a numeric local label \
+                         such as `1:`",
+                        label.name
+                    );
+                    self.report(*location, Lint::NamedLabel, message);
+                    continue;
+                }
+                Statement::Instruction(instruction) => instruction,
+                _ => continue,
+            };
+
+            let first_written = instruction.operands.len() - instruction.written_operands().len();
+            for (index, operand) in instruction.operands.iter().enumerate() {
+                let mut placeholders = Vec::new();
+                collect_placeholders(operand, &mut placeholders);
+                for placeholder in placeholders {
+                    let target = match &placeholder.arg {
+                        PlaceholderArg::Next => {
+                            next += 1;
+                            operands.get(next - 1)
+                        }
+                        PlaceholderArg::Index(index) => operands.get(*index),
+                        PlaceholderArg::Name(name) => operands
+                            .iter()
+                            .find(|operand| operand.name == Some(name.as_str())),
+                    };
+                    let written = index >= first_written
+                        && matches!(operand.undecorated(), Operand::Placeholder(_));
+                    if written && target.is_some_and(|target| target.keyword == "in") {
+                        let message = format!(
+                            "`{instruction}` writes `{placeholder}`, which is an `in` operand; \
+                             declare it with `inout` instead"
+                        );
+                        self.report(*location, Lint::WriteToInput, message);
+                    }
+                }
+            }
+
+            for name in instruction.written_registers() {
+                let Some(family) = Register::new(name.as_str()).family() else {
+                    continue;
+                };
+                if family == "rsp" || reported.contains(&family) {
+                    continue;
+                }
+                let (lint, message) = match bound(&family) {
+                    Some(operand) if operand.keyword == "in" => (
+                        Lint::WriteToInput,
+                        format!(
+                            "`{instruction}` writes `%{name}`, which is bound to an `in` \
+                             operand; declare it with `inout` instead"
+                        ),
+                    ),
+                    Some(_) => continue,
+                    None if RESERVED.contains(&family.as_str()) => {
+                        if template.restores(&family) {
+                            continue;
+                        }
+                        (
+                            Lint::MissingClobber,
+                            format!(
+                                "`{instruction}` writes `%{name}`, which cannot be clobbered; \
+                                 push and pop it around the code"
+                            ),
+                        )
+                    }
+                    // `clobber_abi("C")` clobbers all but the callee-saved registers.
+                    None if clobber_abi && !CALLEE_SAVED.contains(&family.as_str()) => continue,
+                    None => {
+                        let clobber = if family.starts_with("xmm") {
+                            &name
+                        } else {
+                            &family
+                        };
+                        (
+                            Lint::MissingClobber,
+                            format!(
+                                "`{instruction}` writes `%{name}`, which is neither an output \
+                                 nor clobbered; add `out(\"{clobber}\") _`"
+                            ),
+                        )
+                    }
+                };
+                reported.push(family);
+                self.report(*location, lint, message);
+            }
+
+            if nostack && !stack && instruction.uses_stack() {
+                stack = true;
+                let message = format!(
+                    "`{instruction}` uses the stack, which `options(nostack)` promises not to"
+                );
+                self.report(*location, Lint::Nostack, message);
+            }
+        }
+    }
+
+    fn naked(&mut self, template: &Template) {
+        let mut reported: Vec<String> = Vec::new();
+        for (location, instruction) in template.instructions() {
+            if is(instruction, "pop") || is(instruction, "leave") {
+                continue;
+            }
+            for name in instruction.written_registers() {
+                let Some(family) = Register::new(name.as_str()).family() else {
+                    continue;
+                };
+                if !CALLEE_SAVED.contains(&family.as_str())
+                    || reported.contains(&family)
+                    || template.restores(&family)
+                {
+                    continue;
+                }
+                let message = format!(
+                    "`{instruction}` modifies the callee-saved `%{name}` without restoring it; \
+                     push and pop it around the code"
+                );
+                reported.push(family);
+                self.report(location, Lint::CalleeSaved, message);
+            }
+        }
+    }
+}
+
+/// Whether the instruction is `stem`, with or without a size suffix.
+fn is(instruction: &Instruction, stem: &str) -> bool {
+    instruction
+        .mnemonic
+        .to_ascii_lowercase()
+        .strip_prefix(stem)
+        .is_some_and(|suffix| matches!(suffix, "" | "w" | "l" | "q"))
+}
+
+/// The placeholders of an operand, in the order they are written.
+fn collect_placeholders<'a>(operand: &'a Operand, placeholders: &mut Vec<&'a Placeholder>) {
+    match operand {
+        Operand::Placeholder(placeholder) => placeholders.push(placeholder),
+        Operand::Indirect(operand) | Operand::Decorated(operand, _) => {
+            collect_placeholders(operand, placeholders)
+        }
+        Operand::Memory(MemoryOperand {
+            segment,
+            base,
+            index,
+            ..
+        }) => {
+            for operand in [segment, base, index].into_iter().flatten() {
+                collect_placeholders(operand, placeholders);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn lints(source: &str) -> Vec<(Lint, String)> {
+        lint(source)
+            .unwrap()
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.lint, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn finds_writes_to_inputs() {
+        let source = r#"
+            asm_att!("addq {}, {}", in(reg) a, in(reg) b);
+            asm_att!("addq {a}, {b}", "cpuid", a = in(reg) a, b = inout(reg) b, in("eax") 0);
+            asm_att!("movl (%rsi), {0:e}", "movl {0:e}, (%rdi)", in(reg) x);
+        "#;
+        assert_eq!(
+            lints(source),
+            [
+                (
+                    Lint::WriteToInput,
+                    "`addq {}, {}` writes `{}`, which is an `in` operand; declare it with `inout` instead"
+                        .to_string()
+                ),
+                (
+                    Lint::WriteToInput,
+                    "`cpuid` writes `%rax`, which is bound to an `in` operand; declare it with `inout` instead"
+                        .to_string()
+                ),
+                (
+                    Lint::MissingClobber,
+                    "`cpuid` writes `%rbx`, which cannot be clobbered; push and pop it around the code"
+                        .to_string()
+                ),
+                (
+                    Lint::MissingClobber,
+                    "`cpuid` writes `%rcx`, which is neither an output nor clobbered; add `out(\"rcx\") _`"
+                        .to_string()
+                ),
+                (
+                    Lint::MissingClobber,
+                    "`cpuid` writes `%rdx`, which is neither an output nor clobbered; add `out(\"rdx\") _`"
+                        .to_string()
+                ),
+                (
+                    Lint::WriteToInput,
+                    "`movl (%rsi), {0:e}` writes `{0:e}`, which is an `in` operand; declare it with `inout` instead"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_declared_clobbers() {
+        let source = r#"
+            asm_att!("pushq %rbx", "cpuid", "popq %rbx", inout("eax") 0 => _, out("ecx") _, out("edx") _);
+            asm_att!("call {f}", "vmovaps %ymm0, %ymm1", f = sym f, clobber_abi("C"));
+            asm_att!("movq {x}, 8(%rsp)", "1:", "decq {x}", "jnz 1b", x = inout(reg) x, options(nostack));
+        "#;
+        assert_eq!(lints(source), []);
+    }
+
+    #[test]
+    fn finds_stack_and_label_misuse() {
+        let source = r#"
+            asm_att!("loop:", "pushq {x}", "popq {x}", "movq %rax, -8(%rsp)", x = inout(reg) x, out("rax") _, options(nostack));
+        "#;
+        let found: Vec<Lint> = lints(source).into_iter().map(|(lint, _)| lint).collect();
+        assert_eq!(found, [Lint::NamedLabel, Lint::Nostack]);
+    }
+
+    #[test]
+    fn finds_unrestored_callee_saved_registers() {
+        let source = r#"
+            naked_asm_att!(
+                "pushq %rbx",
+                "movl $1, %ebx",
+                "movl $2, %r12d",
+                "popq %rbx",
+                "ret",
+            );
+            global_asm_att!("f:", "movl $2, %r12d", "ret");
+        "#;
+        let report = lint(source).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(
+            report.diagnostics,
+            [Diagnostic {
+                offset: source.find("\"movl $2").unwrap(),
+                lint: Lint::CalleeSaved,
+                message:
+                    "`movl $2, %r12d` modifies the callee-saved `%r12d` without restoring it; \
+                          push and pop it around the code"
+                        .to_string(),
+            }]
+        );
+    }
+}
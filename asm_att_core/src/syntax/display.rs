@@ -0,0 +1,475 @@
+//! AT&T and Intel printing of the syntax tree.
+
+use alloc::borrow::Cow;
+use alloc::format;
+use core::fmt::{self, Display, Formatter, Write};
+
+use super::size::{SUFFIXED, Size, split_memory_suffix};
+use super::{
+    Decorator, Directive, Instruction, Label, MemoryOperand, Operand, Placeholder, PlaceholderArg,
+    Register, Statement, Template,
+};
+
+/// Prints the wrapped value in Intel syntax, as accepted by `core::arch::asm!`
+/// without `options(att_syntax)`.
+///
+/// Created by the `intel()` methods of the syntax types.
+#[derive(Clone, Copy, Debug)]
+pub struct Intel<'a, T: ?Sized>(&'a T);
+
+macro_rules! intel_method {
+    ($($ty:ty),*) => {$(
+        impl $ty {
+            /// Returns a value that prints `self` in Intel syntax.
+            pub fn intel(&self) -> Intel<'_, Self> {
+                Intel(self)
+            }
+        }
+    )*};
+}
+
+intel_method!(
+    Template,
+    Statement,
+    Instruction,
+    Operand,
+    Register,
+    MemoryOperand
+);
+
+fn write_separated<T>(
+    f: &mut Formatter<'_>,
+    items: impl IntoIterator<Item = T>,
+    separator: &str,
+    mut write: impl FnMut(&mut Formatter<'_>, T) -> fmt::Result,
+) -> fmt::Result {
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        write(f, item)?;
+    }
+    Ok(())
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_separated(f, &self.statements, "\n", |f, (_, statement)| {
+            statement.fmt(f)
+        })
+    }
+}
+
+impl Display for Intel<'_, Template> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_separated(f, &self.0.statements, "\n", |f, (_, statement)| {
+            statement.intel().fmt(f)
+        })
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Label(label) => label.fmt(f),
+            Statement::Directive(directive) => directive.fmt(f),
+            Statement::Instruction(instruction) => instruction.fmt(f),
+        }
+    }
+}
+
+impl Display for Intel<'_, Statement> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Statement::Instruction(instruction) => instruction.intel().fmt(f),
+            statement => statement.fmt(f),
+        }
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.name)
+    }
+}
+
+impl Display for Directive {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.args.is_empty() {
+            write!(f, " {}", self.args)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for prefix in &self.prefixes {
+            write!(f, "{prefix} ")?;
+        }
+        f.write_str(&self.mnemonic)?;
+        if !self.operands.is_empty() {
+            f.write_char(' ')?;
+            write_separated(f, &self.operands, ", ", |f, operand| operand.fmt(f))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Intel<'_, Instruction> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let instruction = self.0;
+        for prefix in &instruction.prefixes {
+            write!(f, "{prefix} ")?;
+        }
+        let (mnemonic, size) = intel_mnemonic(&instruction.mnemonic, &instruction.operands);
+        f.write_str(&mnemonic)?;
+        if !instruction.operands.is_empty() {
+            f.write_char(' ')?;
+            let operands = &instruction.operands;
+            let branch = instruction.is_branch();
+            let write = |f: &mut Formatter<'_>, operand| {
+                if branch {
+                    write_intel_target(f, operand, size)
+                } else {
+                    write_intel_operand(f, operand, size)
+                }
+            };
+            if keeps_operand_order(&instruction.mnemonic, operands) {
+                write_separated(f, operands, ", ", write)?;
+            } else {
+                write_separated(f, operands.iter().rev(), ", ", write)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(register) => register.fmt(f),
+            Operand::Placeholder(placeholder) => placeholder.fmt(f),
+            Operand::Immediate(value) => write!(f, "${value}"),
+            Operand::Memory(memory) => memory.fmt(f),
+            Operand::Indirect(operand) => write!(f, "*{operand}"),
+            Operand::Expression(expression) => f.write_str(expression),
+            Operand::Decorated(operand, decorators) => {
+                operand.fmt(f)?;
+                decorators.iter().try_for_each(|decorator| decorator.fmt(f))
+            }
+            Operand::Decorator(decorator) => decorator.fmt(f),
+        }
+    }
+}
+
+impl Display for Intel<'_, Operand> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_intel_operand(f, self.0, None)
+    }
+}
+
+/// Writes the operand of a branch, where an expression is the target itself
+/// unless the branch is indirect.
+fn write_intel_target(f: &mut Formatter<'_>, operand: &Operand, size: Option<&str>) -> fmt::Result {
+    match operand {
+        Operand::Expression(expression) => f.write_str(expression),
+        operand => write_intel_operand(f, operand, size),
+    }
+}
+
+fn write_intel_operand(
+    f: &mut Formatter<'_>,
+    operand: &Operand,
+    size: Option<&str>,
+) -> fmt::Result {
+    match operand {
+        Operand::Register(register) => register.intel().fmt(f),
+        Operand::Immediate(value)
+            if value.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '.') =>
+        {
+            write!(f, "offset {value}")
+        }
+        Operand::Immediate(value) => f.write_str(value),
+        Operand::Memory(memory) => {
+            if let Some(size) = size {
+                write!(f, "{size} ptr ")?;
+            }
+            memory.intel().fmt(f)
+        }
+        // An absolute address such as `0x10` or `foo+8`.
+        Operand::Expression(expression) => {
+            if let Some(size) = size {
+                write!(f, "{size} ptr ")?;
+            }
+            write!(f, "[{expression}]")
+        }
+        Operand::Indirect(operand) => write_intel_operand(f, operand, size),
+        Operand::Decorated(operand, decorators) => {
+            write_intel_operand(f, operand, size)?;
+            decorators.iter().try_for_each(|decorator| match decorator {
+                Decorator::Mask(register) => write!(f, "{{{{{}}}}}", register.intel()),
+                decorator => decorator.fmt(f),
+            })
+        }
+        Operand::Placeholder(_) | Operand::Decorator(_) => operand.fmt(f),
+    }
+}
+
+impl Display for Decorator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Decorator::Mask(register) => write!(f, "{{{{{register}}}}}"),
+            Decorator::Zeroing => f.write_str("{{z}}"),
+            Decorator::Broadcast(count) => write!(f, "{{{{1to{count}}}}}"),
+            Decorator::Rounding(rounding) => write!(f, "{{{{{rounding}}}}}"),
+        }
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl Display for Intel<'_, Register> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.name())
+    }
+}
+
+impl Display for MemoryOperand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(segment) = &self.segment {
+            write!(f, "{segment}:")?;
+        }
+        f.write_str(&self.displacement)?;
+        if self.base.is_none() && self.index.is_none() {
+            return Ok(());
+        }
+        f.write_char('(')?;
+        if let Some(base) = &self.base {
+            base.fmt(f)?;
+        }
+        if let Some(index) = &self.index {
+            write!(f, ",{index}")?;
+            if let Some(scale) = self.scale {
+                write!(f, ",{scale}")?;
+            }
+        }
+        f.write_char(')')
+    }
+}
+
+impl Display for Intel<'_, MemoryOperand> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let memory = self.0;
+        if let Some(segment) = &memory.segment {
+            write!(f, "{}:", segment.intel())?;
+        }
+        f.write_char('[')?;
+        let mut empty = true;
+        if let Some(base) = &memory.base {
+            base.intel().fmt(f)?;
+            empty = false;
+        }
+        if let Some(index) = &memory.index {
+            if !empty {
+                f.write_str(" + ")?;
+            }
+            index.intel().fmt(f)?;
+            if let Some(scale) = memory.scale {
+                write!(f, "*{scale}")?;
+            }
+            empty = false;
+        }
+        let displacement = memory.displacement.trim();
+        if !displacement.is_empty() {
+            match displacement.strip_prefix('-') {
+                Some(negated) if !empty => write!(f, " - {negated}")?,
+                _ if !empty => write!(f, " + {displacement}")?,
+                _ => f.write_str(displacement)?,
+            }
+        }
+        f.write_char(']')
+    }
+}
+
+impl Display for Placeholder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('{')?;
+        match &self.arg {
+            PlaceholderArg::Next => {}
+            PlaceholderArg::Index(index) => write!(f, "{index}")?,
+            PlaceholderArg::Name(name) => f.write_str(name)?,
+        }
+        if let Some(modifier) = &self.modifier {
+            write!(f, ":{modifier}")?;
+        }
+        f.write_char('}')
+    }
+}
+
+/// String instructions whose AT&T `l` suffix is a `d` suffix in Intel syntax.
+pub(super) const STRING_INSTRUCTIONS: &[&str] =
+    &["cmps", "ins", "lods", "movs", "outs", "scas", "stos"];
+
+/// Whether the operands are in the same order in AT&T and Intel syntax: those
+/// of `bound` and `invlpga`, and of instructions with two immediates such as
+/// `enter`.
+pub(super) fn keeps_operand_order(mnemonic: &str, operands: &[Operand]) -> bool {
+    let immediates = operands
+        .iter()
+        .all(|operand| matches!(operand, Operand::Immediate(_)));
+    matches!(mnemonic.to_ascii_lowercase().as_str(), "bound" | "invlpga")
+        || operands.len() > 1 && immediates
+}
+
+/// Sign extensions named differently in AT&T and Intel syntax.
+pub(super) const RENAMED: &[(&str, &str)] = &[
+    ("cbtw", "cbw"),
+    ("cwtl", "cwde"),
+    ("cltq", "cdqe"),
+    ("cwtd", "cwd"),
+    ("cltd", "cdq"),
+    ("cqto", "cqo"),
+];
+
+fn size_keyword(suffix: u8) -> Option<&'static str> {
+    Size::from_suffix(suffix as char).map(Size::intel_keyword)
+}
+
+/// Whether an operand is an `%xmm` or `%mm` register, which make `movq` the
+/// SSE2 or MMX move rather than the general-purpose one.
+fn is_vector(operand: &Operand) -> bool {
+    let Operand::Register(register) = operand.undecorated() else {
+        return false;
+    };
+    let name = register.name().to_ascii_lowercase();
+    name.strip_prefix("xmm")
+        .or_else(|| name.strip_prefix("mm"))
+        .is_some_and(|number| number.parse::<u8>().is_ok())
+}
+
+/// Translates an AT&T mnemonic to Intel, together with the size keyword its
+/// memory operand needs.
+fn intel_mnemonic<'a>(
+    mnemonic: &'a str,
+    operands: &[Operand],
+) -> (Cow<'a, str>, Option<&'static str>) {
+    let lower = mnemonic.to_ascii_lowercase();
+    if let Some((_, intel)) = RENAMED.iter().find(|(att, _)| *att == lower) {
+        return (Cow::Borrowed(intel), None);
+    }
+
+    let bytes = lower.as_bytes();
+    if bytes.len() == 6
+        && (lower.starts_with("movz") || lower.starts_with("movs"))
+        && let (Some(source), Some(_)) = (size_keyword(bytes[4]), size_keyword(bytes[5]))
+    {
+        let intel = match &lower[..4] {
+            "movz" => "movzx",
+            _ if &lower[4..] == "lq" => "movsxd",
+            _ => "movsx",
+        };
+        return (Cow::Borrowed(intel), Some(source));
+    }
+
+    if let Some((stem, keyword)) = split_memory_suffix(&lower) {
+        return (Cow::Owned(stem.into()), Some(keyword));
+    }
+
+    let Some((&suffix, stem)) = bytes.split_last() else {
+        return (Cow::Borrowed(mnemonic), None);
+    };
+    let stem = &lower[..stem.len()];
+    if suffix == b'l' && STRING_INSTRUCTIONS.contains(&stem) {
+        return (Cow::Owned(format!("{stem}d")), None);
+    }
+    let Some(size) = size_keyword(suffix).filter(|_| SUFFIXED.contains(&stem)) else {
+        return (Cow::Borrowed(mnemonic), None);
+    };
+    // Intel syntax has no size for immediates, so `pushw $1` keeps its
+    // suffix, as does the `movq` of SSE2 and MMX.
+    let immediates = !operands.is_empty()
+        && operands
+            .iter()
+            .all(|operand| matches!(operand, Operand::Immediate(_)));
+    if immediates || stem == "mov" && operands.iter().any(is_vector) {
+        return (Cow::Borrowed(mnemonic), Some(size));
+    }
+    (Cow::Owned(stem.into()), Some(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    fn att(text: &str) -> alloc::string::String {
+        text.parse::<Template>().unwrap().to_string()
+    }
+
+    fn intel(text: &str) -> alloc::string::String {
+        text.parse::<Template>().unwrap().intel().to_string()
+    }
+
+    #[test]
+    fn prints_att() {
+        assert_eq!(att("  repe   cmpsb"), "repe cmpsb");
+        assert_eq!(att("1:inc {c}"), "1:\ninc {c}");
+        assert_eq!(
+            att("movq %fs:-8({base},%rcx,8),%rax"),
+            "movq %fs:-8({base},%rcx,8), %rax"
+        );
+        assert_eq!(att("jmp *{0:e}"), "jmp *{0:e}");
+        assert_eq!(att(".global  add2"), ".global add2");
+        assert_eq!(
+            att("vaddps {{rn-sae}}, (%rax){{1to16}}, %zmm1, %zmm2 {{%k1}}{{z}}"),
+            "vaddps {{rn-sae}}, (%rax){{1to16}}, %zmm1, %zmm2{{%k1}}{{z}}"
+        );
+    }
+
+    #[test]
+    fn prints_intel() {
+        assert_eq!(intel("movq {src}, %rsi"), "mov rsi, {src}");
+        assert_eq!(intel("cmp $0, {n}"), "cmp {n}, 0");
+        assert_eq!(intel("movl $1, -8(%rbp)"), "mov dword ptr [rbp - 8], 1");
+        assert_eq!(intel("lea foo(%rip), %rax"), "lea rax, [rip + foo]");
+        assert_eq!(
+            intel("movzbl (%rsi,%rcx), %eax"),
+            "movzx eax, byte ptr [rsi + rcx]"
+        );
+        assert_eq!(intel("movq %fs:0x28, %rax"), "mov rax, qword ptr fs:[0x28]");
+        assert_eq!(intel("rep stosl"), "rep stosd");
+        assert_eq!(intel("cltq"), "cdqe");
+        assert_eq!(intel("call *{f}"), "call {f}");
+        assert_eq!(intel("jne 1b"), "jne 1b");
+        assert_eq!(intel("shl %cl, %eax"), "shl eax, cl");
+        assert_eq!(intel("movq $foo, %rax"), "mov rax, offset foo");
+        assert_eq!(intel("fldt (%rax)"), "fld tbyte ptr [rax]");
+        assert_eq!(intel("fistpll 8(%rsp)"), "fistp qword ptr [rsp + 8]");
+        assert_eq!(intel("fmul %st(1), %st"), "fmul st, st(1)");
+        assert_eq!(intel("enter $16, $0"), "enter 16, 0");
+        assert_eq!(intel("movl 0x10, %eax"), "mov eax, dword ptr [0x10]");
+        assert_eq!(
+            intel("movabsq 0x1234, %rax"),
+            "movabs rax, qword ptr [0x1234]"
+        );
+        assert_eq!(intel("jmp foo+8"), "jmp foo+8");
+        assert_eq!(intel("movq %rax, %xmm0"), "movq xmm0, rax");
+        assert_eq!(intel("movq %xmm0, %xmm1"), "movq xmm1, xmm0");
+        assert_eq!(intel("movq (%rax), %xmm0"), "movq xmm0, qword ptr [rax]");
+        assert_eq!(intel("movq %mm0, %rax"), "movq rax, mm0");
+        assert_eq!(intel("pushw $1"), "pushw 1");
+        assert_eq!(intel("pushq $1"), "pushq 1");
+        assert_eq!(intel("pushq %rax"), "push rax");
+        assert_eq!(
+            intel("vaddps {{rn-sae}}, %zmm0, %zmm1, %zmm2{{%k1}}{{z}}"),
+            "vaddps zmm2{{k1}}{{z}}, zmm1, zmm0, {{rn-sae}}"
+        );
+    }
+}
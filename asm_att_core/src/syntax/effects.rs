@@ -0,0 +1,315 @@
+//! What instructions do besides computing their destination: which registers
+//! they write, and whether they touch the flags, memory or the stack.
+//!
+//! The answers come from the [`isa`](crate::isa) table, and err on the side of
+//! caution: an instruction that is not in the table, that needs the kernel's
+//! privileges, or that names a control, debug or segment register is assumed
+//! to touch the flags and memory, as it may change how memory is mapped or
+//! hand control to other code.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::{Decorator, Instruction, MemoryOperand, Operand, Template};
+use crate::isa::{self, Destination, Memory, Mnemonic, Privilege};
+
+/// The string instructions, by their name in the table.
+const STRING: &[&str] = &["movs", "cmps", "scas", "lods", "stos", "ins", "outs"];
+
+/// How an instruction accesses memory, as given by
+/// [`Instruction::memory_access`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    /// No access.
+    None,
+    /// Loads only.
+    Read,
+    /// Stores, and possibly loads.
+    Write,
+}
+
+/// Whether `mnemonic` is one of `stems`, with or without an AT&T size suffix.
+pub(super) fn matches(mnemonic: &str, stems: &[&str]) -> bool {
+    stems.iter().any(|stem| {
+        mnemonic
+            .strip_prefix(stem)
+            .is_some_and(|suffix| matches!(suffix, "" | "b" | "w" | "l" | "q"))
+    })
+}
+
+fn mnemonic(instruction: &Instruction) -> String {
+    instruction.mnemonic.to_ascii_lowercase()
+}
+
+/// Whether `mnemonic` is the string instruction `stem`, as opposed to an SSE
+/// instruction of the same name such as `movsd %xmm0, %xmm1`.
+fn is_string_form(mnemonic: &str, stem: &str, operands: &[Operand]) -> bool {
+    let suffixed = mnemonic
+        .strip_prefix(stem)
+        .is_some_and(|suffix| matches!(suffix, "" | "b" | "w" | "l" | "d" | "q"));
+    suffixed
+        && operands
+            .iter()
+            .all(|operand| matches!(operand, Operand::Memory(_)))
+}
+
+/// The entry of a string instruction, which the table would take for an SSE
+/// instruction when spelled `movsd` or `cmpsd`.
+pub(super) fn string_form(instruction: &Instruction) -> Option<&'static Mnemonic> {
+    let mnemonic = mnemonic(instruction);
+    STRING
+        .iter()
+        .find(|stem| is_string_form(&mnemonic, stem, &instruction.operands))
+        .and_then(|stem| isa::lookup(stem))
+}
+
+fn entry(instruction: &Instruction) -> Option<&'static Mnemonic> {
+    string_form(instruction).or_else(|| isa::lookup(&instruction.mnemonic))
+}
+
+/// The entry of the instruction, or `None` if its effects cannot be told
+/// from it.
+fn transparent_entry(instruction: &Instruction) -> Option<&'static Mnemonic> {
+    let system = instruction.operands.iter().any(|operand| {
+        matches!(operand.undecorated(), Operand::Register(register) if register.is_system())
+    });
+    entry(instruction).filter(|entry| entry.privilege == Privilege::User && !system)
+}
+
+/// Whether the implicit registers of the entry apply: those of `imul` are
+/// only written by its one-operand form.
+fn writes_implicit(instruction: &Instruction, entry: &Mnemonic) -> bool {
+    entry.name != "imul" || instruction.operands.len() == 1
+}
+
+impl Instruction {
+    /// The explicit operands the instruction writes.
+    pub fn written_operands(&self) -> &[Operand] {
+        written_operands(self)
+    }
+
+    /// The names of the registers the instruction writes, explicitly or not,
+    /// in lower case.
+    pub fn written_registers(&self) -> Vec<String> {
+        written_registers(self)
+    }
+
+    /// Whether the effects of the instruction are known, rather than assumed
+    /// to be the worst.
+    pub fn has_known_effects(&self) -> bool {
+        transparent_entry(self).is_some()
+    }
+
+    /// Whether the instruction calls a function.
+    pub fn calls(&self) -> bool {
+        matches(&mnemonic(self), &["call"])
+    }
+
+    /// Whether the instruction pushes to or pops from the stack.
+    pub fn is_stack_instruction(&self) -> bool {
+        entry(self).is_some_and(|entry| entry.writes.contains(&"rsp"))
+    }
+
+    /// Whether the instruction may change the status flags, or the
+    /// floating-point status and exception flags.
+    pub fn modifies_flags(&self) -> bool {
+        modifies_flags(self)
+    }
+
+    /// How the instruction accesses memory, including the stack.
+    pub fn memory_access(&self) -> Access {
+        memory_access(self)
+    }
+
+    /// Whether the instruction uses the stack: pushes and pops, changes of
+    /// the stack pointer, and stores to the red zone below it.
+    pub fn uses_stack(&self) -> bool {
+        uses_stack(self)
+    }
+}
+
+impl Template {
+    /// Whether the template both pushes and pops the register `family`, such
+    /// as `rbx`, where `enter` and `leave` push and pop `%rbp`.
+    pub fn restores(&self, family: &str) -> bool {
+        let frame = |stem: &str| {
+            family == "rbp"
+                && self
+                    .instructions()
+                    .any(|(_, instruction)| matches(&mnemonic(instruction), &[stem]))
+        };
+        let has = |stem: &str| {
+            self.instructions().any(|(_, instruction)| {
+                matches(&mnemonic(instruction), &[stem])
+                    && instruction.operands.iter().any(|operand| {
+                        matches!(operand, Operand::Register(register)
+                            if register.family().as_deref() == Some(family))
+                    })
+            })
+        };
+        (has("push") || frame("enter")) && (has("pop") || frame("leave"))
+    }
+}
+
+fn written_operands(instruction: &Instruction) -> &[Operand] {
+    let operands = &instruction.operands;
+    let last = operands
+        .last()
+        .map(core::slice::from_ref)
+        .unwrap_or_default();
+    if instruction.is_branch() {
+        return &[];
+    }
+    match entry(instruction) {
+        Some(entry) if entry.name == "imul" && operands.len() == 1 => &[],
+        Some(entry) => match entry.destination {
+            Destination::Last => last,
+            Destination::None => &[],
+            Destination::All => operands,
+        },
+        None => last,
+    }
+}
+
+fn written_registers(instruction: &Instruction) -> Vec<String> {
+    let entry = entry(instruction);
+    let writes_all = entry.is_some_and(|entry| entry.destination == Destination::All);
+    let mut names = Vec::new();
+    for operand in written_operands(instruction) {
+        if let Operand::Register(register) = operand.undecorated() {
+            names.push(register.name().to_ascii_lowercase());
+        }
+        // The mask of an AVX-512 gather, which it clears as it loads.
+        if let Operand::Decorated(_, decorators) = operand
+            && writes_all
+        {
+            for decorator in decorators {
+                if let Decorator::Mask(mask) = decorator {
+                    names.push(mask.name().to_ascii_lowercase());
+                }
+            }
+        }
+    }
+
+    if let Some(entry) = entry
+        && writes_implicit(instruction, entry)
+    {
+        names.extend(entry.writes.iter().map(|name| name.to_string()));
+    }
+    // `rep ret` and `rep nop` leave the count alone.
+    if string_form(instruction).is_some()
+        && instruction
+            .prefixes
+            .iter()
+            .any(|prefix| prefix.to_ascii_lowercase().starts_with("rep"))
+    {
+        names.push("rcx".into());
+    }
+    names
+}
+
+fn modifies_flags(instruction: &Instruction) -> bool {
+    transparent_entry(instruction)
+        .is_none_or(|entry| !entry.flags_written.is_empty() || entry.exceptions)
+}
+
+fn memory_access(instruction: &Instruction) -> Access {
+    let Some(entry) = transparent_entry(instruction) else {
+        return Access::Write;
+    };
+    let mut access = match entry.memory {
+        Memory::Address => return Access::None,
+        Memory::Operands => Access::None,
+        Memory::Loads => Access::Read,
+        Memory::Stores => Access::Write,
+    };
+
+    let references_memory = |operand: &Operand| match operand.undecorated() {
+        Operand::Memory(_) => true,
+        Operand::Expression(_) => !instruction.is_branch(),
+        Operand::Indirect(target) => matches!(**target, Operand::Memory(_)),
+        _ => false,
+    };
+    let written = written_operands(instruction);
+    for (index, operand) in instruction.operands.iter().enumerate() {
+        if references_memory(operand) {
+            let is_written = index >= instruction.operands.len() - written.len();
+            access = access.max(if is_written {
+                Access::Write
+            } else {
+                Access::Read
+            });
+        }
+    }
+    access
+}
+
+fn uses_stack(instruction: &Instruction) -> bool {
+    let below_stack_pointer = |operand: &Operand| match operand.undecorated() {
+        Operand::Memory(MemoryOperand {
+            base: Some(base),
+            displacement,
+            ..
+        }) => {
+            matches!(&**base, Operand::Register(register) if is_stack_pointer(register.name()))
+                && displacement.trim_start().starts_with('-')
+        }
+        _ => false,
+    };
+    instruction.is_stack_instruction()
+        || written_registers(instruction)
+            .iter()
+            .any(|name| is_stack_pointer(name))
+        || instruction.operands.iter().any(below_stack_pointer)
+}
+
+fn is_stack_pointer(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "rsp" | "esp" | "sp" | "spl"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(text: &str) -> Vec<String> {
+        text.parse::<Instruction>().unwrap().written_registers()
+    }
+
+    #[test]
+    fn gathers_clear_their_mask() {
+        assert_eq!(
+            written("vpgatherdd %ymm2, (%rax,%ymm3,4), %ymm1"),
+            ["ymm2", "ymm1"]
+        );
+        assert_eq!(
+            written("vgatherqpd %xmm2, (%rax,%xmm3,8), %xmm1"),
+            ["xmm2", "xmm1"]
+        );
+        assert_eq!(
+            written("vpgatherdd (%rax,%zmm3,4), %zmm1{{%k1}}"),
+            ["zmm1", "k1"]
+        );
+        assert_eq!(written("vmovdqa32 %zmm0, %zmm1{{%k1}}"), ["zmm1"]);
+    }
+
+    #[test]
+    fn repeats_count_down_rcx() {
+        assert_eq!(written("rep stosq"), ["rdi", "rcx"]);
+        assert_eq!(written("repz ret"), ["rsp"]);
+    }
+
+    #[test]
+    fn zeroing_writes_every_ymm() {
+        for mnemonic in ["vzeroupper", "vzeroall"] {
+            let written = written(mnemonic);
+            assert_eq!(written.len(), 16, "{mnemonic}");
+            assert_eq!(
+                (written[0].as_str(), written[15].as_str()),
+                ("ymm0", "ymm15")
+            );
+        }
+    }
+}
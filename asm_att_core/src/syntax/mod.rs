@@ -0,0 +1,264 @@
+//! A model of the AT&T assembly accepted by `asm_att!` and friends.
+//!
+//! Every type can be parsed from AT&T text with [`str::parse`] and printed
+//! back with [`Display`](core::fmt::Display). The `intel()` methods print the
+//! Intel syntax equivalent instead.
+//!
+//! ```
+//! use asm_att_core::syntax::Instruction;
+//!
+//! let instruction: Instruction = "movl $1, 8(%rax,%rbx,4)".parse().unwrap();
+//! assert_eq!(instruction.mnemonic, "movl");
+//! assert_eq!(instruction.to_string(), "movl $1, 8(%rax,%rbx,4)");
+//! assert_eq!(instruction.intel().to_string(), "mov dword ptr [rax + rbx*4 + 8], 1");
+//! ```
+//!
+//! Template placeholders such as `{0}` or `{name:e}` are kept as
+//! [`Operand::Placeholder`], and anything the parser does not understand, such
+//! as symbol arithmetic, is kept verbatim as an [`Operand::Expression`].
+//! AVX-512 decorators are escaped like any other brace, as in
+//! `%zmm0{{%k1}}`; [`escape_decorators`] escapes them in templates written
+//! with single braces.
+//!
+//! [`Template::parse_intel`] parses Intel syntax into the same model instead,
+//! so that printing it gives the AT&T equivalent.
+//!
+//! Methods such as [`Instruction::written_registers`] and
+//! [`Instruction::uses_stack`] tell what an instruction does besides
+//! computing its destination, and [`Instruction::check_prefixes`] whether its
+//! prefixes apply to it.
+//!
+//! [`Register::is_known`] tells whether a register exists on x86 or x86_64,
+//! and [`Register::suggestion`] which one a misspelled name was meant to be.
+
+mod display;
+mod effects;
+mod parse;
+mod parse_intel;
+mod prefixes;
+mod registers;
+mod size;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub use display::Intel;
+pub use effects::Access;
+pub(crate) use parse::PREFIXES;
+pub use parse::escape_decorators;
+pub use size::Size;
+
+/// Where a statement comes from: which template string argument, and which
+/// line inside of it, both counted from zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// Index of the template string argument.
+    pub template: usize,
+    /// Line inside of the template string argument.
+    pub line: usize,
+}
+
+/// Text that could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    location: Location,
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(location: Location, message: impl Into<String>) -> Self {
+        ParseError {
+            location,
+            message: message.into(),
+        }
+    }
+
+    /// Where the error occurred.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// A description of the error, without location information.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// Every statement of a sequence of template strings, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Template {
+    /// The statements together with where they were found.
+    pub statements: Vec<(Location, Statement)>,
+}
+
+impl Template {
+    /// The instructions of the template, skipping labels and directives.
+    pub fn instructions(&self) -> impl Iterator<Item = (Location, &Instruction)> {
+        self.statements
+            .iter()
+            .filter_map(|(location, statement)| match statement {
+                Statement::Instruction(instruction) => Some((*location, instruction)),
+                _ => None,
+            })
+    }
+}
+
+/// A single label, directive or instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Statement {
+    /// `loop:`, `1:`.
+    Label(Label),
+    /// `.global add2`.
+    Directive(Directive),
+    /// `repe cmpsb`, `movq {src}, %rsi`.
+    Instruction(Instruction),
+}
+
+/// A label definition such as `1:` or `.Lloop:`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Label {
+    /// The label name, without the colon.
+    pub name: String,
+}
+
+/// An assembler directive such as `.global add2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Directive {
+    /// The directive name including its leading dot, e.g. `.global`.
+    pub name: String,
+    /// Everything after the name, verbatim.
+    pub args: String,
+}
+
+/// An instruction with its prefixes and operands, in AT&T order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Prefixes such as `lock` or `repe`, as written.
+    pub prefixes: Vec<String>,
+    /// The mnemonic as written, including any size suffix.
+    pub mnemonic: String,
+    /// The operands in AT&T order: sources first, destination last.
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Whether the instruction is a jump, `loop` or call, whose operands are
+    /// code addresses.
+    pub fn is_branch(&self) -> bool {
+        let mnemonic = self.mnemonic.to_ascii_lowercase();
+        mnemonic.starts_with('j')
+            || mnemonic.starts_with("loop")
+            || matches!(mnemonic.as_str(), "call" | "callw" | "calll" | "callq")
+    }
+}
+
+/// An instruction operand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// `%rax`, `%st(1)`.
+    Register(Register),
+    /// `{0}`, `{name:e}`.
+    Placeholder(Placeholder),
+    /// `$1`, `${n}`, without the dollar sign.
+    Immediate(String),
+    /// `%fs:8(%rax,%rbx,4)`.
+    Memory(MemoryOperand),
+    /// `*%rax`, `*8(%rsp)`.
+    Indirect(Box<Operand>),
+    /// Symbols, label references such as `2f`, and anything unrecognised.
+    Expression(String),
+    /// `%zmm0{{%k1}}{{z}}`, `(%rax){{1to16}}`: an operand followed by AVX-512
+    /// decorators.
+    Decorated(Box<Operand>, Vec<Decorator>),
+    /// `{{rn-sae}}`: a rounding control operand.
+    Decorator(Decorator),
+}
+
+impl Operand {
+    /// The operand without its AVX-512 decorators, if it has any.
+    pub fn undecorated(&self) -> &Operand {
+        match self {
+            Operand::Decorated(operand, _) => operand,
+            operand => operand,
+        }
+    }
+}
+
+/// An AVX-512 decorator, written between escaped braces such as `{{%k1}}` in
+/// templates.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Decorator {
+    /// `{{%k1}}`: masking with an opmask register.
+    Mask(Register),
+    /// `{{z}}`: zeroing instead of merging the masked elements.
+    Zeroing,
+    /// `{{1to16}}`: broadcast of one memory element to the given count.
+    Broadcast(u8),
+    /// `{{rn-sae}}`, `{{sae}}`: rounding control or suppression of
+    /// exceptions, without the braces.
+    Rounding(String),
+}
+
+/// A register name such as `rax`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Register(String);
+
+impl Register {
+    /// Creates a register from its name, without the `%` prefix.
+    pub fn new(name: impl Into<String>) -> Self {
+        Register(name.into())
+    }
+
+    /// The name as written, without the `%` prefix.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A memory reference `segment:displacement(base, index, scale)`.
+///
+/// The segment, base and index are either an [`Operand::Register`] or an
+/// [`Operand::Placeholder`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryOperand {
+    /// Segment override such as `%fs`.
+    pub segment: Option<Box<Operand>>,
+    /// The displacement expression, verbatim; empty if there is none.
+    pub displacement: String,
+    /// The base register.
+    pub base: Option<Box<Operand>>,
+    /// The index register.
+    pub index: Option<Box<Operand>>,
+    /// The scale applied to the index.
+    pub scale: Option<u8>,
+}
+
+/// A template placeholder such as `{0}` or `{name:e}`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Placeholder {
+    /// The operand the placeholder refers to.
+    pub arg: PlaceholderArg,
+    /// The template modifier after the colon, such as `e` or `x`.
+    pub modifier: Option<String>,
+}
+
+/// The operand a [`Placeholder`] refers to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PlaceholderArg {
+    /// `{}`: the next positional operand.
+    Next,
+    /// `{0}`: a positional operand.
+    Index(usize),
+    /// `{name}`: a named operand.
+    Name(String),
+}
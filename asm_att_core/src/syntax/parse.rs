@@ -0,0 +1,772 @@
+//! Tokenizer and parser for AT&T template strings.
+//!
+//! The parser is deliberately lenient: it only rejects text that neither
+//! `asm!` nor the assembler would accept, and keeps anything it does not
+//! understand as a raw [`Operand::Expression`].
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use super::{
+    Decorator, Directive, Instruction, Label, Location, MemoryOperand, Operand, ParseError,
+    Placeholder, PlaceholderArg, Register, Statement, Template,
+};
+
+pub(crate) const PREFIXES: &[&str] = &[
+    "lock", "rep", "repe", "repz", "repne", "repnz", "data16", "data32", "addr16", "addr32", "rex",
+    "rex64", "notrack", "xacquire", "xrelease", "bnd",
+];
+
+impl Template {
+    /// Parses the template string arguments of one macro invocation.
+    pub fn parse(templates: &[&str]) -> Result<Self, ParseError> {
+        parse_templates(templates, parse_instruction)
+    }
+}
+
+/// Builds an instruction from its prefixes, its mnemonic and the text of its
+/// operands.
+pub(super) type InstructionParser = fn(Vec<String>, &str, &str) -> Result<Instruction, String>;
+
+/// Splits templates into statements, leaving the instructions to
+/// `instruction`.
+pub(super) fn parse_templates(
+    templates: &[&str],
+    instruction: InstructionParser,
+) -> Result<Template, ParseError> {
+    let mut origins = Vec::new();
+    for (template, text) in templates.iter().enumerate() {
+        origins.extend((0..text.split('\n').count()).map(|line| Location { template, line }));
+    }
+    let source = strip_comments(&templates.join("\n"));
+
+    let mut statements = Vec::new();
+    for (location, line) in origins.into_iter().zip(source.split('\n')) {
+        let error = |message: String| ParseError::new(location, message);
+        for text in split_top_level(line, ';').map_err(error)? {
+            parse_statement(text, instruction, &mut |statement| {
+                statements.push((location, statement))
+            })
+            .map_err(error)?;
+        }
+    }
+    Ok(Template { statements })
+}
+
+impl FromStr for Template {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::parse(&[s])
+    }
+}
+
+fn error(message: impl Into<String>) -> ParseError {
+    ParseError::new(Location::default(), message)
+}
+
+impl FromStr for Statement {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut statements = Template::parse(&[s])?.statements.into_iter();
+        match (statements.next(), statements.next()) {
+            (Some((_, statement)), None) => Ok(statement),
+            (None, _) => Err(error("expected a statement")),
+            (Some(_), Some(_)) => Err(error("expected a single statement")),
+        }
+    }
+}
+
+impl FromStr for Label {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Statement::Label(label) => Ok(label),
+            _ => Err(error("expected a label such as `1:`")),
+        }
+    }
+}
+
+impl FromStr for Directive {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Statement::Directive(directive) => Ok(directive),
+            _ => Err(error("expected a directive such as `.global`")),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Statement::Instruction(instruction) => Ok(instruction),
+            _ => Err(error("expected an instruction")),
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match split_top_level(s, ',').map_err(error)?[..] {
+            [operand] => parse_operand(operand).map_err(error),
+            _ => Err(error("expected a single operand")),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Operand::Register(register) => Ok(register),
+            _ => Err(error("expected a register such as `%rax`")),
+        }
+    }
+}
+
+impl FromStr for MemoryOperand {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Operand::Memory(memory) => Ok(memory),
+            _ => Err(error("expected a memory operand such as `8(%rax)`")),
+        }
+    }
+}
+
+/// Replaces `# ...` and `/* ... */` comments by spaces, keeping line breaks so
+/// that line numbers stay meaningful.
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                out.push(' ');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Splits `text` on `separator`, ignoring separators nested in parentheses,
+/// placeholders and string literals.
+pub(super) fn split_top_level(text: &str, separator: char) -> Result<Vec<&str>, String> {
+    let mut parts = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' if chars.next_if(|&(_, c)| c == '{').is_none() => {
+                let end = text[i..]
+                    .find('}')
+                    .ok_or_else(|| format!("unterminated placeholder `{}`", &text[i..]))?;
+                while chars.next_if(|&(j, _)| j <= i + end).is_some() {}
+            }
+            '}' if chars.next_if(|&(_, c)| c == '}').is_none() => {
+                return Err("unmatched `}` in template; write `}}` to escape it".into());
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("unmatched `)` in `{}`", text.trim()))?;
+            }
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("unclosed `(` in `{}`", text.trim()));
+    }
+    parts.push(&text[start..]);
+    Ok(parts)
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+fn parse_statement(
+    text: &str,
+    instruction: InstructionParser,
+    emit: &mut dyn FnMut(Statement),
+) -> Result<(), String> {
+    let mut text = text.trim();
+    while let Some((label, rest)) = split_label(text) {
+        emit(Statement::Label(Label { name: label.into() }));
+        text = rest.trim_start();
+    }
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let (first, rest) = split_word(text);
+    if first.starts_with('.') {
+        emit(Statement::Directive(Directive {
+            name: first.into(),
+            args: rest.into(),
+        }));
+        return Ok(());
+    }
+
+    let mut prefixes = Vec::new();
+    let (mut mnemonic, mut rest) = (first, rest);
+    while !rest.is_empty() && PREFIXES.contains(&mnemonic.to_ascii_lowercase().as_str()) {
+        prefixes.push(mnemonic.into());
+        (mnemonic, rest) = split_word(rest);
+    }
+
+    emit(Statement::Instruction(instruction(
+        prefixes, mnemonic, rest,
+    )?));
+    Ok(())
+}
+
+fn parse_instruction(
+    prefixes: Vec<String>,
+    mnemonic: &str,
+    operands: &str,
+) -> Result<Instruction, String> {
+    let operands = if operands.is_empty() {
+        Vec::new()
+    } else {
+        split_top_level(operands, ',')?
+            .into_iter()
+            .map(parse_operand)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(Instruction {
+        prefixes,
+        mnemonic: mnemonic.into(),
+        operands,
+    })
+}
+
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let end = text.find(|c| !is_symbol_char(c))?;
+    let rest = text[end..].strip_prefix(':')?;
+    (end > 0 && !rest.starts_with(':')).then(|| (&text[..end], rest))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("expected an operand".into());
+    }
+    let (undecorated, mut decorators) = split_decorators(text, "%");
+    if undecorated.is_empty() && decorators.len() == 1 {
+        return Ok(Operand::Decorator(decorators.remove(0)));
+    }
+    if !decorators.is_empty() && !undecorated.is_empty() {
+        return Ok(Operand::Decorated(
+            Box::new(parse_operand(undecorated)?),
+            decorators,
+        ));
+    }
+    if let Some(rest) = text.strip_prefix('*') {
+        return Ok(Operand::Indirect(Box::new(parse_operand(rest)?)));
+    }
+    if let Some(rest) = text.strip_prefix('$') {
+        if rest.trim().is_empty() {
+            return Err("expected an immediate value after `$`".into());
+        }
+        return Ok(Operand::Immediate(rest.trim().into()));
+    }
+    if let Some(memory) = parse_memory(text)? {
+        return Ok(Operand::Memory(memory));
+    }
+    if let Some((operand, rest)) = parse_register_like(text)? {
+        if rest.is_empty() {
+            return Ok(operand);
+        }
+        if let Some(displacement) = rest.strip_prefix(':') {
+            return Ok(Operand::Memory(MemoryOperand {
+                segment: Some(Box::new(operand)),
+                displacement: displacement.trim().into(),
+                base: None,
+                index: None,
+                scale: None,
+            }));
+        }
+    }
+    Ok(Operand::Expression(text.into()))
+}
+
+/// Parses a `%register` or `{placeholder}` at the start of `text`, returning
+/// it together with the unparsed rest.
+fn parse_register_like(text: &str) -> Result<Option<(Operand, &str)>, String> {
+    if let Some(rest) = text.strip_prefix('%') {
+        if rest.starts_with('{') && !rest.starts_with("{{") {
+            return Err(
+                "redundant `%` before a placeholder: placeholders already expand to `%`-prefixed registers in AT&T syntax"
+                    .into(),
+            );
+        }
+        // `%\name` names the register given as argument `name` of a `.macro`.
+        let argument = usize::from(rest.starts_with('\\'));
+        let mut end = rest[argument..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .map_or(rest.len(), |end| argument + end);
+        if end == argument {
+            return Err("expected a register name after `%`".into());
+        }
+        if rest[..end].eq_ignore_ascii_case("st") && rest[end..].starts_with('(') {
+            end += rest[end..].find(')').map_or(0, |close| close + 1);
+        }
+        return Ok(Some((
+            Operand::Register(Register::new(&rest[..end])),
+            rest[end..].trim_start(),
+        )));
+    }
+    if text.starts_with('{') && !text.starts_with("{{") {
+        let end = text
+            .find('}')
+            .ok_or_else(|| format!("unterminated placeholder `{text}`"))?;
+        let placeholder = parse_placeholder(&text[1..end])?;
+        return Ok(Some((
+            Operand::Placeholder(placeholder),
+            text[end + 1..].trim_start(),
+        )));
+    }
+    Ok(None)
+}
+
+/// Splits the escaped AVX-512 decorators such as `{{%k1}}` off the end of an
+/// operand, where mask registers have the given prefix.
+pub(super) fn split_decorators<'a>(text: &'a str, prefix: &str) -> (&'a str, Vec<Decorator>) {
+    let mut text = text;
+    let mut decorators = Vec::new();
+    while let Some(rest) = text.strip_suffix("}}")
+        && let Some(open) = rest.rfind("{{")
+        && let Some(decorator) = parse_decorator(&rest[open + 2..], prefix)
+    {
+        decorators.push(decorator);
+        text = rest[..open].trim_end();
+    }
+    decorators.reverse();
+    (text, decorators)
+}
+
+/// Parses the text between the braces of an AVX-512 decorator, where mask
+/// registers have the given prefix.
+pub(super) fn parse_decorator(inner: &str, prefix: &str) -> Option<Decorator> {
+    let lower = inner.to_ascii_lowercase();
+    let decorator = match lower.as_str() {
+        "z" => Decorator::Zeroing,
+        "rn-sae" | "rd-sae" | "ru-sae" | "rz-sae" | "sae" => Decorator::Rounding(lower),
+        _ if matches!(
+            lower.strip_prefix(prefix).map(str::as_bytes),
+            Some([b'k', b'0'..=b'7'])
+        ) =>
+        {
+            Decorator::Mask(Register::new(&inner[prefix.len()..]))
+        }
+        _ => match lower.strip_prefix("1to")?.parse() {
+            Ok(count @ (2 | 4 | 8 | 16 | 32)) => Decorator::Broadcast(count),
+            _ => return None,
+        },
+    };
+    Some(decorator)
+}
+
+/// Escapes the braces of the AVX-512 decorators in a template written with
+/// single braces, such as `{%k1}`, `{z}` or `{1to16}`, so that `asm!` does
+/// not take them for placeholders.
+///
+/// `{z}` and `{sae}` are kept as placeholders when `names` has an operand of
+/// that name.
+///
+/// ```
+/// use asm_att_core::syntax::escape_decorators;
+///
+/// assert_eq!(
+///     escape_decorators("vaddps (%rax){1to16}, %zmm1, {dst}{%k1}{z}", &[]),
+///     "vaddps (%rax){{1to16}}, %zmm1, {dst}{{%k1}}{{z}}"
+/// );
+/// assert_eq!(escape_decorators("movl $0, {z}", &["z"]), "movl $0, {z}");
+/// ```
+pub fn escape_decorators<'a>(template: &'a str, names: &[&str]) -> Cow<'a, str> {
+    let bytes = template.as_bytes();
+    let mut escaped = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' | b'}' if bytes.get(i + 1) == Some(&bytes[i]) => i += 2,
+            b'{' => {
+                let Some(length) = template[i + 1..].find(['{', '}']) else {
+                    break;
+                };
+                let end = i + 1 + length;
+                let inner = &template[i + 1..end];
+                if bytes[end] == b'{' {
+                    i = end;
+                    continue;
+                }
+                if parse_decorator(inner, "%").is_some() && !names.contains(&inner) {
+                    escaped.push_str(&template[copied..i]);
+                    escaped.push_str("{{");
+                    escaped.push_str(inner);
+                    escaped.push_str("}}");
+                    copied = end + 1;
+                }
+                i = end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    if copied == 0 {
+        return Cow::Borrowed(template);
+    }
+    escaped.push_str(&template[copied..]);
+    Cow::Owned(escaped)
+}
+
+pub(super) fn parse_placeholder(inner: &str) -> Result<Placeholder, String> {
+    let (arg, modifier) = match inner.split_once(':') {
+        Some((arg, modifier)) => (arg.trim(), Some(modifier.trim())),
+        None => (inner.trim(), None),
+    };
+    let invalid = || format!("invalid placeholder `{{{inner}}}`; write `{{{{` to escape a brace");
+    let arg = if arg.is_empty() {
+        PlaceholderArg::Next
+    } else if arg.bytes().all(|b| b.is_ascii_digit()) {
+        PlaceholderArg::Index(arg.parse().map_err(|_| invalid())?)
+    } else if arg.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && arg.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        PlaceholderArg::Name(arg.into())
+    } else {
+        return Err(invalid());
+    };
+    if modifier.is_some_and(|m| !m.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return Err(invalid());
+    }
+    Ok(Placeholder {
+        arg,
+        modifier: modifier.map(Into::into),
+    })
+}
+
+/// Parses `[segment:][displacement](base[, index[, scale]])`, or returns
+/// `None` when `text` has no address part.
+fn parse_memory(text: &str) -> Result<Option<MemoryOperand>, String> {
+    let Some(inner) = text.strip_suffix(')') else {
+        return Ok(None);
+    };
+    let mut depth = 0_usize;
+    let Some(open) = inner.rfind(|c| {
+        match c {
+            ')' => depth += 1,
+            '(' if depth == 0 => return true,
+            '(' => depth -= 1,
+            _ => {}
+        }
+        false
+    }) else {
+        return Ok(None);
+    };
+
+    let parts = split_top_level(&inner[open + 1..], ',')?;
+    if parts.len() > 3 {
+        return Ok(None);
+    }
+    let mut components = Vec::new();
+    for part in &parts[..parts.len().min(2)] {
+        let part = part.trim();
+        if part.is_empty() {
+            components.push(None);
+            continue;
+        }
+        match parse_register_like(part)? {
+            Some((operand, "")) => components.push(Some(Box::new(operand))),
+            _ => return Ok(None),
+        }
+    }
+    let scale = match parts.get(2).map(|scale| scale.trim().parse()) {
+        Some(Ok(scale)) => Some(scale),
+        Some(Err(_)) => return Ok(None),
+        None => None,
+    };
+    let index = components.get(1).cloned().flatten();
+    if components.len() > 1 && index.is_none() {
+        return Ok(None);
+    }
+
+    let mut segment = None;
+    let mut displacement = inner[..open].trim();
+    if let Some((operand, rest)) = parse_register_like(displacement)?
+        && let Some(rest) = rest.strip_prefix(':')
+    {
+        segment = Some(Box::new(operand));
+        displacement = rest.trim();
+    }
+    Ok(Some(MemoryOperand {
+        segment,
+        displacement: displacement.into(),
+        base: components.into_iter().next().flatten(),
+        index,
+        scale,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn parse(templates: &[&str]) -> Vec<Statement> {
+        Template::parse(templates)
+            .unwrap()
+            .statements
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect()
+    }
+
+    fn placeholder(name: &str) -> Operand {
+        Operand::Placeholder(Placeholder {
+            arg: PlaceholderArg::Name(name.into()),
+            modifier: None,
+        })
+    }
+
+    #[test]
+    fn parses_instructions_and_labels() {
+        let statements = parse(&["cld", "repe cmpsb", "1: inc {c}", "jne 1b"]);
+        assert_eq!(
+            statements,
+            [
+                Statement::Instruction(Instruction {
+                    prefixes: vec![],
+                    mnemonic: "cld".into(),
+                    operands: vec![],
+                }),
+                Statement::Instruction(Instruction {
+                    prefixes: vec!["repe".into()],
+                    mnemonic: "cmpsb".into(),
+                    operands: vec![],
+                }),
+                Statement::Label(Label { name: "1".into() }),
+                Statement::Instruction(Instruction {
+                    prefixes: vec![],
+                    mnemonic: "inc".into(),
+                    operands: vec![placeholder("c")],
+                }),
+                Statement::Instruction(Instruction {
+                    prefixes: vec![],
+                    mnemonic: "jne".into(),
+                    operands: vec![Operand::Expression("1b".into())],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_operands() {
+        let statements = parse(&["movq $0, %fs:-8({base},%rcx,8)", "jmp *{0:e}"]);
+        let Statement::Instruction(mov) = &statements[0] else {
+            panic!()
+        };
+        assert_eq!(mov.operands[0], Operand::Immediate("0".into()));
+        assert_eq!(
+            mov.operands[1],
+            Operand::Memory(MemoryOperand {
+                segment: Some(Box::new(Operand::Register(Register::new("fs")))),
+                displacement: "-8".into(),
+                base: Some(Box::new(placeholder("base"))),
+                index: Some(Box::new(Operand::Register(Register::new("rcx")))),
+                scale: Some(8),
+            })
+        );
+        let Statement::Instruction(jmp) = &statements[1] else {
+            panic!()
+        };
+        assert_eq!(
+            jmp.operands[0],
+            Operand::Indirect(Box::new(Operand::Placeholder(Placeholder {
+                arg: PlaceholderArg::Index(0),
+                modifier: Some("e".into()),
+            })))
+        );
+    }
+
+    #[test]
+    fn parses_macro_arguments() {
+        let statements = parse(&[".macro push_reg reg", "pushq %\\reg", ".endm"]);
+        let Statement::Instruction(push) = &statements[1] else {
+            panic!()
+        };
+        assert_eq!(push.operands[0], Operand::Register(Register::new("\\reg")));
+        assert!("movq 8(%\\base_reg), %rax".parse::<Instruction>().is_ok());
+    }
+
+    #[test]
+    fn parses_decorators() {
+        let statements = parse(&["vpaddd (%rax){{1to16}}, %zmm1, {dst}{{%k1}}{{z}}"]);
+        let Statement::Instruction(add) = &statements[0] else {
+            panic!()
+        };
+        assert_eq!(
+            add.operands[0],
+            Operand::Decorated(
+                Box::new(Operand::Memory(MemoryOperand {
+                    base: Some(Box::new(Operand::Register(Register::new("rax")))),
+                    ..MemoryOperand::default()
+                })),
+                vec![Decorator::Broadcast(16)]
+            )
+        );
+        assert_eq!(
+            add.operands[2],
+            Operand::Decorated(
+                Box::new(placeholder("dst")),
+                vec![Decorator::Mask(Register::new("k1")), Decorator::Zeroing]
+            )
+        );
+        assert_eq!(
+            "{{ru-sae}}".parse(),
+            Ok(Operand::Decorator(Decorator::Rounding("ru-sae".into())))
+        );
+    }
+
+    #[test]
+    fn escapes_decorators() {
+        let escape = |text| escape_decorators(text, &["src"]);
+        assert_eq!(
+            escape("vmovdqu32 {src}, %zmm0{%k2}{z}"),
+            "vmovdqu32 {src}, %zmm0{{%k2}}{{z}}"
+        );
+        assert_eq!(escape("vaddps {rz-sae}, %zmm0"), "vaddps {{rz-sae}}, %zmm0");
+        assert_eq!(
+            escape_decorators("add {z}, {0:e}", &["z"]),
+            "add {z}, {0:e}"
+        );
+        assert_eq!(
+            escape("vmovaps %zmm0, %zmm1{{%k1}}"),
+            "vmovaps %zmm0, %zmm1{{%k1}}"
+        );
+        assert_eq!(escape("vmovaps {1to3}, {"), "vmovaps {1to3}, {");
+        assert!(matches!(escape("nop"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn parses_multi_line_templates() {
+        let template = Template::parse(&[
+            "\n    .global add2 # exported\n    add2:\n        movl %edi, %eax; ret\n",
+        ])
+        .unwrap();
+        let lines: Vec<_> = template
+            .statements
+            .iter()
+            .map(|(location, _)| location.line)
+            .collect();
+        assert_eq!(lines, [1, 2, 3, 3]);
+        assert_eq!(
+            template.statements[0].1,
+            Statement::Directive(Directive {
+                name: ".global".into(),
+                args: "add2".into()
+            })
+        );
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        let error = Template::parse(&["nop", "mov %, %eax"]).unwrap_err();
+        assert_eq!(
+            error.location(),
+            Location {
+                template: 1,
+                line: 0
+            }
+        );
+        assert_eq!(error.message(), "expected a register name after `%`");
+
+        let error = Template::parse(&["add {x, %eax"]).unwrap_err();
+        assert_eq!(error.message(), "unterminated placeholder `{x, %eax`");
+    }
+
+    #[test]
+    fn parses_single_items() {
+        assert_eq!("%rax".parse(), Ok(Register::new("rax")));
+        assert_eq!("1:".parse(), Ok(Label { name: "1".into() }));
+        assert_eq!(
+            "(,%rcx,2)".parse(),
+            Ok(MemoryOperand {
+                index: Some(Box::new(Operand::Register(Register::new("rcx")))),
+                scale: Some(2),
+                ..MemoryOperand::default()
+            })
+        );
+        assert!("rax".parse::<Register>().is_err());
+        assert!("nop; nop".parse::<Instruction>().is_err());
+        assert!(".text".parse::<Instruction>().is_err());
+    }
+}
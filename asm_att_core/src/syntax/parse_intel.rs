@@ -0,0 +1,419 @@
+//! Parser for Intel template strings, into the AT&T syntax tree.
+//!
+//! Operands are reversed, memory references such as `[rax + rbx*4 + 8]`
+//! become [`MemoryOperand`]s and size keywords such as `dword ptr` become
+//! mnemonic suffixes, so that printing the result gives AT&T syntax.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::display::{RENAMED, STRING_INSTRUCTIONS, keeps_operand_order};
+use super::parse::{
+    parse_decorator, parse_placeholder, parse_templates, split_decorators, split_top_level,
+};
+use super::size::{SUFFIXED, Size, memory_suffix};
+use super::{
+    Decorator, Instruction, MemoryOperand, Operand, ParseError, Placeholder, Register, Statement,
+    Template,
+};
+
+/// Intel size keywords, used before `ptr`.
+const SIZE_KEYWORDS: &[&str] = &[
+    "byte", "word", "dword", "fword", "qword", "mmword", "tbyte", "oword", "xmmword", "ymmword",
+    "zmmword",
+];
+
+impl Template {
+    /// Parses template strings written in Intel syntax, as accepted by
+    /// `core::arch::asm!` without `options(att_syntax)`. Printing the result
+    /// gives the AT&T equivalent.
+    ///
+    /// Placeholders are taken for registers, both as operands and inside of
+    /// memory references. The placeholders of `const` operands need a `$` in
+    /// AT&T syntax, and register operands that are called or jumped through
+    /// need a `*`: both must be added to the result.
+    ///
+    /// ```
+    /// use asm_att_core::syntax::Template;
+    ///
+    /// let template = Template::parse_intel(&["mov dword ptr [rax + rcx*4 - 8], 1"]).unwrap();
+    /// assert_eq!(template.to_string(), "movl $1, -8(%rax,%rcx,4)");
+    /// ```
+    pub fn parse_intel(templates: &[&str]) -> Result<Self, ParseError> {
+        let mut template = parse_templates(templates, parse_instruction)?;
+        template.statements.retain(|(_, statement)| {
+            !matches!(statement, Statement::Directive(directive)
+                if directive.name.eq_ignore_ascii_case(".intel_syntax"))
+        });
+        Ok(template)
+    }
+}
+
+fn parse_instruction(
+    prefixes: Vec<String>,
+    mnemonic: &str,
+    operands: &str,
+) -> Result<Instruction, String> {
+    let lower = mnemonic.to_ascii_lowercase();
+    let branch = lower.starts_with('j') || lower.starts_with("loop") || lower == "call";
+    let mut parsed = Vec::new();
+    let mut keyword = None;
+    if !operands.is_empty() {
+        for text in split_top_level(operands, ',')? {
+            let (operand, size) = parse_operand(text, branch)?;
+            keyword = keyword.or(size);
+            parsed.push(operand);
+        }
+    }
+
+    if !keeps_operand_order(mnemonic, &parsed) {
+        parsed.reverse();
+    }
+    Ok(Instruction {
+        prefixes,
+        mnemonic: att_mnemonic(mnemonic, &parsed, keyword),
+        operands: parsed,
+    })
+}
+
+/// The AT&T mnemonic of an Intel one, given its operands in AT&T order and
+/// the size keyword of its memory operand.
+fn att_mnemonic(mnemonic: &str, operands: &[Operand], keyword: Option<&str>) -> String {
+    let lower = mnemonic.to_ascii_lowercase();
+    if let Some((att, _)) = RENAMED.iter().find(|(_, intel)| *intel == lower) {
+        return att.to_string();
+    }
+    if operands.is_empty()
+        && let Some(stem) = lower.strip_suffix('d')
+        && STRING_INSTRUCTIONS.contains(&stem)
+    {
+        return format!("{stem}l");
+    }
+
+    let size = keyword.and_then(Size::from_intel_keyword);
+    if matches!(lower.as_str(), "movzx" | "movsx" | "movsxd") {
+        let register_size = |operand: Option<&Operand>| match operand {
+            Some(Operand::Register(register)) => register.size(),
+            _ => None,
+        };
+        let source = register_size(operands.first()).or(size);
+        let destination = register_size(operands.get(1));
+        return match (source, destination) {
+            (Some(source), Some(destination)) => {
+                format!("{}{}{}", &lower[..4], source.suffix(), destination.suffix())
+            }
+            _ if lower == "movsxd" => "movslq".into(),
+            _ => mnemonic.into(),
+        };
+    }
+
+    let suffix = if SUFFIXED.contains(&lower.as_str()) {
+        size.map(|size| size.suffix().to_string())
+    } else {
+        keyword
+            .and_then(|keyword| memory_suffix(&lower, keyword))
+            .map(ToString::to_string)
+    };
+    format!("{mnemonic}{}", suffix.unwrap_or_default())
+}
+
+/// Parses an Intel operand, together with its size keyword if it has one.
+fn parse_operand(text: &str, branch: bool) -> Result<(Operand, Option<&'static str>), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("expected an operand".into());
+    }
+    let (undecorated, mut decorators) = match split_decorators(text, "") {
+        (_, decorators) if decorators.is_empty() => split_single_decorators(text),
+        split => split,
+    };
+    if undecorated.is_empty() && decorators.len() == 1 {
+        return Ok((Operand::Decorator(decorators.remove(0)), None));
+    }
+    if !decorators.is_empty() && !undecorated.is_empty() {
+        let (operand, size) = parse_operand(undecorated, branch)?;
+        return Ok((Operand::Decorated(Box::new(operand), decorators), size));
+    }
+
+    let (size, text) = split_size(text);
+    let operand = if let Some(symbol) = strip_keyword(text, "offset") {
+        Operand::Immediate(symbol.into())
+    } else if text.contains('[') || size.is_some() || split_segment(text).0.is_some() {
+        Operand::Memory(parse_memory(text)?)
+    } else if let Some(operand) = parse_register_like(text)? {
+        operand
+    } else if branch {
+        Operand::Expression(text.into())
+    } else if text.starts_with(|c: char| c.is_ascii_digit() || "-+~('".contains(c)) {
+        Operand::Immediate(number(text))
+    } else {
+        Operand::Expression(text.into())
+    };
+    let operand = match operand {
+        Operand::Register(_) | Operand::Memory(_) if branch => Operand::Indirect(Box::new(operand)),
+        operand => operand,
+    };
+    Ok((operand, size))
+}
+
+/// Splits AVX-512 decorators written with single braces, as in `zmm1 {k1}{z}`
+/// or `[rax]{1to16}`, off the end of an operand. Braces that make up the
+/// whole operand are taken for a placeholder unless they hold a rounding
+/// mode such as `{rn-sae}`.
+fn split_single_decorators(text: &str) -> (&str, Vec<Decorator>) {
+    let mut text = text;
+    let mut decorators = Vec::new();
+    while let Some(rest) = text.strip_suffix('}')
+        && let Some(open) = rest.rfind('{')
+        && (!rest[..open].trim().is_empty() || rest[open..].contains('-'))
+        && let Some(decorator) = parse_decorator(&rest[open + 1..], "")
+    {
+        decorators.push(decorator);
+        text = rest[..open].trim_end();
+    }
+    decorators.reverse();
+    (text, decorators)
+}
+
+/// Splits a `dword ptr` size keyword off the start of an operand.
+fn split_size(text: &str) -> (Option<&'static str>, &str) {
+    let word = text.split(|c: char| !c.is_ascii_alphabetic()).next();
+    let keyword = SIZE_KEYWORDS
+        .iter()
+        .find(|keyword| word.is_some_and(|word| word.eq_ignore_ascii_case(keyword)));
+    match keyword.and_then(|keyword| strip_keyword(strip_keyword(text, keyword)?, "ptr")) {
+        Some(rest) => (keyword.copied(), rest),
+        None => (None, text),
+    }
+}
+
+/// Strips a case-insensitive keyword off the start of `text`.
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let (start, rest) = (text.get(..keyword.len())?, &text[keyword.len()..]);
+    (start.eq_ignore_ascii_case(keyword)
+        && !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+    .then(|| rest.trim_start())
+}
+
+/// Parses a register or a placeholder, or returns `None`.
+fn parse_register_like(text: &str) -> Result<Option<Operand>, String> {
+    if Register::new(text).is_known() {
+        return Ok(Some(Operand::Register(Register::new(text))));
+    }
+    Ok(placeholder(text)?.map(Operand::Placeholder))
+}
+
+fn placeholder(text: &str) -> Result<Option<Placeholder>, String> {
+    match text
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+    {
+        Some(inner) if !inner.starts_with('{') && !inner.contains(['{', '}']) => {
+            parse_placeholder(inner).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses `[segment:]displacement[base + index*scale + displacement]`.
+fn parse_memory(text: &str) -> Result<MemoryOperand, String> {
+    let mut memory = MemoryOperand::default();
+    let (segment, rest) = split_segment(text);
+    memory.segment = segment;
+    let (outside, inside) = match (rest.find('['), rest.rfind(']')) {
+        (Some(open), Some(close)) if open < close => (
+            format!("{}{}", &rest[..open], &rest[close + 1..]),
+            rest[open + 1..close].replace("][", "+"),
+        ),
+        (None, None) => (rest.into(), String::new()),
+        _ => return Err(format!("unmatched bracket in `{text}`")),
+    };
+    let (segment, inside) = split_segment(&inside);
+    memory.segment = memory.segment.or(segment);
+
+    for (negative, term) in terms(outside.trim())
+        .into_iter()
+        .chain(terms(inside.trim()))
+    {
+        if term.is_empty() {
+            return Err(format!("expected an address term in `{text}`"));
+        }
+        if let Some((left, right)) = term.split_once('*') {
+            let (left, right) = (left.trim(), right.trim());
+            let scaled = match (parse_register_like(left)?, parse_register_like(right)?) {
+                (Some(index), None) => Some((index, right)),
+                (None, Some(index)) => Some((index, left)),
+                _ => None,
+            };
+            if let Some((index, scale)) = scaled {
+                let scale = match scale.parse() {
+                    Ok(scale @ (1 | 2 | 4 | 8)) => scale,
+                    _ => {
+                        return Err(format!(
+                            "invalid scale `{scale}` in `{text}`; expected 1, 2, 4 or 8"
+                        ));
+                    }
+                };
+                if negative || memory.index.is_some() {
+                    return Err(format!("invalid index `{term}` in `{text}`"));
+                }
+                memory.index = Some(Box::new(index));
+                memory.scale = Some(scale);
+                continue;
+            }
+        } else if let Some(register) = parse_register_like(term)? {
+            if negative {
+                return Err(format!("cannot subtract the register `{term}` in `{text}`"));
+            }
+            if memory.base.is_none() {
+                memory.base = Some(Box::new(register));
+            } else if memory.index.is_none() {
+                memory.index = Some(Box::new(register));
+            } else {
+                return Err(format!("too many registers in `{text}`"));
+            }
+            continue;
+        }
+        if negative {
+            memory.displacement.push('-');
+        } else if !memory.displacement.is_empty() {
+            memory.displacement.push('+');
+        }
+        memory.displacement.push_str(&number(term));
+    }
+    Ok(memory)
+}
+
+/// Splits a `fs:` segment override off the start of a memory reference.
+fn split_segment(text: &str) -> (Option<Box<Operand>>, &str) {
+    let text = text.trim();
+    match text.split_once(':') {
+        Some((segment, rest))
+            if matches!(
+                segment.trim().to_ascii_lowercase().as_str(),
+                "cs" | "ds" | "es" | "fs" | "gs" | "ss"
+            ) =>
+        {
+            let segment = Operand::Register(Register::new(segment.trim()));
+            (Some(Box::new(segment)), rest.trim())
+        }
+        _ => (None, text),
+    }
+}
+
+/// Splits an address into its terms, each with whether it is subtracted.
+fn terms(text: &str) -> Vec<(bool, &str)> {
+    let mut terms = Vec::new();
+    if text.is_empty() {
+        return terms;
+    }
+    let (mut negative, mut start, mut depth) = (false, 0, 0_usize);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth = depth.saturating_sub(1),
+            '+' | '-' if depth == 0 => {
+                if i > 0 {
+                    terms.push((negative, text[start..i].trim()));
+                }
+                negative = c == '-';
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push((negative, text[start..].trim()));
+    terms
+}
+
+/// Rewrites a number with an Intel `h` suffix such as `0ffh` as `0xff`.
+fn number(text: &str) -> String {
+    match text.strip_suffix(['h', 'H']) {
+        Some(digits)
+            if digits.starts_with(|c: char| c.is_ascii_digit())
+                && digits.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            format!("0x{digits}")
+        }
+        _ => text.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn att(text: &str) -> String {
+        Template::parse_intel(&[text]).unwrap().to_string()
+    }
+
+    #[test]
+    fn converts_instructions() {
+        assert_eq!(att("mov eax, 1"), "mov $1, %eax");
+        assert_eq!(att("add rax, {x}"), "add {x}, %rax");
+        assert_eq!(att("imul eax, ebx, 10h"), "imul $0x10, %ebx, %eax");
+        assert_eq!(att("mov qword ptr [rsp - 8], 0"), "movq $0, -8(%rsp)");
+        assert_eq!(
+            att("movzx eax, byte ptr [rsi + rcx]"),
+            "movzbl (%rsi,%rcx), %eax"
+        );
+        assert_eq!(att("movsxd rax, dword ptr [{p}]"), "movslq ({p}), %rax");
+        assert_eq!(att("movsx ecx, ax"), "movswl %ax, %ecx");
+        assert_eq!(att("rep stosd"), "rep stosl");
+        assert_eq!(att("cdqe"), "cltq");
+        assert_eq!(att("fld tbyte ptr [rax]"), "fldt (%rax)");
+        assert_eq!(att("fild qword ptr [rax]"), "fildll (%rax)");
+        assert_eq!(
+            att("cvtsi2sd xmm0, dword ptr [rdi]"),
+            "cvtsi2sdl (%rdi), %xmm0"
+        );
+        assert_eq!(att("enter 16, 0"), "enter $16, $0");
+        assert_eq!(
+            att("vaddps zmm1{{k1}}{{z}}, zmm2, dword ptr [rax]{{1to16}}"),
+            "vaddps (%rax){{1to16}}, %zmm2, %zmm1{{%k1}}{{z}}"
+        );
+        assert_eq!(
+            att("vpaddd zmm1 {k1}{z}, zmm2, zmm3"),
+            "vpaddd %zmm3, %zmm2, %zmm1{{%k1}}{{z}}"
+        );
+        assert_eq!(
+            att("vaddps zmm1, zmm2, dword ptr [rax]{1to16}"),
+            "vaddps (%rax){{1to16}}, %zmm2, %zmm1"
+        );
+        assert_eq!(
+            att("vaddps zmm1, zmm2, zmm3, {rn-sae}"),
+            "vaddps {{rn-sae}}, %zmm3, %zmm2, %zmm1"
+        );
+    }
+
+    #[test]
+    fn converts_memory_references() {
+        assert_eq!(att("lea rax, [rip + foo]"), "lea foo(%rip), %rax");
+        assert_eq!(att("mov rax, qword ptr fs:[0x28]"), "movq %fs:0x28, %rax");
+        assert_eq!(att("mov eax, ds:0x10"), "mov %ds:0x10, %eax");
+        assert_eq!(
+            att("mov ecx, [rbx*8 + rdi + 16]"),
+            "mov 16(%rdi,%rbx,8), %ecx"
+        );
+        assert_eq!(att("mov eax, table[rax*4]"), "mov table(,%rax,4), %eax");
+        assert_eq!(att("mov rax, offset foo"), "mov $foo, %rax");
+        assert_eq!(att("mov eax, foo"), "mov foo, %eax");
+
+        let error = Template::parse_intel(&["mov eax, [rax*3]"]).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "invalid scale `3` in `[rax*3]`; expected 1, 2, 4 or 8"
+        );
+    }
+
+    #[test]
+    fn converts_branches() {
+        assert_eq!(att("jne 1b"), "jne 1b");
+        assert_eq!(att("call {f}"), "call {f}");
+        assert_eq!(att("call rax"), "call *%rax");
+        assert_eq!(att("jmp qword ptr [rax + 8]"), "jmpq *8(%rax)");
+        assert_eq!(att(".intel_syntax noprefix\n1: ret"), "1:\nret");
+    }
+}
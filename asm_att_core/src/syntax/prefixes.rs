@@ -0,0 +1,237 @@
+//! Which instructions the `lock`, repeat and HLE prefixes apply to.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::effects::{matches, string_form};
+use super::{Instruction, Operand};
+
+/// Instructions that `lock` applies to, when their destination is in memory.
+const LOCKABLE: &[&str] = &[
+    "adc",
+    "add",
+    "and",
+    "btc",
+    "btr",
+    "bts",
+    "cmpxchg",
+    "cmpxchg8b",
+    "cmpxchg16b",
+    "dec",
+    "inc",
+    "neg",
+    "not",
+    "or",
+    "sbb",
+    "sub",
+    "xadd",
+    "xchg",
+    "xor",
+];
+
+/// The repeat prefixes.
+const REPEAT: &[&str] = &["rep", "repe", "repz", "repne", "repnz"];
+
+/// String instructions that the conditional repeat prefixes apply to.
+const COMPARING: &[&str] = &["cmps", "scas"];
+
+/// Instructions other than string instructions that take `rep`, or `repe`
+/// and `repz`, which share its encoding: `rep ret` pads returns for older AMD
+/// processors, and `rep nop` is `pause`.
+const REPEATABLE: &[&str] = &["ret", "nop"];
+
+impl Instruction {
+    /// Checks that the prefixes of the instruction apply to it.
+    ///
+    /// `lock` applies to read-modify-write instructions such as `add` or
+    /// `cmpxchg` with a memory destination, `rep` to string instructions,
+    /// `repe` and `repne` to `cmps` and `scas`, `rep` and `repe` to `ret` and
+    /// `nop` as well, and `xacquire` and `xrelease` to locked instructions and
+    /// `xchg`. The destination of a string
+    /// instruction is always in `%es`, so that it takes no other segment
+    /// override.
+    ///
+    /// ```
+    /// use asm_att_core::syntax::Instruction;
+    ///
+    /// let check = |text: &str| text.parse::<Instruction>().unwrap().check_prefixes();
+    /// assert!(check("lock incq (%rdi)").is_ok());
+    /// assert!(check("lock movq %rax, (%rdi)").is_err());
+    /// assert!(check("repe addq %rax, %rbx").is_err());
+    /// ```
+    pub fn check_prefixes(&self) -> Result<(), String> {
+        let mnemonic = self.mnemonic.to_ascii_lowercase();
+        let prefixes: Vec<String> = self
+            .prefixes
+            .iter()
+            .map(|prefix| prefix.to_ascii_lowercase())
+            .collect();
+        let has = |prefix: &str| prefixes.iter().any(|p| p == prefix);
+        let string = string_form(self).map(|entry| entry.name);
+
+        let repeats: Vec<&String> = prefixes
+            .iter()
+            .filter(|prefix| REPEAT.contains(&prefix.as_str()))
+            .collect();
+        if let [first, second, ..] = repeats[..] {
+            return Err(format!("`{first}` and `{second}` cannot be combined"));
+        }
+        if let Some(repeat) = repeats.first() {
+            if has("lock") {
+                return Err(format!("`lock` and `{repeat}` cannot be combined"));
+            }
+            let applies = match repeat.as_str() {
+                "rep" => string.is_some() || matches(&mnemonic, REPEATABLE),
+                "repe" | "repz" => {
+                    string.is_some_and(|stem| COMPARING.contains(&stem))
+                        || matches(&mnemonic, REPEATABLE)
+                }
+                _ => string.is_some_and(|stem| COMPARING.contains(&stem)),
+            };
+            if !applies {
+                let which = if repeat.as_str() == "rep" {
+                    "string instructions such as `movs` and `stos`"
+                } else {
+                    "`cmps` and `scas`"
+                };
+                return Err(format!(
+                    "`{repeat}` does not apply to `{}`; it repeats {which}",
+                    self.mnemonic
+                ));
+            }
+        }
+
+        if has("lock") {
+            if !matches(&mnemonic, LOCKABLE) {
+                return Err(format!(
+                    "`lock` does not apply to `{}`, which is not a read-modify-write instruction",
+                    self.mnemonic
+                ));
+            }
+            let in_memory =
+                |operand: &Operand| matches!(operand, Operand::Memory(_) | Operand::Expression(_));
+            let locked = if matches(&mnemonic, &["xchg"]) {
+                self.operands.iter().any(in_memory)
+            } else {
+                self.operands.last().is_some_and(in_memory)
+            };
+            if !locked {
+                return Err(format!(
+                    "`lock {}` needs a memory destination",
+                    self.mnemonic
+                ));
+            }
+        }
+
+        for hint in ["xacquire", "xrelease"] {
+            let store = hint == "xrelease"
+                && matches(&mnemonic, &["mov"])
+                && matches!(self.operands.last(), Some(Operand::Memory(_)));
+            if has(hint) && !has("lock") && !matches(&mnemonic, &["xchg"]) && !store {
+                return Err(format!(
+                    "`{hint}` only applies to locked instructions and `xchg`"
+                ));
+            }
+        }
+
+        if string.is_some() {
+            for operand in &self.operands {
+                let Operand::Memory(memory) = operand else {
+                    continue;
+                };
+                let (Some(segment), Some(Operand::Register(base))) =
+                    (&memory.segment, memory.base.as_deref())
+                else {
+                    continue;
+                };
+                let destination = matches!(
+                    base.name().to_ascii_lowercase().as_str(),
+                    "rdi" | "edi" | "di"
+                );
+                let es = matches!(&**segment, Operand::Register(segment)
+                    if segment.name().eq_ignore_ascii_case("es"));
+                if destination && !es {
+                    return Err(format!(
+                        "`{}` always addresses `{operand}` in `%es`, which cannot be overridden",
+                        self.mnemonic
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Result<(), String> {
+        text.parse::<Instruction>().unwrap().check_prefixes()
+    }
+
+    #[test]
+    fn accepts_prefixes() {
+        for text in [
+            "rep movsb",
+            "rep stosq",
+            "repe cmpsb",
+            "repnz scasb (%rdi)",
+            "rep ret",
+            "repz ret",
+            "repe nop",
+            "lock incq ({p})",
+            "lock cmpxchgq %rcx, 8(%rdi)",
+            "lock xchgl (%rdi), %eax",
+            "xacquire lock addl $1, (%rdi)",
+            "xrelease movl $0, (%rdi)",
+            "movsb %fs:(%rsi), %es:(%rdi)",
+            "movsd %xmm0, %xmm1",
+        ] {
+            assert_eq!(check(text), Ok(()), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_prefixes() {
+        let error = |text: &str| check(text).unwrap_err();
+        assert_eq!(
+            error("lock movq %rax, (%rdi)"),
+            "`lock` does not apply to `movq`, which is not a read-modify-write instruction"
+        );
+        assert_eq!(
+            error("lock addq %rax, {x}"),
+            "`lock addq` needs a memory destination"
+        );
+        assert_eq!(
+            error("repe addq %rax, %rbx"),
+            "`repe` does not apply to `addq`; it repeats `cmps` and `scas`"
+        );
+        assert_eq!(
+            error("rep movsd %xmm0, %xmm1"),
+            "`rep` does not apply to `movsd`; it repeats string instructions such as `movs` \
+             and `stos`"
+        );
+        assert_eq!(
+            error("repnz ret"),
+            "`repnz` does not apply to `ret`; it repeats `cmps` and `scas`"
+        );
+        assert_eq!(
+            error("rep repne scasb"),
+            "`rep` and `repne` cannot be combined"
+        );
+        assert_eq!(
+            error("lock rep stosb"),
+            "`lock` and `rep` cannot be combined"
+        );
+        assert_eq!(
+            error("xacquire addl $1, (%rdi)"),
+            "`xacquire` only applies to locked instructions and `xchg`"
+        );
+        assert_eq!(
+            error("stosb %fs:(%rdi)"),
+            "`stosb` always addresses `%fs:(%rdi)` in `%es`, which cannot be overridden"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! The registers of x86 and x86_64: which names exist, which ones share
+//! storage, and which existing name a misspelled one is closest to.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::Register;
+
+/// The general-purpose registers by their 64-bit name, with every name of
+/// their lower parts.
+const GENERAL_PURPOSE: &[(&str, &[&str])] = &[
+    ("rax", &["al", "ah", "ax", "eax"]),
+    ("rbx", &["bl", "bh", "bx", "ebx"]),
+    ("rcx", &["cl", "ch", "cx", "ecx"]),
+    ("rdx", &["dl", "dh", "dx", "edx"]),
+    ("rsi", &["sil", "si", "esi"]),
+    ("rdi", &["dil", "di", "edi"]),
+    ("rbp", &["bpl", "bp", "ebp"]),
+    ("rsp", &["spl", "sp", "esp"]),
+    ("r8", &["r8b", "r8w", "r8d"]),
+    ("r9", &["r9b", "r9w", "r9d"]),
+    ("r10", &["r10b", "r10w", "r10d"]),
+    ("r11", &["r11b", "r11w", "r11d"]),
+    ("r12", &["r12b", "r12w", "r12d"]),
+    ("r13", &["r13b", "r13w", "r13d"]),
+    ("r14", &["r14b", "r14w", "r14d"]),
+    ("r15", &["r15b", "r15w", "r15d"]),
+];
+
+/// The instruction pointers, segment registers, the top of the x87 stack,
+/// and the zero index registers GAS writes to pad an address with a SIB byte.
+const SPECIAL: &[&str] = &[
+    "rip", "eip", "ip", "cs", "ds", "es", "fs", "gs", "ss", "st", "riz", "eiz",
+];
+
+/// Numbered registers: vector, mask, MMX, control, debug, bound and tile
+/// registers, by prefix and count.
+const NUMBERED: &[(&str, u8)] = &[
+    ("xmm", 32),
+    ("ymm", 32),
+    ("zmm", 32),
+    ("k", 8),
+    ("mm", 8),
+    ("cr", 16),
+    ("dr", 16),
+    ("bnd", 4),
+    ("tmm", 8),
+];
+
+impl Register {
+    /// The name every alias of the register shares, in lower case: `rax` for
+    /// `%al`, `xmm1` for `%ymm1` or `st(0)` for `%st`. `None` for registers
+    /// that cannot be clobbered, such as segment registers.
+    pub fn family(&self) -> Option<String> {
+        let name = self.name().to_ascii_lowercase();
+        if let Some((full, _)) = GENERAL_PURPOSE
+            .iter()
+            .find(|(full, parts)| *full == name || parts.contains(&name.as_str()))
+        {
+            return Some(full.to_string());
+        }
+        if let Some(n) = ["xmm", "ymm", "zmm"]
+            .iter()
+            .find_map(|prefix| numbered(&name, prefix, 32))
+        {
+            return Some(format!("xmm{n}"));
+        }
+        if numbered(&name, "mm", 8).is_some() || numbered(&name, "k", 8).is_some_and(|n| n != 0) {
+            return Some(name);
+        }
+        x87(&name).map(|n| format!("st({n})"))
+    }
+
+    /// Whether the name, in any case, is that of an x86 or x86_64 register,
+    /// or a `.macro` argument such as `\reg`, which stands for one.
+    ///
+    /// ```
+    /// use asm_att_core::syntax::Register;
+    ///
+    /// assert!(Register::new("R8D").is_known());
+    /// assert!(Register::new("st(7)").is_known());
+    /// assert!(Register::new("\\reg").is_known());
+    /// assert!(!Register::new("r8l").is_known());
+    /// ```
+    pub fn is_known(&self) -> bool {
+        let name = self.name().to_ascii_lowercase();
+        self.is_macro_argument()
+            || GENERAL_PURPOSE
+                .iter()
+                .any(|(full, parts)| *full == name || parts.contains(&name.as_str()))
+            || SPECIAL.contains(&name.as_str())
+            || NUMBERED
+                .iter()
+                .any(|(prefix, count)| numbered(&name, prefix, *count).is_some())
+            || x87(&name).is_some()
+    }
+
+    /// Whether the name is that of a `.macro` argument, such as `\reg`,
+    /// whose register is only known once the macro is expanded.
+    pub fn is_macro_argument(&self) -> bool {
+        self.name().starts_with('\\')
+    }
+
+    /// Whether the register configures the CPU rather than holds data: a
+    /// control, debug or segment register.
+    ///
+    /// ```
+    /// use asm_att_core::syntax::Register;
+    ///
+    /// assert!(Register::new("cr3").is_system());
+    /// assert!(Register::new("FS").is_system());
+    /// assert!(!Register::new("rax").is_system());
+    /// ```
+    pub fn is_system(&self) -> bool {
+        let name = self.name().to_ascii_lowercase();
+        matches!(name.as_str(), "cs" | "ds" | "es" | "fs" | "gs" | "ss")
+            || numbered(&name, "cr", 16).is_some()
+            || numbered(&name, "dr", 16).is_some()
+    }
+
+    /// The known register whose name is the closest to this unknown one, such
+    /// as `rax` for `rxa`, or `r8b` for the Intel name `r8l`. `None` if the
+    /// register is known or no name is close.
+    pub fn suggestion(&self) -> Option<Register> {
+        if self.is_known() {
+            return None;
+        }
+        let name = self.name().to_ascii_lowercase();
+        if let Some(number) = name
+            .strip_prefix('r')
+            .and_then(|rest| rest.strip_suffix('l'))
+            && numbered(number, "", 16).is_some_and(|n| n >= 8)
+        {
+            return Some(Register::new(format!("r{number}b")));
+        }
+        let limit = if name.len() >= 5 { 2 } else { 1 };
+        known_names()
+            .into_iter()
+            .map(|known| (distance(&name, &known), known))
+            .filter(|(distance, _)| *distance <= limit)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| Register::new(known))
+    }
+}
+
+/// The number of a register named `prefix` and a number below `count`,
+/// written without leading zeros.
+fn numbered(name: &str, prefix: &str, count: u8) -> Option<u8> {
+    let digits = name.strip_prefix(prefix)?;
+    let number: u8 = digits.parse().ok()?;
+    (number < count && digits == number.to_string()).then_some(number)
+}
+
+/// The number of an x87 register such as `st(1)`, with `st` for `st(0)`.
+fn x87(name: &str) -> Option<u8> {
+    if name == "st" {
+        return Some(0);
+    }
+    let number = name.strip_prefix("st(")?.strip_suffix(')')?.trim();
+    numbered(number, "", 8)
+}
+
+/// Every register name, in lower case.
+fn known_names() -> Vec<String> {
+    let mut names = Vec::new();
+    for (full, parts) in GENERAL_PURPOSE {
+        names.push(full.to_string());
+        names.extend(parts.iter().map(|part| part.to_string()));
+    }
+    names.extend(SPECIAL.iter().map(|name| name.to_string()));
+    for (prefix, count) in NUMBERED {
+        names.extend((0..*count).map(|n| format!("{prefix}{n}")));
+    }
+    names.extend((0..8).map(|n| format!("st({n})")));
+    names
+}
+
+/// The edit distance between two names, counting a swap of two adjacent
+/// characters as one edit.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut rows = [
+        Vec::new(),
+        Vec::new(),
+        (0..=b.len()).collect::<Vec<usize>>(),
+    ];
+    for i in 1..=a.len() {
+        rows.rotate_left(1);
+        let [before, previous, current] = &mut rows;
+        current.clear();
+        current.push(i);
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(before[j - 2] + 1);
+            }
+            current.push(best);
+        }
+    }
+    rows[2][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(name: &str) -> Option<String> {
+        Register::new(name)
+            .suggestion()
+            .map(|register| register.name().to_string())
+    }
+
+    #[test]
+    fn knows_registers() {
+        for name in [
+            "rax", "AH", "r15d", "rip", "gs", "st", "st(7)", "mm7", "xmm31", "ymm0", "zmm16", "k0",
+            "cr8", "dr7", "bnd3", "tmm0", "riz", "EIZ",
+        ] {
+            assert!(Register::new(name).is_known(), "{name}");
+        }
+        for name in [
+            "r16", "xmm32", "xmm01", "k8", "st(8)", "r8l", "eflags", "rxa",
+        ] {
+            assert!(!Register::new(name).is_known(), "{name}");
+        }
+    }
+
+    #[test]
+    fn suggests_registers() {
+        assert_eq!(suggestion("rxa"), Some("rax".into()));
+        assert_eq!(suggestion("r8l"), Some("r8b".into()));
+        assert_eq!(suggestion("R12L"), Some("r12b".into()));
+        assert_eq!(suggestion("eaxx"), Some("eax".into()));
+        assert_eq!(suggestion("xmn3"), Some("xmm3".into()));
+        assert_eq!(suggestion("foo"), None);
+        assert_eq!(suggestion("rax"), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! AT&T operand-size suffixes.
+
+use super::{Instruction, Register};
+
+/// Mnemonics that take a `b`, `w`, `l` or `q` operand-size suffix in AT&T
+/// syntax but none in Intel syntax.
+pub(crate) const SUFFIXED: &[&str] = &[
+    "adc", "adcx", "add", "adox", "and", "bsf", "bsr", "bswap", "bt", "btc", "btr", "bts", "call",
+    "cmp", "cmpxchg", "dec", "div", "idiv", "imul", "inc", "jmp", "lea", "lzcnt", "mov", "movabs",
+    "movnti", "mul", "neg", "nop", "not", "or", "pop", "popcnt", "push", "rcl", "rcr", "ret",
+    "rol", "ror", "sal", "sar", "sbb", "shl", "shld", "shr", "shrd", "sub", "test", "tzcnt",
+    "xadd", "xchg", "xor",
+];
+
+/// Mnemonic stems, with the AT&T suffix of each Intel size keyword.
+type SuffixedStems = (
+    &'static [&'static str],
+    &'static [(&'static str, &'static str)],
+);
+
+/// Mnemonics whose AT&T suffix gives the type of their memory operand, with
+/// the suffix of each Intel size keyword: x87 floating-point and integer
+/// instructions, and conversions from integers.
+pub(crate) const MEMORY_SUFFIXES: &[SuffixedStems] = &[
+    (
+        &[
+            "fadd", "fcom", "fcomp", "fdiv", "fdivr", "fld", "fmul", "fst", "fstp", "fsub", "fsubr",
+        ],
+        &[("dword", "s"), ("qword", "l"), ("tbyte", "t")],
+    ),
+    (
+        &[
+            "fiadd", "ficom", "ficomp", "fidiv", "fidivr", "fild", "fimul", "fist", "fistp",
+            "fisttp", "fisub", "fisubr",
+        ],
+        &[("word", "s"), ("dword", "l"), ("qword", "ll")],
+    ),
+    (
+        &[
+            "cvtsi2sd",
+            "cvtsi2ss",
+            "vcvtsi2sd",
+            "vcvtsi2ss",
+            "vcvtusi2sd",
+            "vcvtusi2ss",
+        ],
+        &[("dword", "l"), ("qword", "q")],
+    ),
+];
+
+/// The AT&T suffix of a [`MEMORY_SUFFIXES`] mnemonic for an Intel size
+/// keyword.
+pub(crate) fn memory_suffix(stem: &str, keyword: &str) -> Option<&'static str> {
+    let (_, suffixes) = MEMORY_SUFFIXES
+        .iter()
+        .find(|(stems, _)| stems.contains(&stem))?;
+    suffixes
+        .iter()
+        .find(|(size, _)| keyword.eq_ignore_ascii_case(size))
+        .map(|(_, suffix)| *suffix)
+}
+
+/// Splits a suffixed [`MEMORY_SUFFIXES`] mnemonic such as `fldl` into its
+/// stem and the Intel size keyword of its memory operand.
+pub(crate) fn split_memory_suffix(mnemonic: &str) -> Option<(&str, &'static str)> {
+    MEMORY_SUFFIXES.iter().find_map(|(stems, suffixes)| {
+        suffixes.iter().find_map(|(keyword, suffix)| {
+            let stem = mnemonic.strip_suffix(suffix)?;
+            stems.contains(&stem).then_some((stem, *keyword))
+        })
+    })
+}
+
+/// An operand size selected by an AT&T mnemonic suffix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Size {
+    /// `b`: 8 bits.
+    Byte,
+    /// `w`: 16 bits.
+    Word,
+    /// `l`: 32 bits.
+    Long,
+    /// `q`: 64 bits.
+    Quad,
+}
+
+impl Size {
+    /// The size a suffix letter selects.
+    pub fn from_suffix(suffix: char) -> Option<Size> {
+        match suffix.to_ascii_lowercase() {
+            'b' => Some(Size::Byte),
+            'w' => Some(Size::Word),
+            'l' => Some(Size::Long),
+            'q' => Some(Size::Quad),
+            _ => None,
+        }
+    }
+
+    /// The suffix letter selecting this size.
+    pub fn suffix(self) -> char {
+        match self {
+            Size::Byte => 'b',
+            Size::Word => 'w',
+            Size::Long => 'l',
+            Size::Quad => 'q',
+        }
+    }
+
+    /// The size in bits.
+    pub fn bits(self) -> u16 {
+        match self {
+            Size::Byte => 8,
+            Size::Word => 16,
+            Size::Long => 32,
+            Size::Quad => 64,
+        }
+    }
+
+    pub(crate) fn intel_keyword(self) -> &'static str {
+        match self {
+            Size::Byte => "byte",
+            Size::Word => "word",
+            Size::Long => "dword",
+            Size::Quad => "qword",
+        }
+    }
+
+    pub(crate) fn from_intel_keyword(keyword: &str) -> Option<Size> {
+        [Size::Byte, Size::Word, Size::Long, Size::Quad]
+            .into_iter()
+            .find(|size| keyword.eq_ignore_ascii_case(size.intel_keyword()))
+    }
+}
+
+impl Instruction {
+    /// The sizes of the source and destination operands selected by the
+    /// suffix of the mnemonic.
+    ///
+    /// Both are the same except for the zero and sign extensions: `movl` gives
+    /// `(Long, Long)`, `movzbl` gives `(Byte, Long)`. Mnemonics without a size
+    /// suffix, such as `mov` or `cmovl`, give `None`.
+    pub fn operand_sizes(&self) -> Option<(Size, Size)> {
+        let lower = self.mnemonic.to_ascii_lowercase();
+        let bytes = lower.as_bytes();
+        if bytes.len() == 6
+            && (lower.starts_with("movz") || lower.starts_with("movs"))
+            && let (Some(source), Some(destination)) = (
+                Size::from_suffix(bytes[4] as char),
+                Size::from_suffix(bytes[5] as char),
+            )
+        {
+            return Some((source, destination));
+        }
+        let (&suffix, stem) = bytes.split_last()?;
+        let size = Size::from_suffix(suffix as char)?;
+        SUFFIXED
+            .contains(&&lower[..stem.len()])
+            .then_some((size, size))
+    }
+
+    /// Whether the mnemonic has no size suffix but accepts one, such as `mov`
+    /// or `add`. The assembler then infers the size from register operands.
+    pub fn accepts_size_suffix(&self) -> bool {
+        SUFFIXED.contains(&self.mnemonic.to_ascii_lowercase().as_str())
+    }
+}
+
+impl Register {
+    /// The size of a general-purpose register, such as `Long` for `%eax` or
+    /// `%r8d`.
+    pub fn size(&self) -> Option<Size> {
+        let name = self.name().to_ascii_lowercase();
+        let size = match name.as_str() {
+            "al" | "bl" | "cl" | "dl" | "ah" | "bh" | "ch" | "dh" | "sil" | "dil" | "bpl"
+            | "spl" => Size::Byte,
+            "ax" | "bx" | "cx" | "dx" | "si" | "di" | "bp" | "sp" => Size::Word,
+            "eax" | "ebx" | "ecx" | "edx" | "esi" | "edi" | "ebp" | "esp" => Size::Long,
+            "rax" | "rbx" | "rcx" | "rdx" | "rsi" | "rdi" | "rbp" | "rsp" => Size::Quad,
+            _ => {
+                let rest = name.strip_prefix('r')?;
+                let digits = rest.trim_end_matches(['b', 'w', 'd']);
+                if !matches!(digits.parse::<u8>(), Ok(8..=15)) {
+                    return None;
+                }
+                match &rest[digits.len()..] {
+                    "b" => Size::Byte,
+                    "w" => Size::Word,
+                    "d" => Size::Long,
+                    "" => Size::Quad,
+                    _ => return None,
+                }
+            }
+        };
+        Some(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(text: &str) -> Option<(Size, Size)> {
+        text.parse::<Instruction>().unwrap().operand_sizes()
+    }
+
+    #[test]
+    fn reads_size_suffixes() {
+        assert_eq!(sizes("movl $1, %eax"), Some((Size::Long, Size::Long)));
+        assert_eq!(sizes("movzbl %al, %eax"), Some((Size::Byte, Size::Long)));
+        assert_eq!(sizes("SHRQ $3, %rax"), Some((Size::Quad, Size::Quad)));
+        assert_eq!(sizes("mov %eax, %ebx"), None);
+        assert_eq!(sizes("cmovl %eax, %ebx"), None);
+        assert_eq!(sizes("movsd %xmm0, %xmm1"), None);
+
+        let instruction: Instruction = "ADD %eax, %ebx".parse().unwrap();
+        assert!(instruction.accepts_size_suffix());
+        let instruction: Instruction = "addl %eax, %ebx".parse().unwrap();
+        assert!(!instruction.accepts_size_suffix());
+    }
+
+    #[test]
+    fn sizes_general_purpose_registers() {
+        let size = |name| Register::new(name).size();
+        assert_eq!(size("AL"), Some(Size::Byte));
+        assert_eq!(size("r10w"), Some(Size::Word));
+        assert_eq!(size("r8d"), Some(Size::Long));
+        assert_eq!(size("r15"), Some(Size::Quad));
+        assert_eq!(size("r16"), None);
+        assert_eq!(size("xmm0"), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Arguments of the `asm!` family of macros.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Block, Expr, ExprLit, Ident, Lit, LitStr, Token, parenthesized, token};
+
+mod kw {
+    syn::custom_keyword!(out);
+    syn::custom_keyword!(lateout);
+    syn::custom_keyword!(inout);
+    syn::custom_keyword!(inlateout);
+    syn::custom_keyword!(sym);
+    syn::custom_keyword!(label);
+    syn::custom_keyword!(options);
+    syn::custom_keyword!(clobber_abi);
+    syn::custom_keyword!(target_feature);
+    syn::custom_keyword!(enable);
+}
+
+/// The parsed input of `asm_att!`, `global_asm_att!` or `naked_asm_att!`.
+pub(crate) struct AsmArgs {
+    pub templates: Vec<Expr>,
+    pub operands: Vec<Operand>,
+    /// The options of every `options(...)` group, in order.
+    pub options: Vec<Ident>,
+    pub clobber_abis: Vec<TokenStream>,
+    /// The features of every `target_feature(enable = "...")`, which the
+    /// surrounding function enables, so that the template may use them
+    /// without a warning.
+    pub enabled_features: Vec<String>,
+    /// The Rust block after `; else`, used on targets without AT&T syntax.
+    pub fallback: Option<Block>,
+}
+
+pub(crate) struct Operand {
+    pub name: Option<Ident>,
+    pub kind: OperandKind,
+}
+
+pub(crate) enum OperandKind {
+    Reg {
+        dir: Direction,
+        span: Span,
+        reg: RegSpec,
+        expr: Expr,
+        out_expr: Option<Box<Expr>>,
+    },
+    Const(Expr),
+    Sym(Expr),
+    Label(Block),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    In,
+    Out,
+    LateOut,
+    InOut,
+    InLateOut,
+}
+
+pub(crate) enum RegSpec {
+    /// A register class such as `reg` or `xmm_reg`.
+    Class(Ident),
+    /// An explicit register such as `"rax"`.
+    Explicit(LitStr),
+}
+
+impl AsmArgs {
+    /// The template string literals, or `None` if any template is produced
+    /// by a macro such as `concat!` and therefore cannot be inspected.
+    pub fn template_literals(&self) -> Option<Vec<&LitStr>> {
+        self.templates
+            .iter()
+            .map(|template| match template {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => Some(lit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replaces the text of the template literal at `index`, keeping its span
+    /// for error messages.
+    pub fn replace_template(&mut self, index: usize, value: &str) {
+        let span = self.templates[index].span();
+        self.templates[index] = Expr::Lit(ExprLit {
+            attrs: Vec::new(),
+            lit: Lit::Str(LitStr::new(value, span)),
+        });
+    }
+}
+
+impl Parse for AsmArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = AsmArgs {
+            templates: Vec::new(),
+            operands: Vec::new(),
+            options: Vec::new(),
+            clobber_abis: Vec::new(),
+            enabled_features: Vec::new(),
+            fallback: None,
+        };
+        let mut in_templates = true;
+        while !input.is_empty() {
+            if input.peek(kw::options) && input.peek2(token::Paren) {
+                in_templates = false;
+                input.parse::<kw::options>()?;
+                let content;
+                parenthesized!(content in input);
+                let options = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                args.options.extend(options);
+            } else if input.peek(kw::clobber_abi) && input.peek2(token::Paren) {
+                in_templates = false;
+                let keyword: kw::clobber_abi = input.parse()?;
+                let content;
+                let paren = parenthesized!(content in input);
+                let abis: TokenStream = content.parse()?;
+                let mut tokens = keyword.to_token_stream();
+                paren.surround(&mut tokens, |tokens| abis.to_tokens(tokens));
+                args.clobber_abis.push(tokens);
+            } else if input.peek(kw::target_feature) && input.peek2(token::Paren) {
+                in_templates = false;
+                input.parse::<kw::target_feature>()?;
+                let content;
+                parenthesized!(content in input);
+                content.parse::<kw::enable>()?;
+                content.parse::<Token![=]>()?;
+                let features: LitStr = content.parse()?;
+                if !content.is_empty() {
+                    return Err(content.error("expected `enable = \"...\"`"));
+                }
+                let value = features.value();
+                args.enabled_features
+                    .extend(value.split(',').map(|feature| feature.trim().to_string()));
+            } else if in_templates && !is_operand_start(input) {
+                args.templates.push(input.parse()?);
+            } else {
+                in_templates = false;
+                args.operands.push(input.parse()?);
+            }
+
+            if input.peek(Token![;]) {
+                input.parse::<Token![;]>()?;
+                input.parse::<Token![else]>()?;
+                args.fallback = Some(input.parse()?);
+                if !input.is_empty() {
+                    return Err(input.error("unexpected tokens after the fallback block"));
+                }
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        if args.templates.is_empty() {
+            return Err(input.error("requires at least a template string argument"));
+        }
+        Ok(args)
+    }
+}
+
+fn is_operand_start(input: ParseStream) -> bool {
+    (input.peek(Ident) && input.peek2(Token![=]) && !input.peek2(Token![==]))
+        || input.peek(Token![in])
+        || input.peek(kw::out)
+        || input.peek(kw::lateout)
+        || input.peek(kw::inout)
+        || input.peek(kw::inlateout)
+        || input.peek(Token![const])
+        || (input.peek(kw::sym) && !input.peek2(Token![!]))
+        || (input.peek(kw::label) && input.peek2(token::Brace))
+}
+
+impl Parse for Operand {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = if input.peek(Ident) && input.peek2(Token![=]) {
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Some(name)
+        } else {
+            None
+        };
+
+        let kind = if input.peek(Token![const]) {
+            input.parse::<Token![const]>()?;
+            OperandKind::Const(input.parse()?)
+        } else if input.peek(kw::sym) {
+            input.parse::<kw::sym>()?;
+            OperandKind::Sym(input.parse()?)
+        } else if input.peek(kw::label) {
+            input.parse::<kw::label>()?;
+            OperandKind::Label(input.parse()?)
+        } else {
+            let span = input.span();
+            let dir = if input.peek(Token![in]) {
+                input.parse::<Token![in]>()?;
+                Direction::In
+            } else if input.peek(kw::out) {
+                input.parse::<kw::out>()?;
+                Direction::Out
+            } else if input.peek(kw::lateout) {
+                input.parse::<kw::lateout>()?;
+                Direction::LateOut
+            } else if input.peek(kw::inout) {
+                input.parse::<kw::inout>()?;
+                Direction::InOut
+            } else if input.peek(kw::inlateout) {
+                input.parse::<kw::inlateout>()?;
+                Direction::InLateOut
+            } else {
+                return Err(input.error(
+                    "expected operand, clobber_abi, options, or additional template string",
+                ));
+            };
+
+            let content;
+            parenthesized!(content in input);
+            let reg = if content.peek(LitStr) {
+                RegSpec::Explicit(content.parse()?)
+            } else {
+                RegSpec::Class(content.parse()?)
+            };
+            let expr = input.parse()?;
+            let out_expr = match dir {
+                Direction::InOut | Direction::InLateOut if input.peek(Token![=>]) => {
+                    input.parse::<Token![=>]>()?;
+                    Some(Box::new(input.parse()?))
+                }
+                _ => None,
+            };
+            OperandKind::Reg {
+                dir,
+                span,
+                reg,
+                expr,
+                out_expr,
+            }
+        };
+        Ok(Operand { name, kind })
+    }
+}
+
+impl Direction {
+    fn keyword(self, span: Span) -> TokenStream {
+        let keyword = match self {
+            Direction::In => return quote::quote_spanned!(span=> in),
+            Direction::Out => "out",
+            Direction::LateOut => "lateout",
+            Direction::InOut => "inout",
+            Direction::InLateOut => "inlateout",
+        };
+        Ident::new(keyword, span).into_token_stream()
+    }
+}
+
+impl ToTokens for RegSpec {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            RegSpec::Class(class) => class.to_tokens(tokens),
+            RegSpec::Explicit(reg) => reg.to_tokens(tokens),
+        }
+    }
+}
+
+impl ToTokens for Operand {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        if let Some(name) = &self.name {
+            tokens.extend(quote!(#name =));
+        }
+        tokens.extend(match &self.kind {
+            OperandKind::Reg {
+                dir,
+                span,
+                reg,
+                expr,
+                out_expr,
+            } => {
+                let dir = dir.keyword(*span);
+                let out_expr = out_expr.as_ref().map(|out_expr| quote!(=> #out_expr));
+                quote!(#dir(#reg) #expr #out_expr)
+            }
+            OperandKind::Const(expr) => quote!(const #expr),
+            OperandKind::Sym(path) => quote!(sym #path),
+            OperandKind::Label(block) => quote!(label #block),
+        });
+    }
+}
+
+impl ToTokens for AsmArgs {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let templates = &self.templates;
+        let operands = &self.operands;
+        let clobber_abis = &self.clobber_abis;
+        tokens.extend(quote! {
+            #(#templates,)*
+            #(#operands,)*
+            #(#clobber_abis,)*
+        });
+        if !self.options.is_empty() {
+            let options = &self.options;
+            tokens.extend(quote!(options(#(#options),*)));
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Clobber inference for `asm_att_auto!`.
+//!
+//! Every register the template writes, either by naming it as a destination
+//! or implicitly, such as `%rcx` for a `rep` prefix, must be known to the
+//! compiler. Registers that are not already outputs are added as
+//! `out("reg") _` clobbers.
+
+use proc_macro2::Span;
+use quote::quote;
+use syn::{LitStr, parse_quote};
+
+use asm_att_core::syntax::{Instruction, Location, Register, Template};
+
+use crate::args::{AsmArgs, Direction, OperandKind, RegSpec};
+use crate::expand::located_error;
+
+/// Registers the compiler relies on, which can be neither operands nor
+/// clobbers. Templates that write them must restore them.
+const RESERVED: &[&str] = &["rbx", "rbp", "rsp"];
+
+/// A register the template writes.
+struct Written {
+    /// The name every alias of the register shares, such as `rax` for `%al`
+    /// or `xmm1` for `%ymm1`.
+    family: String,
+    /// The name to clobber: the family, except for vector registers, which
+    /// are clobbered by the widest name written.
+    clobber: String,
+    /// The instruction writing it, for error messages.
+    instruction: String,
+    /// Where the instruction is.
+    location: Location,
+}
+
+/// Appends an `out("reg") _` operand for every register the template writes
+/// that is not an output yet, and a `clobber_abi("C")` if it calls a function.
+pub(crate) fn infer(template: &Template, args: &mut AsmArgs) -> syn::Result<()> {
+    let mut written: Vec<Written> = Vec::new();
+    let mut calls = false;
+    for (location, instruction) in template.instructions() {
+        calls |= instruction.calls();
+        for name in instruction.written_registers() {
+            let Some(family) = family(&name) else {
+                continue;
+            };
+            // Pushes, pops, calls and returns balance the stack pointer.
+            if family == "rsp" && instruction.is_stack_instruction() {
+                continue;
+            }
+            match written.iter_mut().find(|w| w.family == family) {
+                Some(existing) => {
+                    if vector_width(&name) > vector_width(&existing.clobber) {
+                        existing.clobber = name;
+                    }
+                }
+                None => written.push(Written {
+                    clobber: if family.starts_with("xmm") {
+                        name
+                    } else {
+                        family.clone()
+                    },
+                    family,
+                    instruction: instruction.to_string(),
+                    location,
+                }),
+            }
+        }
+    }
+
+    for register in written {
+        if RESERVED.contains(&register.family.as_str()) {
+            if restores(template, &register.family) {
+                continue;
+            }
+            let message = format!(
+                "`{}` writes `%{}`, which cannot be clobbered; push and pop it around the code",
+                register.instruction, register.family,
+            );
+            return Err(match args.template_literals() {
+                Some(literals) => located_error(&literals, register.location, message),
+                None => syn::Error::new(Span::call_site(), message),
+            });
+        }
+        match bound(args, &register.family) {
+            Some((Direction::In, lit)) => {
+                let message = format!(
+                    "`{}` writes `%{}`, which is bound to an input operand; \
+                     use `inout({:?}) <expr> => _` instead",
+                    register.instruction,
+                    register.clobber,
+                    lit.value(),
+                );
+                return Err(syn::Error::new(lit.span(), message));
+            }
+            Some(_) => {}
+            None => {
+                let clobber = LitStr::new(&register.clobber, Span::call_site());
+                args.operands.push(parse_quote!(out(#clobber) _));
+            }
+        }
+    }
+
+    if calls && args.clobber_abis.is_empty() {
+        args.clobber_abis.push(quote!(clobber_abi("C")));
+    }
+    Ok(())
+}
+
+/// Whether the template visibly restores a reserved register: by pushing and
+/// popping it, or for `%rsp`, by restoring the frame pointer or adding back
+/// what it subtracts.
+fn restores(template: &Template, family: &str) -> bool {
+    if family != "rsp" {
+        return template.restores(family);
+    }
+    let adjusts = |stem: &str| {
+        template.instructions().any(|(_, instruction)| {
+            instruction.mnemonic.trim_end_matches('q') == stem && writes_rsp(instruction)
+        })
+    };
+    template.restores("rbp") || (adjusts("sub") && adjusts("add"))
+}
+
+fn writes_rsp(instruction: &Instruction) -> bool {
+    instruction
+        .written_registers()
+        .iter()
+        .any(|name| family(name).as_deref() == Some("rsp"))
+}
+
+/// The explicit register operand bound to a register family, if any.
+fn bound<'a>(args: &'a AsmArgs, register: &str) -> Option<(Direction, &'a LitStr)> {
+    args.operands
+        .iter()
+        .find_map(|operand| match &operand.kind {
+            OperandKind::Reg {
+                dir,
+                reg: RegSpec::Explicit(lit),
+                ..
+            } if family(&lit.value()).as_deref() == Some(register) => Some((*dir, lit)),
+            _ => None,
+        })
+}
+
+/// The name shared by every alias of a register that can be clobbered.
+fn family(name: &str) -> Option<String> {
+    Register::new(name).family()
+}
+
+fn vector_width(name: &str) -> u8 {
+    match name.as_bytes().first() {
+        Some(b'z') => 2,
+        Some(b'y') => 1,
+        _ => 0,
+    }
+}
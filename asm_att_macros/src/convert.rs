@@ -0,0 +1,74 @@
+//! `intel_to_att!`, on its own or as a template of the other macros.
+//!
+//! Inside of `asm_att!` and friends the operands are known, so that the
+//! placeholders of `const` operands get the `$` of immediates and register
+//! operands that are called or jumped through get the `*` of indirect
+//! branches.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, ExprMacro, LitStr, Token};
+
+use asm_att_core::convert::{OperandClass, adapt_placeholders};
+use asm_att_core::syntax::{Placeholder, PlaceholderArg, Template};
+
+use crate::args::{AsmArgs, OperandKind};
+use crate::expand::template_error;
+
+/// Expands `intel_to_att!` to the AT&T template string literal.
+pub(crate) fn intel_to_att(input: TokenStream) -> syn::Result<TokenStream> {
+    let literals = Punctuated::<LitStr, Token![,]>::parse_terminated.parse2(input)?;
+    let template = parse_intel(&literals)?.to_string();
+    Ok(quote!(#template))
+}
+
+/// Replaces the `intel_to_att!(...)` templates of `args` with the AT&T
+/// template literal they expand to.
+pub(crate) fn expand_templates(args: &mut AsmArgs) -> syn::Result<()> {
+    for index in 0..args.templates.len() {
+        let Expr::Macro(ExprMacro { mac, .. }) = &args.templates[index] else {
+            continue;
+        };
+        if mac
+            .path
+            .segments
+            .last()
+            .is_none_or(|segment| segment.ident != "intel_to_att")
+        {
+            continue;
+        }
+        let literals = mac.parse_body_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?;
+        let mut template = parse_intel(&literals)?;
+        adapt_placeholders(&mut template, |placeholder| {
+            kind(placeholder, args).map(|kind| match kind {
+                OperandKind::Reg { .. } => OperandClass::Register,
+                OperandKind::Const(_) => OperandClass::Const,
+                OperandKind::Sym(_) | OperandKind::Label(_) => OperandClass::Symbol,
+            })
+        });
+        args.replace_template(index, &template.to_string());
+    }
+    Ok(())
+}
+
+fn parse_intel(literals: &Punctuated<LitStr, Token![,]>) -> syn::Result<Template> {
+    let literals: Vec<&LitStr> = literals.iter().collect();
+    let values: Vec<String> = literals.iter().map(|literal| literal.value()).collect();
+    let texts: Vec<&str> = values.iter().map(String::as_str).collect();
+    Template::parse_intel(&texts).map_err(|error| template_error(&literals, error))
+}
+
+/// The operand a placeholder refers to, unless it is a `{}` placeholder.
+pub(crate) fn kind<'a>(placeholder: &Placeholder, args: &'a AsmArgs) -> Option<&'a OperandKind> {
+    let operand = match &placeholder.arg {
+        PlaceholderArg::Next => None,
+        PlaceholderArg::Index(index) => args.operands.get(*index),
+        PlaceholderArg::Name(name) => args
+            .operands
+            .iter()
+            .find(|operand| operand.name.as_ref().is_some_and(|n| n == name)),
+    }?;
+    Some(&operand.kind)
+}
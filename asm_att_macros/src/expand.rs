@@ -0,0 +1,961 @@
+//! Expansion of the macros into their `core::arch` counterparts.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Block, Expr, ExprLit, Ident, Lit, LitStr};
+
+use asm_att_core::syntax::{self, Location, ParseError, Template};
+
+use crate::args::AsmArgs;
+use crate::sizes::{self, TypeChecks};
+use crate::{
+    clobbers, convert, features, immediates, labels, memory, options, registers, suffixes, writes,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Macro {
+    Asm,
+    AsmAuto,
+    AsmAsIntel,
+    GlobalAsm,
+    NakedAsm,
+}
+
+impl Macro {
+    fn name(self) -> &'static str {
+        match self {
+            Macro::Asm => "asm_att",
+            Macro::AsmAuto => "asm_att_auto",
+            Macro::AsmAsIntel => "asm_att_as_intel",
+            Macro::GlobalAsm => "global_asm_att",
+            Macro::NakedAsm => "naked_asm_att",
+        }
+    }
+
+    /// Whether the macro expands to `asm!`, as opposed to `global_asm!` or
+    /// `naked_asm!`.
+    fn is_asm(self) -> bool {
+        matches!(self, Macro::Asm | Macro::AsmAuto | Macro::AsmAsIntel)
+    }
+}
+
+/// Parses the arguments of a macro, rewrites the labels, `mem!` and
+/// immediates of its template literals to plain AT&T syntax, and parses and
+/// checks the template if every template is a literal. Also returns the
+/// placeholders of the rewritten `{name:imm}`.
+pub(crate) fn prepare(input: TokenStream) -> syn::Result<(AsmArgs, Option<Template>, Vec<String>)> {
+    let mut args: AsmArgs = syn::parse2(input)?;
+    convert::expand_templates(&mut args)?;
+    labels::expand(&mut args)?;
+    memory::expand(&mut args)?;
+    let immediates = immediates::expand(&mut args);
+    escape_decorators(&mut args);
+    let template = match args.template_literals() {
+        Some(literals) => {
+            let template = parse_template(&literals)?;
+            registers::check(&template, &literals)?;
+            check_prefixes(&template, &literals)?;
+            Some(template)
+        }
+        None => None,
+    };
+    Ok((args, template, immediates))
+}
+
+pub(crate) fn expand(mac: Macro, input: TokenStream) -> syn::Result<TokenStream> {
+    let (mut args, mut template, immediates) = prepare(input)?;
+    let mut warnings = Vec::new();
+    if let (Some(template), Some(literals)) = (&template, args.template_literals())
+        && mac.is_asm()
+    {
+        options::check(template, &args.options)?;
+        writes::check(template, &literals, &args)?;
+        warnings = features::check(template, &literals, &args.enabled_features);
+    }
+    if mac == Macro::AsmAuto {
+        let Some(template) = &mut template else {
+            return Err(syn::Error::new_spanned(
+                &args.templates[0],
+                "`asm_att_auto!` needs string literal templates to infer suffixes, clobbers and options",
+            ));
+        };
+        suffixes::infer(template, &mut args)?;
+        clobbers::infer(template, &mut args)?;
+        options::infer(template, &mut args);
+    }
+    let checks = match (&template, args.template_literals()) {
+        (Some(template), Some(literals)) if mac.is_asm() => {
+            let mut checks = sizes::check(template, &literals, &args)?;
+            checks
+                .before
+                .extend(immediates::check(template, &literals, &args, &immediates)?);
+            checks
+        }
+        _ => TypeChecks::default(),
+    };
+    if mac == Macro::AsmAsIntel {
+        let Some(template) = &template else {
+            return Err(syn::Error::new_spanned(
+                &args.templates[0],
+                "`asm_att_as_intel!` needs string literal templates to convert them to Intel syntax",
+            ));
+        };
+        to_intel(template, &mut args);
+    }
+    merge_options(mac, &mut args.options)?;
+
+    let fallback = fallback(mac, args.fallback.take())?;
+    let x86 = quote!(any(target_arch = "x86", target_arch = "x86_64"));
+    let invocation = match mac {
+        Macro::Asm | Macro::AsmAuto | Macro::AsmAsIntel => quote!(::core::arch::asm!(#args)),
+        Macro::GlobalAsm => quote!(::core::arch::global_asm!(#args);),
+        Macro::NakedAsm => quote!(::core::arch::naked_asm!(#args);),
+    };
+    if !mac.is_asm() {
+        return Ok(quote! {
+            #[cfg(#x86)]
+            #invocation
+            #[cfg(not(#x86))]
+            #fallback
+        });
+    }
+
+    // A single block, whose value is that of `asm!` on x86 and that of the
+    // fallback elsewhere, so that `asm_att!` remains an expression, for
+    // instance of type `!` with `options(noreturn)`. The checks of outputs
+    // must follow `asm!`, which then cannot be the tail of the block, but
+    // `asm!` with outputs is of type `()` anyway.
+    let (before, after) = (checks.before, checks.after);
+    let invocation = if after.is_empty() {
+        invocation
+    } else {
+        quote! {
+            #invocation;
+            #(let _ = #after;)*
+        }
+    };
+    let (features, warnings): (Vec<_>, Vec<_>) = warnings.into_iter().unzip();
+    Ok(quote! {
+        {
+            #(#[cfg(all(#x86, not(target_feature = #features)))] #warnings)*
+            #[cfg(#x86)]
+            {
+                #(let _ = #before;)*
+                #invocation
+            }
+            #[cfg(not(#x86))]
+            #fallback
+        }
+    })
+}
+
+/// Escapes the AVX-512 decorators such as `{%k1}` in the template literals,
+/// which `asm!` would otherwise take for placeholders.
+fn escape_decorators(args: &mut AsmArgs) {
+    let names: Vec<String> = args
+        .operands
+        .iter()
+        .filter_map(|operand| operand.name.as_ref().map(ToString::to_string))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let escaped: Vec<(usize, String)> = args
+        .templates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, template)| match template {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(literal),
+                ..
+            }) => match syntax::escape_decorators(&literal.value(), &names) {
+                Cow::Owned(value) => Some((index, value)),
+                Cow::Borrowed(_) => None,
+            },
+            _ => None,
+        })
+        .collect();
+    for (index, value) in escaped {
+        args.replace_template(index, &value);
+    }
+}
+
+/// What to expand to on targets without AT&T syntax: the `; else` block of
+/// `asm_att!`, or an error.
+fn fallback(mac: Macro, block: Option<Block>) -> syn::Result<TokenStream> {
+    match block {
+        Some(block) if mac.is_asm() => Ok(quote!(#block)),
+        Some(block) => Err(syn::Error::new_spanned(
+            block,
+            format!(
+                "`; else {{ ... }}` is only supported by `asm_att!`, not by `{}!`",
+                mac.name()
+            ),
+        )),
+        None if mac.is_asm() => {
+            let message = format!(
+                "`{}!` uses AT&T syntax, which only exists on x86 and x86_64 targets; \
+                 end the arguments with `; else {{ ... }}` to provide a Rust fallback for other targets",
+                mac.name()
+            );
+            Ok(quote!({ ::core::compile_error!(#message) }))
+        }
+        None => {
+            let message = format!(
+                "`{}!` uses AT&T syntax, which only exists on x86 and x86_64 targets",
+                mac.name()
+            );
+            Ok(quote!(::core::compile_error!(#message);))
+        }
+    }
+}
+
+/// Replaces the template literals with their Intel syntax.
+fn to_intel(template: &Template, args: &mut AsmArgs) {
+    for index in 0..args.templates.len() {
+        let lines: Vec<String> = template
+            .statements
+            .iter()
+            .filter(|(location, _)| location.template == index)
+            .map(|(_, statement)| statement.intel().to_string())
+            .collect();
+        args.replace_template(index, &lines.join("\n"));
+    }
+}
+
+/// Adds `att_syntax` to the options given by the caller, unless it is already
+/// there, and rejects the conflicting `intel_syntax`. `asm_att_as_intel!`
+/// drops `att_syntax` instead.
+fn merge_options(mac: Macro, options: &mut Vec<Ident>) -> syn::Result<()> {
+    if mac == Macro::AsmAsIntel {
+        options.retain(|option| option != "att_syntax");
+        return Ok(());
+    }
+    if let Some(intel) = options.iter().find(|option| *option == "intel_syntax") {
+        let message = format!(
+            "`intel_syntax` conflicts with the AT&T syntax of `{}!`",
+            mac.name()
+        );
+        return Err(syn::Error::new(intel.span(), message));
+    }
+    if !options.iter().any(|option| option == "att_syntax") {
+        options.insert(0, Ident::new("att_syntax", Span::call_site()));
+    }
+    Ok(())
+}
+
+/// Parses the template literals, pointing errors at the offending literal.
+pub(crate) fn parse_template(literals: &[&LitStr]) -> syn::Result<Template> {
+    let values: Vec<String> = literals.iter().map(|lit| lit.value()).collect();
+    let texts: Vec<&str> = values.iter().map(String::as_str).collect();
+    Template::parse(&texts).map_err(|error| template_error(literals, error))
+}
+
+/// Checks that the `lock`, repeat and HLE prefixes of every instruction
+/// apply to it.
+fn check_prefixes(template: &Template, literals: &[&LitStr]) -> syn::Result<()> {
+    for (location, instruction) in template.instructions() {
+        instruction
+            .check_prefixes()
+            .map_err(|message| located_error(literals, location, message))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn template_error(literals: &[&LitStr], error: ParseError) -> syn::Error {
+    located_error(literals, error.location(), error.message())
+}
+
+/// An error about the statement at `location`, pointing at its literal.
+pub(crate) fn located_error(
+    literals: &[&LitStr],
+    location: Location,
+    message: impl Display,
+) -> syn::Error {
+    let literal = literals[location.template];
+    if literal.value().contains('\n') {
+        syn::Error::new(
+            literal.span(),
+            format!("{message} (line {} of this template)", location.line + 1),
+        )
+    } else {
+        syn::Error::new(literal.span(), message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(mac: Macro, input: &str) -> Result<String, String> {
+        expand(mac, input.parse().unwrap())
+            .map(|tokens| tokens.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn forwards_arguments() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""cmp $0, {n}", "je 2f", c = inout(reg) counter, n = in(reg) number, options(nostack)"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            ::core::arch::asm!(
+                "cmp $0, {n}", "je 2f",
+                c = inout(reg) counter, n = in(reg) number,
+                options(att_syntax, nostack)
+            )
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let error = expand_str(Macro::GlobalAsm, r#"".global f\nf:\n  movl $, %eax""#).unwrap_err();
+        assert_eq!(
+            error,
+            "expected an immediate value after `$` (line 3 of this template)"
+        );
+    }
+
+    #[test]
+    fn merges_options() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""ud2", options(noreturn), options(att_syntax, nostack)"#,
+        )
+        .unwrap();
+        let expected = quote!(::core::arch::asm!(
+            "ud2",
+            options(noreturn, att_syntax, nostack)
+        ));
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let error = expand_str(Macro::Asm, r#""nop", options(nostack, intel_syntax)"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`intel_syntax` conflicts with the AT&T syntax of `asm_att!`"
+        );
+    }
+
+    #[test]
+    fn gates_on_the_target_architecture() {
+        let expanded =
+            expand_str(Macro::Asm, r#""pause"; else { core::hint::spin_loop() }"#).unwrap();
+        let expected = quote! {
+            {
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                {
+                    ::core::arch::asm!("pause", options(att_syntax))
+                }
+                #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+                { core::hint::spin_loop() }
+            }
+        };
+        assert_eq!(expanded, expected.to_string());
+
+        let expanded = expand_str(Macro::Asm, r#""ud2", options(noreturn)"#).unwrap();
+        let expected = quote! {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                ::core::arch::asm!("ud2", options(att_syntax, noreturn))
+            }
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let expanded = expand_str(Macro::GlobalAsm, r#"".text""#).unwrap();
+        assert!(expanded.contains("compile_error"), "{expanded}");
+
+        let error = expand_str(Macro::NakedAsm, r#""ret"; else { loop {} }"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`; else { ... }` is only supported by `asm_att!`, not by `naked_asm_att!`"
+        );
+    }
+
+    #[test]
+    fn infers_clobbers() {
+        let error = expand_str(
+            Macro::AsmAuto,
+            r#""movq {src}, %rsi", "rep movsb", src = in(reg) src, in("rdi") dst"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`rep movsb` writes `%rdi`, which is bound to an input operand; \
+             use `inout(\"rdi\") <expr> => _` instead"
+        );
+
+        let expanded = expand_str(
+            Macro::AsmAuto,
+            r#""movq {src}, %rsi", "movl {n:e}, %ecx", "rep movsb", "vmovdqu %xmm1, %xmm2", "vmovdqu %ymm1, %ymm2",
+               "cmpq %rax, %rdx", src = in(reg) src, inout("rdi") dst => _, n = in(reg) n"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            src = in(reg) src, inout("rdi") dst => _, n = in(reg) n,
+            out("rsi") _, out("rcx") _, out("ymm2") _,
+            options(att_syntax, nostack)
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let expanded = expand_str(Macro::AsmAuto, r#""call {f}", f = sym f"#).unwrap();
+        assert!(expanded.contains(r#"clobber_abi ("C")"#), "{expanded}");
+
+        let expanded = expand_str(
+            Macro::AsmAuto,
+            r#""vpgatherdd %ymm2, ({p},%ymm3,4), %ymm1", p = in(reg) p"#,
+        )
+        .unwrap();
+        assert!(
+            expanded.contains(r#"out ("ymm2") _ , out ("ymm1") _"#),
+            "{expanded}"
+        );
+
+        let expanded = expand_str(Macro::AsmAuto, r#""vzeroupper""#).unwrap();
+        assert!(
+            expanded.contains(r#"out ("ymm0") _"#) && expanded.contains(r#"out ("ymm15") _"#),
+            "{expanded}"
+        );
+
+        // Registers the compiler reserves must be restored by the template.
+        let error = expand_str(Macro::AsmAuto, r#""cpuid", inout("eax") leaf => a"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`cpuid` writes `%rbx`, which cannot be clobbered; push and pop it around the code"
+        );
+        let expanded = expand_str(
+            Macro::AsmAuto,
+            r#""pushq %rbx", "cpuid", "popq %rbx", inout("eax") leaf => a"#,
+        )
+        .unwrap();
+        assert!(expanded.contains(r#"out ("rdx") _"#), "{expanded}");
+        assert!(!expanded.contains(r#"out ("rbx") _"#), "{expanded}");
+        assert!(
+            expand_str(Macro::AsmAuto, r#""movq %rax, %rsp""#).is_err(),
+            "an unrestored stack pointer"
+        );
+        assert!(expand_str(Macro::AsmAuto, r#""subq $8, %rsp", "addq $8, %rsp""#).is_ok());
+    }
+
+    #[test]
+    fn infers_options() {
+        let options = |input: &str| {
+            let expanded = expand_str(Macro::AsmAuto, input).unwrap();
+            let options = &expanded[expanded.find("options").unwrap()..];
+            options[..options.find(')').unwrap() + 1].to_string()
+        };
+        assert_eq!(
+            options(r#""movq {a}, {b}", "shlx {a}, {b}, {b}", a = in(reg) a, b = out(reg) b"#),
+            "options (att_syntax , preserves_flags , nomem , nostack)"
+        );
+        assert_eq!(
+            options(r#""1:", "movq 8({p}), {x}", "jmp 1b", p = in(reg) p, x = out(reg) x"#),
+            "options (att_syntax , preserves_flags , readonly , nostack)"
+        );
+        assert_eq!(
+            options(r#""addq $1, -8(%rsp)", options(nomem)"#),
+            "options (att_syntax , nomem)"
+        );
+        assert_eq!(options(r#"".byte 0x90""#), "options (att_syntax)");
+
+        // Implicit, symbolic and privileged memory accesses.
+        assert_eq!(
+            options(r#""xlatb""#),
+            "options (att_syntax , preserves_flags , readonly , nostack)"
+        );
+        assert_eq!(
+            options(r#""maskmovdqu %xmm1, %xmm0""#),
+            "options (att_syntax , preserves_flags , nostack)"
+        );
+        assert_eq!(
+            options(r#""movl {c}, %eax", c = sym COUNTER"#),
+            "options (att_syntax , preserves_flags , readonly , nostack)"
+        );
+        assert_eq!(options(r#""movq %rax, %cr3""#), "options (att_syntax)");
+        assert_eq!(options(r#""iretq""#), "options (att_syntax)");
+        assert_eq!(options(r#""vfrobnicate %ymm0""#), "options (att_syntax)");
+
+        let expanded = expand_str(Macro::AsmAuto, r#""pcmpestriq $0, %xmm1, %xmm0""#).unwrap();
+        assert!(expanded.contains(r#"out ("rcx") _"#), "{expanded}");
+        assert!(!expanded.contains("preserves_flags"), "{expanded}");
+
+        let error = expand_str(
+            Macro::Asm,
+            r#""push %rbx", "cpuid", "pop %rbx", options(nostack)"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`push %rbx` uses the stack, which `options(nostack)` promises not to"
+        );
+    }
+
+    #[test]
+    fn checks_operand_sizes() {
+        let error = expand_str(
+            Macro::Asm,
+            r#""movq {y}, {x}", x = out(reg) x, y = in(reg_byte) y"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`movq` operates on 64-bit operands, but `{y}` is an 8-bit register"
+        );
+
+        let error = expand_str(Macro::Asm, r#""shll %cl, {0:x}", inout(reg) x"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`shll` operates on 32-bit operands, but `{0:x}` is a 16-bit register"
+        );
+
+        let error = expand_str(Macro::Asm, r#""movzbl {}, %eax", in(reg) x"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`movzbl` operates on 8-bit operands, but `{}` is a `reg` register, \
+             which is at least 16 bits wide; use a `reg_byte` operand or `{:l}`"
+        );
+
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""movl {x}, {y:e}", "addq $1, {z}", "movw ({p}), {w:x}",
+               x = in(reg) x, y = out(reg) y, z = inout(reg) a => b, p = in(reg) p, w = out(reg) _"#,
+        )
+        .unwrap();
+        let before = quote! {
+            let _ = || ::asm_att::__private::suffix_l(&(x));
+            let _ = || ::asm_att::__private::suffix_q(&(a));
+            ::core::arch::asm!
+        };
+        assert!(expanded.contains(&before.to_string()), "{expanded}");
+        let after = quote! {
+            ;
+            let _ = || ::asm_att::__private::suffix_q(&(b));
+        };
+        let after = after.to_string();
+        assert!(expanded.contains(&format!("{after} }}")), "{expanded}");
+
+        // Without a modifier, placeholders are replaced with `%rax` and the
+        // like on x86_64, and `%eax` and the like on x86.
+        let error = expand_str(Macro::Asm, r#""incw {x}", x = inout(reg) x"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`incw` operates on 16-bit operands, but `{x}` is a 32-bit or 64-bit register; \
+             use `{x:x}`"
+        );
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""movl {y}, %eax", y = in(reg) y, out("eax") _"#,
+        )
+        .unwrap();
+        let wide = quote! {
+            #[cfg(target_arch = "x86_64")]
+            ::core::compile_error!(
+                "`movl` operates on 32-bit operands, but `{y}` is a 64-bit register; use `{y:e}`"
+            );
+        };
+        assert!(expanded.contains(&wide.to_string()), "{expanded}");
+    }
+
+    #[test]
+    fn rejects_writes_to_inputs() {
+        let error = expand_str(
+            Macro::Asm,
+            r#""movq {0}, {2}", "addq {1}, {2}", in(reg) a, in(reg) b, in(reg) c"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`movq {0}, {2}` writes `{2}`, which is an `in` operand; declare it with `inout` instead"
+        );
+
+        let error = expand_str(Macro::Asm, r#""movl $1, {n}", n = const 8"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`movl $1, {n}` writes `{n}`, which is a `const` operand and cannot be written"
+        );
+
+        let error = expand_str(
+            Macro::AsmAuto,
+            r#""xchgq {f}, {x}", f = sym f, x = inout(reg) x"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`xchgq {f}, {x}` writes `{f}`, which is a `sym` operand and cannot be written"
+        );
+
+        for template in [
+            r#""cmpq {a}, {b}", "jne {done}", "btq {a}, {b}""#,
+            r#""mulq {b}", "pushq {a}", "jmp {done}""#,
+        ] {
+            let input = format!("{template}, a = in(reg) a, b = in(reg) b, done = label {{}}");
+            expand_str(Macro::Asm, &input).unwrap();
+        }
+    }
+
+    #[test]
+    fn numbers_named_labels() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""jmp {label:check}", "2:", "{label:body}:", "decq {n}", "jmp 2b",
+               "{label:check}: testq {n}, {n}", "jnz {label:body}", n = inout(reg) n"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            ::core::arch::asm!(
+                "jmp 4f", "2:", "3:", "decq {n}", "jmp 2b",
+                "4: testq {n}, {n}", "jnz 3b", n = inout(reg) n,
+                options(att_syntax)
+            )
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let error = expand_str(Macro::Asm, r#""jmp {label:done}""#).unwrap_err();
+        assert_eq!(
+            error,
+            "`{label:done}` is never defined; define it with `{label:done}:`"
+        );
+        let error = expand_str(Macro::Asm, r#""{label:a}:", "{label:a}:""#).unwrap_err();
+        assert_eq!(error, "`{label:a}` is defined twice");
+    }
+
+    #[test]
+    fn builds_memory_references() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""movq mem!(8, {base}, {i}, 4), %rax", "movl mem!(%fs: -16, , {i:e}), %eax",
+               "leaq mem!({f}, %rip), %rdx", "movl mem!(, {p}), %ecx",
+               base = in(reg) base, i = in(reg) i, p = in("rsi") p, f = sym f,
+               out("rax") _, out("eax") _, out("rdx") _, out("ecx") _"#,
+        )
+        .unwrap();
+        assert!(
+            expanded.contains(
+                r#""movq 8({base},{i},4), %rax" , "movl %fs:-16(,{i:e}), %eax" , "leaq {f}(%rip), %rdx" , "movl ({p}), %ecx""#
+            ),
+            "{expanded}"
+        );
+
+        for (template, message) in [
+            (
+                "movq mem!(0, {base}, {i}, 3), %rax",
+                "the scale of `mem!` must be 1, 2, 4 or 8, not `3`",
+            ),
+            (
+                "movq mem!(0, {base:e}, {i:r}, 8), %rax",
+                "the base `{base:e}` is 32-bit but the index `{i:r}` is 64-bit; \
+                 both must have the same width",
+            ),
+            (
+                "movq mem!(0, %rax, %ebx), %rax",
+                "the base `%rax` is 64-bit but the index `%ebx` is 32-bit; \
+                 both must have the same width",
+            ),
+            (
+                "movq mem!(0, {base:x}), %rax",
+                "`{base:x}` is 16-bit, but addresses take 64-bit or 32-bit registers",
+            ),
+            (
+                "movq mem!(0, %rax, %rsp), %rax",
+                "`%rsp` cannot be an index",
+            ),
+            (
+                "movq mem!({f}, %rip, {i}), %rax",
+                "`mem!({f}, %rip, {i})` is relative to `%rip` and so takes no index",
+            ),
+            (
+                "movq mem!(0, {base}, , 2), %rax",
+                "`mem!(0, {base}, , 2)` has a scale but no index",
+            ),
+            (
+                "movq mem!(%ax: 0, {base}), %rax",
+                "`%ax` is not a segment register",
+            ),
+            (
+                "movq mem!(0, {f}), %rax",
+                "`{f}` is a `sym` operand, but `mem!` takes registers",
+            ),
+            (
+                "movq mem!(0), %rax",
+                "`mem!(0)` takes a displacement, a base, an index and a scale, \
+                 such as `mem!(8, {base}, {index}, 4)`",
+            ),
+        ] {
+            let input = format!(
+                "{template:?}, base = in(reg) base, i = in(reg) i, f = sym f, out(\"rax\") _"
+            );
+            assert_eq!(expand_str(Macro::Asm, &input).unwrap_err(), message);
+        }
+    }
+
+    #[test]
+    fn checks_immediates() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""addl {n:imm}, %eax", "shlq {0:imm}, %rdx", const 3, n = const N, out("eax") _, out("rdx") _"#,
+        )
+        .unwrap();
+        assert!(
+            expanded.contains(r#""addl ${n}, %eax" , "shlq ${0}, %rdx""#),
+            "{expanded}"
+        );
+        for check in [
+            "let value = (N) as i128 ; :: asm_att :: __private :: immediate (value , - 2147483648i128 , 4294967295i128 , \
+             \"`{n:imm}` of `addl` does not fit in an imm32, from -2147483648 to 4294967295\")",
+            "let value = (3) as i128 ; :: asm_att :: __private :: immediate (value , - 128i128 , 255i128 , \
+             \"`{0:imm}` of `shlq` does not fit in an imm8, from -128 to 255\")",
+        ] {
+            assert!(expanded.contains(check), "{expanded}");
+        }
+
+        let error = expand_str(Macro::Asm, r#""addl {x:imm}, %eax", x = in(reg) x"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`{x:imm}` refers to a register operand, but immediates take `const` or `sym` operands"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_registers() {
+        for (template, message) in [
+            (
+                "movq %rxa, %rbx",
+                "unknown register `%rxa`; did you mean `%rax`?",
+            ),
+            (
+                "movb $1, %r8l",
+                "unknown register `%r8l`; did you mean `%r8b`?",
+            ),
+            (
+                "movl 8(%rsp,%exc,4), %eax",
+                "unknown register `%exc`; did you mean `%ecx`?",
+            ),
+            (
+                "vaddps %zmm1, %zmm2, %zmm33",
+                "unknown register `%zmm33`; did you mean `%zmm3`?",
+            ),
+            ("movq %foo, %rax", "unknown register `%foo`"),
+        ] {
+            let input = format!("{template:?}");
+            assert_eq!(expand_str(Macro::Asm, &input).unwrap_err(), message);
+        }
+        expand_str(
+            Macro::GlobalAsm,
+            r#""movq %cr0, %rax", "fldl %st(1)", "kmovw %k1, %eax", "movq %fs:0, %rax""#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_misplaced_prefixes() {
+        let error = expand_str(Macro::Asm, r#""lock movq $1, ({p})", p = in(reg) p"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`lock` does not apply to `movq`, which is not a read-modify-write instruction"
+        );
+        let error =
+            expand_str(Macro::GlobalAsm, r#"".text\nf:\n  repe addq %rax, %rbx""#).unwrap_err();
+        assert_eq!(
+            error,
+            "`repe` does not apply to `addq`; it repeats `cmps` and `scas` (line 3 of this template)"
+        );
+    }
+
+    #[test]
+    fn warns_of_missing_features() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""vpaddd %ymm0, %ymm1, %ymm2", "pdep {a}, {b}, {c}", "vpsubd %ymm0, %ymm1, %ymm2", a = in(reg) a, b = in(reg) b, c = out(reg) c"#,
+        )
+        .unwrap();
+        assert_eq!(expanded.matches("# [must_use").count(), 2, "{expanded}");
+        assert!(
+            expanded.contains("not (target_feature = \"avx2\")"),
+            "{expanded}"
+        );
+        assert!(
+            expanded.contains("`pdep` needs the `bmi2` target feature"),
+            "{expanded}"
+        );
+        let expanded = expand_str(Macro::Asm, r#""addq $1, %rax", out("rax") _"#).unwrap();
+        assert!(!expanded.contains("# [must_use"), "{expanded}");
+        let expanded = expand_str(Macro::GlobalAsm, r#""vpaddd %ymm0, %ymm1, %ymm2""#).unwrap();
+        assert!(!expanded.contains("# [must_use"), "{expanded}");
+
+        // Features the surrounding function enables.
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""vpaddd %ymm0, %ymm1, %ymm2", "pdep {a}, {b}, {c}", a = in(reg) a, b = in(reg) b,
+               c = out(reg) c, target_feature(enable = "avx2, bmi2")"#,
+        )
+        .unwrap();
+        assert!(!expanded.contains("# [must_use"), "{expanded}");
+        assert!(!expanded.contains("enable"), "{expanded}");
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""pdep {a}, {b}, {c}", a = in(reg) a, b = in(reg) b, c = out(reg) c,
+               target_feature(enable = "avx2")"#,
+        )
+        .unwrap();
+        assert_eq!(expanded.matches("# [must_use").count(), 1, "{expanded}");
+    }
+
+    #[test]
+    fn lists_required_features() {
+        let expanded = crate::features::required_features(
+            r#""vaddps %zmm0, %zmm1, %zmm2", "crc32q %rax, %rbx", "vmovaps %xmm0, %xmm1""#
+                .parse()
+                .unwrap(),
+        )
+        .unwrap()
+        .to_string();
+        assert!(
+            expanded.contains(r#"& ["avx512f" , "sse4.2" , "avx"]"#),
+            "{expanded}"
+        );
+        let error = crate::features::required_features("concat!(\"pdep\")".parse().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "`required_features!` needs string literal templates to find their instructions"
+        );
+    }
+
+    #[test]
+    fn infers_suffixes() {
+        let expanded = expand_str(
+            Macro::AsmAuto,
+            r#""mov %eax, (%rdi); mov {x:e}, 4(%rdi) # mov", "add {b}, ({p})", "mov {a}, ({p})",
+               x = in(reg) x, b = in(reg_byte) b, a = in(reg) a, p = in(reg) p"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            "movl %eax, (%rdi); movl {x:e}, 4(%rdi) # mov", "addb {b}, ({p})", "mov {a}, ({p})",
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let error = expand_str(Macro::AsmAuto, r#""mov $1, (%rdi)""#).unwrap_err();
+        assert_eq!(
+            error,
+            "`mov $1, (%rdi)` is ambiguous: none of its operands has a size; \
+             write `movb`, `movw`, `movl` or `movq`"
+        );
+        let error = expand_str(Macro::AsmAuto, r#""shl %cl, 8(%rsp)""#).unwrap_err();
+        assert!(
+            error.starts_with("`shl %cl, 8(%rsp)` is ambiguous"),
+            "{error}"
+        );
+        let error = expand_str(Macro::AsmAuto, r#""mov %eax, %rbx""#).unwrap_err();
+        assert_eq!(error, "`mov %eax, %rbx` mixes 32-bit and 64-bit operands");
+    }
+
+    #[test]
+    fn escapes_decorators() {
+        let expanded = expand_str(
+            Macro::GlobalAsm,
+            r#""vpaddd (%rax){1to16}, %zmm1, %zmm2{%k1}{z}", "vaddps {rn-sae}, %zmm0, %zmm1, %zmm2""#,
+        )
+        .unwrap();
+        let expected = quote! {
+            "vpaddd (%rax){{1to16}}, %zmm1, %zmm2{{%k1}}{{z}}", "vaddps {{rn-sae}}, %zmm0, %zmm1, %zmm2"
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let expanded = expand_str(
+            Macro::Asm,
+            r#""vmovdqa32 {x}, {z}{%k1}", x = in(zmm_reg) x, z = out(zmm_reg) z"#,
+        )
+        .unwrap();
+        assert!(
+            expanded.contains(r#""vmovdqa32 {x}, {z}{{%k1}}""#),
+            "{expanded}"
+        );
+
+        let expanded = expand_str(Macro::AsmAuto, r#""vmovdqa32 %zmm0, %zmm1{%k1}{z}""#).unwrap();
+        assert!(expanded.contains(r#"out ("zmm1") _"#), "{expanded}");
+    }
+
+    #[test]
+    fn converts_intel_templates() {
+        let expanded = expand_str(
+            Macro::Asm,
+            r#"intel_to_att!("mov eax, dword ptr [{p} + {off}]", "add eax, {n}", "call {f}"),
+               "nop", p = in(reg) p, off = const 4, n = const 1, f = in(reg) f"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            "movl {off}({p}), %eax\nadd ${n}, %eax\ncall *{f}", "nop",
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let error =
+            expand_str(Macro::Asm, r#"intel_to_att!("nop", "mov eax, [rax*3]")"#).unwrap_err();
+        assert_eq!(
+            error,
+            "invalid scale `3` in `[rax*3]`; expected 1, 2, 4 or 8"
+        );
+    }
+
+    #[test]
+    fn converts_to_intel() {
+        let expanded = expand_str(
+            Macro::AsmAsIntel,
+            r#""movl ({p},{i},4), %eax; addl ${n}, %eax", "1:", "call *{f}",
+               p = in(reg) p, i = in(reg) i, n = const 1, f = in(reg) f,
+               options(att_syntax, nomem)"#,
+        )
+        .unwrap();
+        let expected = quote! {
+            ::core::arch::asm!(
+                "mov eax, dword ptr [{p} + {i}*4]\nadd eax, {n}", "1:", "call {f}",
+                p = in(reg) p, i = in(reg) i, n = const 1, f = in(reg) f,
+                options(nomem)
+            )
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let error = expand_str(Macro::AsmAsIntel, r#"concat!("nop")"#).unwrap_err();
+        assert_eq!(
+            error,
+            "`asm_att_as_intel!` needs string literal templates to convert them to Intel syntax"
+        );
+    }
+
+    #[test]
+    fn lowers_gcc_statements() {
+        let lower = |input: &str| {
+            crate::gcc::gcc_asm_att(input.parse().unwrap())
+                .map(|tokens| tokens.to_string())
+                .map_err(|e| e.to_string())
+        };
+        let expanded =
+            lower(r#""movl %1, %0\n\t" "addl $1, %0" : "=r" (out) : "m" (*p) : "cc", "memory""#)
+                .unwrap();
+        let expected = quote! {
+            ::core::arch::asm!(
+                "movl ({1}), {0:e}", "addl $1, {0:e}",
+                lateout(reg) out, in(reg) &raw const *p,
+                options(att_syntax)
+            )
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+
+        let expanded =
+            lower(r#""rdtsc" : "=a" (lo), "=d" (hi); else { lo = 0; hi = 0; }"#).unwrap();
+        let expected = quote! {
+            ::core::arch::asm!(
+                "rdtsc", lateout("eax") lo, lateout("edx") hi,
+                options(att_syntax, nomem)
+            )
+        };
+        assert!(expanded.contains(&expected.to_string()), "{expanded}");
+        assert!(expanded.contains("lo = 0"), "{expanded}");
+
+        let error = lower(r#""incl %0" : "r" (x)"#).unwrap_err();
+        assert_eq!(error, "output constraint `r` must start with `=` or `+`");
+    }
+}
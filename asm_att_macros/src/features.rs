@@ -0,0 +1,85 @@
+//! The CPU features the instructions of a template need, as listed by
+//! `required_features!` and checked against the target features the crate
+//! is compiled with.
+//!
+//! A function with `#[target_feature(enable = "...")]` does not change the
+//! `cfg(target_feature)` of the crate, so that a missing feature is only a
+//! warning, the `unused_must_use` lint of a call to a function of its own.
+//! Templates in such functions list the features they enable with a
+//! `target_feature(enable = "...")` argument instead.
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::LitStr;
+
+use asm_att_core::isa;
+use asm_att_core::syntax::{Instruction, Location, Template};
+
+use crate::expand::prepare;
+
+/// The features the instructions of the template need, in the order they
+/// first appear, with the first instruction to need each one.
+fn required(template: &Template) -> Vec<(&'static str, Location, &Instruction)> {
+    let mut required: Vec<(&'static str, Location, &Instruction)> = Vec::new();
+    for (location, instruction) in template.instructions() {
+        for feature in isa::features(instruction) {
+            if !required.iter().any(|(known, ..)| *known == feature) {
+                required.push((feature, location, instruction));
+            }
+        }
+    }
+    required
+}
+
+/// A warning for every feature the template needs besides the `enabled`
+/// ones and those they imply, to be compiled only when the feature is not
+/// enabled.
+pub(crate) fn check(
+    template: &Template,
+    literals: &[&LitStr],
+    enabled: &[String],
+) -> Vec<(&'static str, TokenStream)> {
+    required(template)
+        .into_iter()
+        .filter(|(feature, ..)| !enabled.iter().any(|enabled| isa::implies(enabled, feature)))
+        .map(|(feature, location, instruction)| {
+            let note = format!(
+                "`{}` needs the `{feature}` target feature, which is not enabled; compile with \
+                 `-C target-feature=+{feature}`, or use it in a function with \
+                 `#[target_feature(enable = \"{feature}\")]` and pass \
+                 `target_feature(enable = \"{feature}\")` to the macro",
+                instruction.mnemonic
+            );
+            let warning = quote_spanned! {literals[location.template].span()=>
+                {
+                    #[must_use = #note]
+                    struct MissingTargetFeature;
+                    fn missing_target_feature() -> MissingTargetFeature {
+                        MissingTargetFeature
+                    }
+                    missing_target_feature();
+                }
+            };
+            (feature, warning)
+        })
+        .collect()
+}
+
+/// Expands `required_features!` to the features its template needs, as a
+/// `&'static [&'static str]`.
+pub(crate) fn required_features(input: TokenStream) -> syn::Result<TokenStream> {
+    let (args, template, _) = prepare(input)?;
+    let Some(template) = template else {
+        return Err(syn::Error::new_spanned(
+            &args.templates[0],
+            "`required_features!` needs string literal templates to find their instructions",
+        ));
+    };
+    let features = required(&template).into_iter().map(|(feature, ..)| feature);
+    Ok(quote! {
+        {
+            const FEATURES: &[&str] = &[#(#features),*];
+            FEATURES
+        }
+    })
+}
@@ -0,0 +1,207 @@
+//! `gcc_asm_att!`: GCC extended `asm` statements lowered to `asm_att!`.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Block, Expr, Ident, LitStr, Token, bracketed, parenthesized};
+
+use asm_att_core::convert::{AsmOperand, GccAsm, GccError, GccOperand, GccPart, RegSpec, Value};
+
+use crate::expand::{Macro, expand};
+
+/// The input of `gcc_asm_att!`: `template : outputs : inputs : clobbers`,
+/// optionally followed by `; else { ... }`.
+struct GccArgs {
+    templates: Vec<LitStr>,
+    /// Whether any `:` was given.
+    extended: bool,
+    outputs: Vec<(LitStr, GccOperand<Expr>)>,
+    inputs: Vec<(LitStr, GccOperand<Expr>)>,
+    clobbers: Vec<LitStr>,
+    fallback: Option<Block>,
+}
+
+impl Parse for GccArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = GccArgs {
+            templates: Vec::new(),
+            extended: false,
+            outputs: Vec::new(),
+            inputs: Vec::new(),
+            clobbers: Vec::new(),
+            fallback: None,
+        };
+        while input.peek(LitStr) {
+            args.templates.push(input.parse()?);
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        if args.templates.is_empty() {
+            return Err(input.error("expected a template string"));
+        }
+
+        let mut section = 0;
+        loop {
+            if input.peek(Token![::]) {
+                input.parse::<Token![::]>()?;
+                section += 2;
+            } else if input.peek(Token![:]) {
+                input.parse::<Token![:]>()?;
+                section += 1;
+            } else {
+                break;
+            }
+            args.extended = true;
+            while !(input.is_empty() || input.peek(Token![:]) || input.peek(Token![;])) {
+                match section {
+                    1 => args.outputs.push(parse_operand(input)?),
+                    2 => args.inputs.push(parse_operand(input)?),
+                    3 => args.clobbers.push(input.parse()?),
+                    _ => return Err(input.error("expected at most three `:`")),
+                }
+                if !input.peek(Token![,]) {
+                    break;
+                }
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+            input.parse::<Token![else]>()?;
+            args.fallback = Some(input.parse()?);
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected `:`, `,` or `; else { ... }`"));
+        }
+        Ok(args)
+    }
+}
+
+/// Parses `[name] "constraint" (expr)`, keeping the constraint literal for
+/// error messages.
+fn parse_operand(input: ParseStream) -> syn::Result<(LitStr, GccOperand<Expr>)> {
+    let name = if input.peek(syn::token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        Some(content.parse::<Ident>()?.to_string())
+    } else {
+        None
+    };
+    let constraint: LitStr = input.parse()?;
+    let content;
+    parenthesized!(content in input);
+    let expr = content.parse()?;
+    let operand = GccOperand {
+        name,
+        constraint: constraint.value(),
+        expr,
+    };
+    Ok((constraint, operand))
+}
+
+/// Expands `gcc_asm_att!` to the `asm_att!` invocation it lowers to.
+pub(crate) fn gcc_asm_att(input: TokenStream) -> syn::Result<TokenStream> {
+    let args: GccArgs = syn::parse2(input)?;
+    let span = args.templates[0].span();
+    let error = |error: GccError| {
+        let span = match error.part() {
+            GccPart::Statement | GccPart::Template => span,
+            GccPart::Output(index) => args.outputs[index].0.span(),
+            GccPart::Input(index) => args.inputs[index].0.span(),
+            GccPart::Clobber(index) => args.clobbers[index].span(),
+        };
+        syn::Error::new(span, error)
+    };
+    let statement = GccAsm {
+        template: args.templates.iter().map(LitStr::value).collect(),
+        basic: !args.extended,
+        outputs: args
+            .outputs
+            .iter()
+            .map(|(_, operand)| operand.clone())
+            .collect(),
+        inputs: args
+            .inputs
+            .iter()
+            .map(|(_, operand)| operand.clone())
+            .collect(),
+        clobbers: args.clobbers.iter().map(LitStr::value).collect(),
+    };
+    let lowered = statement.lower().map_err(error)?;
+
+    let mut arguments: Vec<TokenStream> = lowered
+        .templates
+        .iter()
+        .map(|template| {
+            let template = LitStr::new(template, span);
+            quote!(#template)
+        })
+        .collect();
+    if arguments.is_empty() {
+        arguments.push(quote!(""));
+    }
+    arguments.extend(
+        lowered
+            .operands
+            .into_iter()
+            .map(|operand| operand_tokens(operand, span)),
+    );
+    if !lowered.options.is_empty() {
+        let options = lowered
+            .options
+            .iter()
+            .map(|option| Ident::new(option, span));
+        arguments.push(quote!(options(#(#options),*)));
+    }
+    let fallback = args.fallback.map(|block| quote!(; else #block));
+    expand(Macro::Asm, quote!(#(#arguments),* #fallback))
+}
+
+fn operand_tokens(operand: AsmOperand<Expr>, span: Span) -> TokenStream {
+    let reg = |reg: RegSpec| match reg {
+        RegSpec::Class(class) => {
+            let class = Ident::new(class, span);
+            quote!(#class)
+        }
+        RegSpec::Explicit(register) => {
+            let register = LitStr::new(&register, span);
+            quote!(#register)
+        }
+    };
+    match operand {
+        AsmOperand::In(register, value) => {
+            let register = reg(register);
+            let value = match value {
+                Value::Expr(expr) => quote!(#expr),
+                Value::Address(expr) => quote!(&raw const #expr),
+                Value::AddressMut(expr) => quote!(&raw mut #expr),
+            };
+            quote!(in(#register) #value)
+        }
+        AsmOperand::Out {
+            reg: register,
+            late,
+            place,
+        } => {
+            let register = reg(register);
+            let place = place.map_or_else(|| quote!(_), |place| quote!(#place));
+            if late {
+                quote!(lateout(#register) #place)
+            } else {
+                quote!(out(#register) #place)
+            }
+        }
+        AsmOperand::InOut {
+            reg: register,
+            value,
+            place,
+        } => {
+            let register = reg(register);
+            let place = place.map(|place| quote!(=> #place));
+            quote!(inout(#register) #value #place)
+        }
+        AsmOperand::Const(expr) => quote!(const #expr),
+    }
+}
@@ -0,0 +1,198 @@
+//! `{name:imm}` placeholders, rewritten to the immediate `${name}`, and the
+//! checks that their `const` operands fit the immediate of their instruction.
+//!
+//! The value of a `const` operand is only known to the compiler, so that each
+//! check is an inline `const` block placed before the `asm!` invocation.
+
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+use syn::{Expr, LitStr};
+
+use asm_att_core::syntax::{Instruction, Operand, Size, Statement, Template};
+
+use crate::args::{AsmArgs, OperandKind};
+use crate::convert::kind;
+use crate::expand::located_error;
+use crate::sizes::is_count;
+
+/// Instructions taking an 8-bit immediate whatever the size of their other
+/// operands.
+const IMM8: &[&str] = &[
+    "bt", "btc", "btr", "bts", "in", "inb", "inw", "inl", "int", "out", "outb", "outw", "outl",
+];
+
+/// Rewrites the `{name:imm}` placeholders of the template literals to
+/// `${name}`, and returns the placeholders that were rewritten, such as
+/// `{name}`.
+pub(crate) fn expand(args: &mut AsmArgs) -> Vec<String> {
+    let Some(literals) = args.template_literals() else {
+        return Vec::new();
+    };
+    let mut placeholders = Vec::new();
+    let mut rewritten = Vec::new();
+    for (index, literal) in literals.iter().enumerate() {
+        let value = literal.value();
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find('{') {
+            if rest[start..].starts_with("{{") {
+                result.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let Some(length) = rest[start..].find('}') else {
+                break;
+            };
+            let inner = &rest[start + 1..start + length];
+            result.push_str(&rest[..start]);
+            match inner.strip_suffix(":imm") {
+                Some(arg) => {
+                    let placeholder = format!("{{{}}}", arg.trim());
+                    result.push('$');
+                    result.push_str(&placeholder);
+                    if !placeholders.contains(&placeholder) {
+                        placeholders.push(placeholder);
+                    }
+                }
+                None => result.push_str(&rest[start..=start + length]),
+            }
+            rest = &rest[start + length + 1..];
+        }
+        result.push_str(rest);
+        if result != value {
+            rewritten.push((index, result));
+        }
+    }
+    for (index, value) in rewritten {
+        args.replace_template(index, &value);
+    }
+    placeholders
+}
+
+/// Checks that the `{name:imm}` placeholders refer to `const` or `sym`
+/// operands, and returns the checks that the values of the `const` ones fit
+/// the immediate of their instruction.
+pub(crate) fn check(
+    template: &Template,
+    literals: &[&LitStr],
+    args: &AsmArgs,
+    placeholders: &[String],
+) -> syn::Result<Vec<TokenStream>> {
+    let mut checks = Vec::new();
+    if placeholders.is_empty() {
+        return Ok(checks);
+    }
+    for (location, statement) in &template.statements {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        for (index, operand) in instruction.operands.iter().enumerate() {
+            let Operand::Immediate(text) = operand else {
+                continue;
+            };
+            if !placeholders.contains(text) {
+                continue;
+            }
+            let Ok(Operand::Placeholder(placeholder)) = text.parse() else {
+                continue;
+            };
+            let class = match kind(&placeholder, args) {
+                Some(OperandKind::Const(expr)) => {
+                    if let Some(check) = range_check(instruction, index, text, expr) {
+                        checks.push(check);
+                    }
+                    continue;
+                }
+                Some(OperandKind::Sym(_)) | None => continue,
+                Some(OperandKind::Reg { .. }) => "a register",
+                Some(OperandKind::Label(_)) => "a `label`",
+            };
+            let message = format!(
+                "`{}` refers to {class} operand, but immediates take `const` or `sym` operands",
+                imm(text)
+            );
+            return Err(located_error(literals, *location, message));
+        }
+    }
+    Ok(checks)
+}
+
+/// An inline `const` block checking that the value of `expr` fits the
+/// immediate at `index` of an instruction.
+fn range_check(
+    instruction: &Instruction,
+    index: usize,
+    placeholder: &str,
+    expr: &Expr,
+) -> Option<TokenStream> {
+    let (min, max, encoding) = encoding(instruction, index)?;
+    let message = format!(
+        "`{}` of `{}` does not fit in {encoding}, from {min} to {max}",
+        imm(placeholder),
+        instruction.mnemonic
+    );
+    Some(quote_spanned! {expr.span()=>
+        const {
+            #[allow(clippy::unnecessary_cast)]
+            let value = (#expr) as i128;
+            ::asm_att::__private::immediate(value, #min, #max, #message)
+        }
+    })
+}
+
+/// `{name:imm}` for the placeholder `{name}`.
+fn imm(placeholder: &str) -> String {
+    let arg = &placeholder[1..placeholder.len() - 1];
+    format!("{{{arg}:imm}}")
+}
+
+/// The range of the immediate operand at `index` of an instruction, and the
+/// name of its encoding, if the instruction gives it.
+fn encoding(instruction: &Instruction, index: usize) -> Option<(i128, i128, &'static str)> {
+    const BYTE: (i128, i128, &str) = (-0x80, 0xff, "an imm8");
+    let mnemonic = instruction.mnemonic.to_ascii_lowercase();
+    let sizes = instruction.operand_sizes();
+    let stem = match sizes {
+        Some(_) => &mnemonic[..mnemonic.len() - 1],
+        None => &mnemonic,
+    };
+    let count = instruction.operands.len();
+    if IMM8.contains(&stem)
+        || IMM8.contains(&mnemonic.as_str())
+        || is_count(stem, index, count)
+        || (count >= 3 && index == 0 && stem != "imul")
+        || (stem == "enter" && index == 1)
+    {
+        return Some(BYTE);
+    }
+    if matches!(stem, "ret" | "lret" | "enter") {
+        return Some((0, 0xffff, "an imm16"));
+    }
+    let size = sizes.map(|(source, _)| source).or_else(|| {
+        instruction
+            .operands
+            .iter()
+            .find_map(|operand| match operand {
+                Operand::Register(register) => register.size(),
+                _ => None,
+            })
+    })?;
+    Some(match size {
+        Size::Byte => BYTE,
+        Size::Word => (-0x8000, 0xffff, "an imm16"),
+        Size::Long => (-0x8000_0000, 0xffff_ffff, "an imm32"),
+        // Only `mov` to a register takes a 64-bit immediate; the others
+        // sign-extend a 32-bit one.
+        Size::Quad
+            if matches!(stem, "mov" | "movabs")
+                && matches!(
+                    instruction.operands.get(1),
+                    Some(Operand::Register(_) | Operand::Placeholder(_))
+                ) =>
+        {
+            (-0x8000_0000_0000_0000, 0xffff_ffff_ffff_ffff, "an imm64")
+        }
+        Size::Quad => (-0x8000_0000, 0x7fff_ffff, "a sign-extended imm32"),
+    })
+}
@@ -0,0 +1,145 @@
+//! Named local labels: `{label:name}` in a template, rewritten to a numeric
+//! local label that no other label of the template uses.
+//!
+//! Named labels such as `retry:` are defined twice when the compiler
+//! duplicates inline assembly, such as by inlining it, while numeric local
+//! labels can be defined any number of times. `{label:retry}:` defines one,
+//! and every other `{label:retry}` refers to it, with the `b` or `f` suffix
+//! of the direction it is in.
+
+use std::cmp::Ordering;
+
+use crate::args::AsmArgs;
+
+/// An occurrence of `{label:name}` in a template.
+struct Occurrence {
+    template: usize,
+    start: usize,
+    end: usize,
+    name: String,
+    definition: bool,
+}
+
+/// Rewrites the `{label:name}` labels of the template literals, unless an
+/// operand is named `label`.
+pub(crate) fn expand(args: &mut AsmArgs) -> syn::Result<()> {
+    if args
+        .operands
+        .iter()
+        .any(|operand| operand.name.as_ref().is_some_and(|name| name == "label"))
+    {
+        return Ok(());
+    }
+    let Some(literals) = args.template_literals() else {
+        return Ok(());
+    };
+    let values: Vec<String> = literals.iter().map(|literal| literal.value()).collect();
+    let occurrences = find(&values);
+    if occurrences.is_empty() {
+        return Ok(());
+    }
+
+    // Number the labels in the order they are defined.
+    let mut used = numeric_labels(&values);
+    let mut labels: Vec<(&str, usize, u32)> = Vec::new();
+    for (index, occurrence) in occurrences.iter().enumerate() {
+        if !occurrence.definition {
+            continue;
+        }
+        if labels.iter().any(|(name, ..)| *name == occurrence.name) {
+            let message = format!("`{{label:{}}}` is defined twice", occurrence.name);
+            return Err(syn::Error::new(
+                literals[occurrence.template].span(),
+                message,
+            ));
+        }
+        let number = (2..)
+            .find(|number: &u32| {
+                let digits = number.to_string();
+                !used.contains(number) && !digits.bytes().all(|digit| matches!(digit, b'0' | b'1'))
+            })
+            .expect("labels are finite");
+        used.push(number);
+        labels.push((&occurrence.name, index, number));
+    }
+
+    let mut rewritten = values.clone();
+    for (index, occurrence) in occurrences.iter().enumerate().rev() {
+        let Some(&(_, definition, number)) =
+            labels.iter().find(|(name, ..)| *name == occurrence.name)
+        else {
+            let message = format!(
+                "`{{label:{0}}}` is never defined; define it with `{{label:{0}}}:`",
+                occurrence.name
+            );
+            return Err(syn::Error::new(
+                literals[occurrence.template].span(),
+                message,
+            ));
+        };
+        let label = match index.cmp(&definition) {
+            Ordering::Equal => number.to_string(),
+            Ordering::Less => format!("{number}f"),
+            Ordering::Greater => format!("{number}b"),
+        };
+        rewritten[occurrence.template].replace_range(occurrence.start..occurrence.end, &label);
+    }
+    for (index, (value, original)) in rewritten.iter().zip(&values).enumerate() {
+        if value != original {
+            args.replace_template(index, value);
+        }
+    }
+    Ok(())
+}
+
+/// The `{label:name}` occurrences of the templates, in order.
+fn find(values: &[String]) -> Vec<Occurrence> {
+    let mut occurrences = Vec::new();
+    for (template, value) in values.iter().enumerate() {
+        let bytes = value.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i..].starts_with(b"{{") || bytes[i..].starts_with(b"}}") {
+                i += 2;
+                continue;
+            }
+            if bytes[i] != b'{' {
+                i += 1;
+                continue;
+            }
+            let Some(length) = value[i..].find('}') else {
+                break;
+            };
+            let end = i + length + 1;
+            if let Some(name) = value[i + 1..end - 1].strip_prefix("label:") {
+                let name = name.trim();
+                occurrences.push(Occurrence {
+                    template,
+                    start: i,
+                    end,
+                    name: name.to_string(),
+                    definition: value[end..]
+                        .trim_start_matches([' ', '\t'])
+                        .starts_with(':'),
+                });
+            }
+            i = end;
+        }
+    }
+    occurrences
+}
+
+/// The numbers of the numeric local labels written in the templates.
+fn numeric_labels(values: &[String]) -> Vec<u32> {
+    let mut numbers = Vec::new();
+    for value in values {
+        for (index, _) in value.match_indices(':') {
+            let before = &value[..index];
+            let digits = before.len() - before.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+            if let Ok(number) = before[before.len() - digits..].parse() {
+                numbers.push(number);
+            }
+        }
+    }
+    numbers
+}
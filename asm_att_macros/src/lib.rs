@@ -0,0 +1,248 @@
+#![deny(missing_docs)]
+
+//!
+//! Procedural backend of [`asm_att`](https://docs.rs/asm_att), enabled by its
+//! `parser` feature.
+//!
+//! Every template string is tokenized and parsed into labels, directives and
+//! instructions before the invocation is forwarded to `core::arch`, so that
+//! mistakes are reported on the template line that contains them instead of
+//! by the assembler. Registers that do not exist, such as `%rxa` or `%r8l`,
+//! are reported along with the closest register that does, and prefixes on
+//! instructions they do not apply to, such as `lock movq` or `repe addq`,
+//! are rejected.
+
+mod args;
+mod clobbers;
+mod convert;
+mod expand;
+mod features;
+mod gcc;
+mod immediates;
+mod labels;
+mod memory;
+mod options;
+mod placeholders;
+mod registers;
+mod sizes;
+mod suffixes;
+mod writes;
+
+use proc_macro::TokenStream;
+
+use crate::expand::{Macro, expand};
+
+fn run(mac: Macro, input: TokenStream) -> TokenStream {
+    expand(mac, input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// See [`core::arch::asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any.
+///
+/// AT&T syntax only exists on x86 and x86_64. Code that must also build for
+/// other targets can end the arguments with `; else { ... }`: the block is
+/// used instead of the assembly on those targets, and ignored on x86.
+///
+/// `options(nostack)` is rejected if the template pushes, pops or calls.
+///
+/// `{label:name}:` defines a named local label, and every other
+/// `{label:name}` jumps to it. Each becomes a numeric local label, such as
+/// `2:` and `2b`, that no other label of the template uses, so that the
+/// template stays valid when the compiler duplicates it, such as by inlining
+/// it, which named labels such as `name:` do not.
+///
+/// `mem!(disp, base, index, scale)` stands for the memory reference
+/// `disp(base,index,scale)`, such as `mem!(8, {p}, {i}, 4)` for
+/// `8({p},{i},4)`. The index and scale are optional, the displacement and
+/// base may be empty, the displacement may start with a segment override
+/// such as `%fs:`, and a base of `%rip` gives a RIP-relative reference. The
+/// scale must be 1, 2, 4 or 8, and the base and index registers must have
+/// the same width.
+///
+/// `{name:imm}` is the immediate `${name}` of a `const` or `sym` operand.
+/// The value of a `const` operand must fit the immediate of its
+/// instruction, which the compiler checks: an 8-bit one for shifts, rotates
+/// and instructions such as `pshufd`, a sign-extended 32-bit one for the
+/// 64-bit instructions other than `movq` to a register, or else one of the
+/// size given by the suffix or register operands.
+///
+/// A placeholder in the destination of an instruction, such as `{2}` in
+/// `add {1}, {2}`, must refer to an `out`, `lateout`, `inout` or `inlateout`
+/// operand: writing an `in`, `const`, `sym` or `label` operand is an error.
+///
+/// The size suffix of an instruction such as `movl` must agree with the width
+/// of the general-purpose register operands its placeholders refer to: the
+/// width of a template modifier such as `{x:e}`, of the `reg_byte` class, or
+/// else of the Rust type of the operand. A placeholder without a modifier is
+/// still replaced with a 64-bit register on x86_64, and a 32-bit one on x86,
+/// so that the `b`, `w` and, on x86_64, `l` suffixes need one.
+///
+/// AVX-512 decorators such as `{%k1}`, `{z}`, `{1to16}` or `{rn-sae}` are
+/// escaped, so that they need not be written `{{%k1}}`. `{z}` and `{sae}`
+/// stay placeholders when an operand has that name.
+///
+/// Instructions that need a CPU feature the crate is not compiled with, such
+/// as `vpaddd %ymm0, %ymm1, %ymm2` without `avx2` or `pdep` without `bmi2`,
+/// get an `unused_must_use` warning naming the feature. `cfg(target_feature)`
+/// does not see `#[target_feature(enable = "...")]`, so that templates in
+/// functions with it pass the same `target_feature(enable = "...")` as an
+/// argument instead. See
+/// [`required_features!`](macro@required_features) for the features of a
+/// template.
+#[proc_macro]
+pub fn asm_att(input: TokenStream) -> TokenStream {
+    run(Macro::Asm, input)
+}
+
+/// [`asm_att!`](macro@asm_att) that works out size suffixes, clobbers and
+/// options itself.
+///
+/// Instructions such as `mov` or `add` written without a size suffix get one
+/// when the size is given by an explicit register such as `%eax`, a template
+/// modifier such as `{x:e}` or a `reg_byte` operand. Placeholders sized by
+/// the Rust type of their operand are left to the assembler. Instructions
+/// whose operands have no size at all, such as `inc (%rax)`, are an error.
+///
+/// Every register the template writes, either as a destination such as
+/// `%rsi` in `movq {src}, %rsi` or implicitly such as `%rcx` for a `rep`
+/// prefix, is added as an `out("reg") _` operand unless it already is an
+/// output. Templates that contain a `call` also get `clobber_abi("C")`, unless
+/// a `clobber_abi` is given.
+///
+/// Writing a register that is bound by `in("reg")` is an error: it must be
+/// `inout("reg") expr => _` instead. `%rsp`, `%rbp` and `%rbx` are reserved by
+/// the compiler and never added, so writing them is an error too unless the
+/// template pushes and pops them, or for `%rsp`, adds back what it subtracts.
+///
+/// `preserves_flags`, `nomem` or `readonly`, and `nostack` are added to the
+/// options when no instruction touches the flags, writes or reads memory, or
+/// uses the stack. Instructions that are not known to leave them alone are
+/// assumed to touch them, and templates with directives other than `.align`,
+/// `.p2align` or `.balign` get no options added.
+#[proc_macro]
+pub fn asm_att_auto(input: TokenStream) -> TokenStream {
+    run(Macro::AsmAuto, input)
+}
+
+/// [`asm_att!`](macro@asm_att) that expands to the equivalent Intel syntax
+/// template instead, without `options(att_syntax)`.
+///
+/// The template is checked as by `asm_att!` and then rewritten one template
+/// string at a time, so that the same snippet can be built in either syntax
+/// and the two can be compared:
+///
+/// ```ignore
+/// asm_att_as_intel!("movl ({p}), %eax", "addl ${n}, %eax", p = in(reg) p, n = const 1, out("eax") x);
+/// // core::arch::asm!("mov eax, dword ptr [{p}]", "add eax, {n}", p = in(reg) p, n = const 1, out("eax") x);
+/// ```
+#[proc_macro]
+pub fn asm_att_as_intel(input: TokenStream) -> TokenStream {
+    run(Macro::AsmAsIntel, input)
+}
+
+/// Lowers a GCC extended `asm` statement written in AT&T syntax to
+/// [`asm_att!`](macro@asm_att), so that C code can be ported as it is:
+///
+/// ```ignore
+/// gcc_asm_att!(
+///     "movl %1, %0\n\t"
+///     "addl $1, %0"
+///     : "=r" (out)
+///     : "r" (value)
+///     : "cc"
+/// );
+/// // asm_att!("movl {1:e}, {0:e}", "addl $1, {0:e}", lateout(reg) out, in(reg) value, options(nomem));
+/// ```
+///
+/// `%0` and `%[name]` become placeholders and `%%eax` becomes `%eax`.
+/// Placeholders of general-purpose registers get the modifier matching the
+/// suffix of their instruction, as GCC sizes them by the type of the operand.
+/// `r`
+/// constraints become `reg` operands, `a`, `c`, `d`, `S` and `D` explicit
+/// registers, `i` constants and `m` the address of the operand in a `reg`;
+/// `=` outputs are `lateout` unless early-clobbered with `&`, `+` outputs are
+/// `inout`, and inputs matching an output such as `"0" (x)` are merged into
+/// it as `inout(...) x => out`. Register clobbers become `out("...") _`.
+///
+/// The statement gets `nomem`, or `readonly` if it only reads memory
+/// operands, unless it clobbers `"memory"`. `asm!` always clobbers the
+/// flags, so that `"cc"` is dropped. The arguments may end with
+/// `; else { ... }` as for `asm_att!`.
+#[proc_macro]
+pub fn gcc_asm_att(input: TokenStream) -> TokenStream {
+    gcc::gcc_asm_att(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// See [`core::arch::global_asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any.
+///
+/// AVX-512 decorators are escaped as in [`asm_att!`](macro@asm_att).
+#[proc_macro]
+pub fn global_asm_att(input: TokenStream) -> TokenStream {
+    run(Macro::GlobalAsm, input)
+}
+
+/// Rewrites template strings written in Intel syntax into one AT&T template
+/// string, so that Intel code can be pasted into
+/// [`asm_att!`](macro@asm_att):
+///
+/// ```ignore
+/// asm_att!(
+///     intel_to_att!("mov eax, dword ptr [{p} + 4]", "add eax, {n}"),
+///     p = in(reg) pointer,
+///     n = const 1,
+///     out("eax") value,
+/// );
+/// ```
+///
+/// Operands are reversed, registers and immediates get their `%` and `$`
+/// prefixes, `[base + index*scale + disp]` becomes `disp(base,index,scale)`
+/// and size keywords such as `dword ptr` become mnemonic suffixes.
+///
+/// Placeholders are taken for registers, except when `intel_to_att!` is a
+/// template of `asm_att!` and friends, which know their operands: there the
+/// placeholders of `const` operands become immediates, those of `const` and
+/// `sym` operands in memory references become displacements, and register
+/// operands that are called or jumped through become indirect branches.
+/// `{}` placeholders are always taken for registers.
+#[proc_macro]
+pub fn intel_to_att(input: TokenStream) -> TokenStream {
+    convert::intel_to_att(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The CPU features the instructions of a template need, as a
+/// `&'static [&'static str]` of their `#[target_feature]` names in the order
+/// they first appear:
+///
+/// ```ignore
+/// const FEATURES: &[&str] = required_features!("vpaddd %ymm0, %ymm1, %ymm2", "pdep %rax, %rbx, %rcx");
+/// assert_eq!(FEATURES, ["avx2", "bmi2"]);
+/// ```
+///
+/// The arguments are those of [`asm_att!`](macro@asm_att), whose operands
+/// may be given but are not needed. Instructions that are not in
+/// `asm_att::isa` need no feature.
+#[proc_macro]
+pub fn required_features(input: TokenStream) -> TokenStream {
+    features::required_features(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// See [`core::arch::naked_asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any.
+///
+/// AVX-512 decorators are escaped as in [`asm_att!`](macro@asm_att).
+#[proc_macro]
+pub fn naked_asm_att(input: TokenStream) -> TokenStream {
+    run(Macro::NakedAsm, input)
+}
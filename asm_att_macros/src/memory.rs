@@ -0,0 +1,231 @@
+//! `mem!(displacement, base, index, scale)` in a template, rewritten to the
+//! AT&T memory reference `displacement(base,index,scale)` once it is checked.
+//!
+//! The displacement may start with a segment override such as `%fs:`, and
+//! may be empty, as may the base. A base of `%rip` makes the reference
+//! RIP-relative, such as `{sym}(%rip)`.
+//!
+//! Placeholders without a template modifier take the address width of the
+//! target, so that the widths of the base and index are only compared when
+//! both are registers or modified placeholders such as `{i:e}`.
+
+use syn::LitStr;
+
+use asm_att_core::syntax::{Location, MemoryOperand, Operand, Placeholder, Register, Size};
+
+use crate::args::{AsmArgs, OperandKind, RegSpec};
+use crate::convert::kind;
+use crate::expand::located_error;
+use crate::sizes::{Width, placeholder_width};
+
+/// Segment registers, which may prefix the displacement.
+const SEGMENTS: &[&str] = &["cs", "ds", "es", "fs", "gs", "ss"];
+
+/// Rewrites the `mem!(...)` of the template literals.
+pub(crate) fn expand(args: &mut AsmArgs) -> syn::Result<()> {
+    let Some(literals) = args.template_literals() else {
+        return Ok(());
+    };
+    let rewritten = rewrite(&literals, args)?;
+    for (index, value) in rewritten {
+        args.replace_template(index, &value);
+    }
+    Ok(())
+}
+
+/// The new values of the template literals that use `mem!`, by index.
+fn rewrite(literals: &[&LitStr], args: &AsmArgs) -> syn::Result<Vec<(usize, String)>> {
+    let mut rewritten = Vec::new();
+    for (index, literal) in literals.iter().enumerate() {
+        let value = literal.value();
+        if !value.contains("mem!(") {
+            continue;
+        }
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find("mem!(") {
+            result.push_str(&rest[..start]);
+            let location = Location {
+                template: index,
+                line: value[..value.len() - rest.len() + start]
+                    .matches('\n')
+                    .count(),
+            };
+            let error = |message: String| located_error(literals, location, message);
+            let body = &rest[start + "mem!(".len()..];
+            let Some(end) = closing_paren(body) else {
+                return Err(error("unclosed `mem!(`".to_string()));
+            };
+            let memory = memory(&body[..end], args).map_err(error)?;
+            result.push_str(&memory.to_string());
+            rest = &body[end + 1..];
+        }
+        result.push_str(rest);
+        rewritten.push((index, result));
+    }
+    Ok(rewritten)
+}
+
+/// The offset of the `)` closing a `mem!(`, in the text that follows it.
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (offset, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(offset),
+            ')' => depth -= 1,
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the arguments of a `mem!` on the commas outside of parentheses.
+fn split_args(text: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let (mut depth, mut start) = (0_usize, 0);
+    for (offset, c) in text.char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                args.push(text[start..offset].trim());
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(text[start..].trim());
+    args
+}
+
+/// The memory reference a `mem!` stands for, once checked.
+fn memory(text: &str, args: &AsmArgs) -> Result<MemoryOperand, String> {
+    let parts = split_args(text);
+    if !(2..=4).contains(&parts.len()) {
+        return Err(format!(
+            "`mem!({text})` takes a displacement, a base, an index and a scale, \
+             such as `mem!(8, {{base}}, {{index}}, 4)`"
+        ));
+    }
+    let mut memory = MemoryOperand::default();
+
+    let mut displacement = parts[0];
+    if let Some((segment, rest)) = displacement.split_once(':')
+        && segment.starts_with('%')
+    {
+        let name = segment.trim().trim_start_matches('%');
+        if !SEGMENTS.contains(&name.to_ascii_lowercase().as_str()) {
+            return Err(format!("`{}` is not a segment register", segment.trim()));
+        }
+        memory.segment = Some(Box::new(Operand::Register(Register::new(name))));
+        displacement = rest.trim();
+    }
+    memory.displacement = displacement.to_string();
+
+    let base = register_arg(parts[1], args)?;
+    let index = match parts.get(2) {
+        Some(part) => register_arg(part, args)?,
+        None => None,
+    };
+    if let Some(scale) = parts.get(3) {
+        match scale.parse() {
+            Ok(scale @ (1 | 2 | 4 | 8)) => memory.scale = Some(scale),
+            _ => {
+                return Err(format!(
+                    "the scale of `mem!` must be 1, 2, 4 or 8, not `{scale}`"
+                ));
+            }
+        }
+        if index.is_none() {
+            return Err(format!("`mem!({text})` has a scale but no index"));
+        }
+    }
+
+    let rip = base
+        .as_ref()
+        .is_some_and(|(operand, _)| is_register(operand, &["rip", "eip"]));
+    if let Some((operand, _)) = &index {
+        if rip {
+            return Err(format!(
+                "`mem!({text})` is relative to `{}` and so takes no index",
+                parts[1]
+            ));
+        }
+        if is_register(operand, &["rsp", "esp", "rip", "eip"]) {
+            return Err(format!("`{operand}` cannot be an index"));
+        }
+    }
+    if let (Some((base, Some(base_size))), Some((index, Some(index_size)))) = (&base, &index)
+        && base_size != index_size
+        && !rip
+    {
+        return Err(format!(
+            "the base `{base}` is {}-bit but the index `{index}` is {}-bit; \
+             both must have the same width",
+            base_size.bits(),
+            index_size.bits()
+        ));
+    }
+
+    memory.base = base.map(|(operand, _)| Box::new(operand));
+    memory.index = index.map(|(operand, _)| Box::new(operand));
+    Ok(memory)
+}
+
+/// The register or placeholder of a base or index, with its width if known,
+/// or `None` if it is empty.
+fn register_arg(text: &str, args: &AsmArgs) -> Result<Option<(Operand, Option<Size>)>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let operand: Operand = text.parse().map_err(|_| not_a_register(text))?;
+    let size = match &operand {
+        Operand::Register(register) => register.size(),
+        Operand::Placeholder(placeholder) => placeholder_size(placeholder, args)?,
+        _ => return Err(not_a_register(text)),
+    };
+    if let Some(size @ (Size::Byte | Size::Word)) = size {
+        return Err(format!(
+            "`{text}` is {}-bit, but addresses take 64-bit or 32-bit registers",
+            size.bits()
+        ));
+    }
+    Ok(Some((operand, size)))
+}
+
+/// The width of the register a placeholder stands for, or an error if it
+/// does not stand for a register.
+fn placeholder_size(placeholder: &Placeholder, args: &AsmArgs) -> Result<Option<Size>, String> {
+    let operand = kind(placeholder, args);
+    let class = match operand {
+        Some(OperandKind::Const(_)) => "const",
+        Some(OperandKind::Sym(_)) => "sym",
+        Some(OperandKind::Label(_)) => "label",
+        Some(OperandKind::Reg {
+            reg: RegSpec::Explicit(register),
+            ..
+        }) if placeholder.modifier.is_none() => {
+            return Ok(Register::new(register.value()).size());
+        }
+        _ => {
+            return Ok(match placeholder_width(placeholder, operand) {
+                Width::Known(size) => Some(size),
+                Width::Type | Width::Unknown => None,
+            });
+        }
+    };
+    Err(format!(
+        "`{placeholder}` is a `{class}` operand, but `mem!` takes registers"
+    ))
+}
+
+fn is_register(operand: &Operand, names: &[&str]) -> bool {
+    matches!(operand, Operand::Register(register)
+        if names.contains(&register.name().to_ascii_lowercase().as_str()))
+}
+
+fn not_a_register(text: &str) -> String {
+    format!("`{text}` is not a register or a placeholder")
+}
@@ -0,0 +1,113 @@
+//! Options inference for `asm_att_auto!`, and checks of the options given to
+//! `asm_att!`.
+
+use proc_macro2::Span;
+use syn::Ident;
+
+use asm_att_core::isa::{self, Memory};
+use asm_att_core::syntax::{Access, Instruction, Operand, Statement, Template};
+
+use crate::args::{AsmArgs, OperandKind};
+use crate::placeholders;
+
+/// Directives that only pad the code and can therefore be ignored.
+const ALIGNMENT: &[&str] = &[".align", ".p2align", ".balign"];
+
+/// Rejects options that the template provably breaks.
+pub(crate) fn check(template: &Template, options: &[Ident]) -> syn::Result<()> {
+    if let Some(nostack) = options.iter().find(|option| *option == "nostack")
+        && let Some((_, instruction)) = template
+            .instructions()
+            .find(|(_, instruction)| instruction.is_stack_instruction())
+    {
+        let message =
+            format!("`{instruction}` uses the stack, which `options(nostack)` promises not to");
+        return Err(syn::Error::new(nostack.span(), message));
+    }
+    Ok(())
+}
+
+/// Adds `preserves_flags`, `nomem` or `readonly`, and `nostack` to `options`
+/// when no instruction of the template touches the flags, writes or reads
+/// memory, or uses the stack.
+///
+/// Nothing is added if the template contains directives, which may emit
+/// arbitrary code, or instructions whose effects are not known.
+///
+/// `const` and `sym` operands that stand alone as an operand are addresses
+/// in AT&T syntax, and count as memory references.
+pub(crate) fn infer(template: &Template, args: &mut AsmArgs) {
+    let instructions = || template.instructions().map(|(_, instruction)| instruction);
+    let opaque = template.statements.iter().any(|(_, statement)| {
+        matches!(statement, Statement::Directive(directive)
+            if !ALIGNMENT.contains(&directive.name.to_ascii_lowercase().as_str()))
+    });
+    if opaque || !instructions().all(Instruction::has_known_effects) {
+        return;
+    }
+
+    let addresses = addresses(template, args);
+    let options = &mut args.options;
+    let has = |options: &[Ident], name: &str| options.iter().any(|option| option == name);
+    let add = |options: &mut Vec<Ident>, name: &str| {
+        if !has(options, name) {
+            options.push(Ident::new(name, Span::call_site()));
+        }
+    };
+
+    if !instructions().any(Instruction::modifies_flags) {
+        add(options, "preserves_flags");
+    }
+    let memory = instructions()
+        .map(Instruction::memory_access)
+        .chain(addresses)
+        .max()
+        .unwrap_or(Access::None);
+    let declared = has(options, "nomem") || has(options, "readonly");
+    match memory {
+        Access::None if !declared => add(options, "nomem"),
+        Access::Read if !declared => add(options, "readonly"),
+        _ => {}
+    }
+    if !instructions().any(Instruction::uses_stack) {
+        add(options, "nostack");
+    }
+}
+
+/// How the instructions of the template access the memory at the addresses
+/// of their `const` and `sym` operands, other than those of branches and of
+/// `lea`.
+fn addresses(template: &Template, args: &AsmArgs) -> Vec<Access> {
+    let resolved = placeholders::resolve(template, args);
+    let mut accesses = Vec::new();
+    for (statement_index, (_, statement)) in template.statements.iter().enumerate() {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let address_only =
+            isa::lookup(&instruction.mnemonic).is_some_and(|entry| entry.memory == Memory::Address);
+        if instruction.is_branch() || address_only {
+            continue;
+        }
+        let first_written = instruction.operands.len() - instruction.written_operands().len();
+        for (index, operand) in instruction.operands.iter().enumerate() {
+            let Operand::Placeholder(_) = operand.undecorated() else {
+                continue;
+            };
+            let address = resolved
+                .get(&(statement_index, index))
+                .and_then(|&operand_index| args.operands.get(operand_index))
+                .is_some_and(|operand| {
+                    matches!(operand.kind, OperandKind::Const(_) | OperandKind::Sym(_))
+                });
+            if address {
+                accesses.push(if index >= first_written {
+                    Access::Write
+                } else {
+                    Access::Read
+                });
+            }
+        }
+    }
+    accesses
+}
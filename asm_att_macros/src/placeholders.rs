@@ -0,0 +1,85 @@
+//! Resolution of template placeholders to the operands they refer to.
+
+use std::collections::HashMap;
+
+use asm_att_core::syntax::{Operand, PlaceholderArg, Statement, Template};
+
+use crate::args::AsmArgs;
+
+/// The index of the operand referred to by every placeholder that is a direct
+/// operand of an instruction, keyed by the index of the statement and the
+/// index of the operand inside of it.
+///
+/// `{}` placeholders refer to the operand after the one referred to by the
+/// previous `{}`, wherever that one was.
+pub(crate) fn resolve(template: &Template, args: &AsmArgs) -> HashMap<(usize, usize), usize> {
+    let mut resolved = HashMap::new();
+    let mut next = 0;
+    for (statement_index, (_, statement)) in template.statements.iter().enumerate() {
+        let instruction = match statement {
+            Statement::Instruction(instruction) => instruction,
+            Statement::Directive(directive) => {
+                next += count_next(&directive.args);
+                continue;
+            }
+            Statement::Label(_) => continue,
+        };
+        for (index, operand) in instruction.operands.iter().enumerate() {
+            let Operand::Placeholder(placeholder) = operand.undecorated() else {
+                next += count_nested(operand);
+                continue;
+            };
+            let operand_index = match &placeholder.arg {
+                PlaceholderArg::Next => {
+                    next += 1;
+                    Some(next - 1)
+                }
+                PlaceholderArg::Index(index) => Some(*index),
+                PlaceholderArg::Name(name) => args
+                    .operands
+                    .iter()
+                    .position(|operand| operand.name.as_ref().is_some_and(|n| n == name)),
+            };
+            if let Some(operand_index) = operand_index {
+                resolved.insert((statement_index, index), operand_index);
+            }
+        }
+    }
+    resolved
+}
+
+/// The number of `{}` placeholders in text kept verbatim by the parser.
+fn count_next(text: &str) -> usize {
+    let mut count = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            chars.next();
+        } else if matches!(chars.peek(), Some('}' | ':')) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// The number of `{}` placeholders inside an operand.
+fn count_nested(operand: &Operand) -> usize {
+    match operand {
+        Operand::Register(_) | Operand::Decorator(_) => 0,
+        Operand::Placeholder(placeholder) => usize::from(placeholder.arg == PlaceholderArg::Next),
+        Operand::Immediate(text) | Operand::Expression(text) => count_next(text),
+        Operand::Indirect(operand) | Operand::Decorated(operand, _) => count_nested(operand),
+        Operand::Memory(memory) => {
+            let nested = [&memory.segment, &memory.base, &memory.index];
+            count_next(&memory.displacement)
+                + nested
+                    .into_iter()
+                    .flatten()
+                    .map(|operand| count_nested(operand))
+                    .sum::<usize>()
+        }
+    }
+}
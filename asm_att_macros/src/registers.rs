@@ -0,0 +1,61 @@
+//! Checks that every register a template names exists, suggesting the
+//! closest existing name for the ones that do not.
+
+use syn::LitStr;
+
+use asm_att_core::syntax::{Decorator, Operand, Register, Statement, Template};
+
+use crate::expand::located_error;
+
+/// Checks the registers of every instruction operand, including the segment,
+/// base and index of memory references and the masks of AVX-512 decorators.
+pub(crate) fn check(template: &Template, literals: &[&LitStr]) -> syn::Result<()> {
+    for (location, statement) in &template.statements {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let mut registers = Vec::new();
+        for operand in &instruction.operands {
+            collect(operand, &mut registers);
+        }
+        if let Some(register) = registers.into_iter().find(|register| !register.is_known()) {
+            let message = match register.suggestion() {
+                Some(suggestion) => {
+                    format!("unknown register `{register}`; did you mean `{suggestion}`?")
+                }
+                None => format!("unknown register `{register}`"),
+            };
+            return Err(located_error(literals, *location, message));
+        }
+    }
+    Ok(())
+}
+
+fn collect<'a>(operand: &'a Operand, registers: &mut Vec<&'a Register>) {
+    match operand {
+        Operand::Register(register) | Operand::Decorator(Decorator::Mask(register)) => {
+            registers.push(register);
+        }
+        Operand::Memory(memory) => {
+            for operand in [&memory.segment, &memory.base, &memory.index]
+                .into_iter()
+                .flatten()
+            {
+                collect(operand, registers);
+            }
+        }
+        Operand::Indirect(operand) => collect(operand, registers),
+        Operand::Decorated(operand, decorators) => {
+            collect(operand, registers);
+            for decorator in decorators {
+                if let Decorator::Mask(register) = decorator {
+                    registers.push(register);
+                }
+            }
+        }
+        Operand::Placeholder(_)
+        | Operand::Immediate(_)
+        | Operand::Expression(_)
+        | Operand::Decorator(_) => {}
+    }
+}
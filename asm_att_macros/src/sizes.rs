@@ -0,0 +1,236 @@
+//! Checks that the size suffix of each instruction agrees with the width of
+//! the general-purpose register operands its placeholders refer to.
+//!
+//! Widths given by a template modifier such as `{x:e}` or by the `reg_byte`
+//! class are checked right away. Otherwise the width is that of the Rust type
+//! of the operand, which is checked by the compiler through the traits of
+//! `asm_att::__private`. Such placeholders are replaced with the full
+//! register all the same, which is 64 bits wide on x86_64 and 32 bits wide
+//! on x86, so that the `b`, `w` and `l` suffixes need a modifier.
+
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+use syn::{Expr, Ident, LitStr};
+
+use asm_att_core::syntax::{Instruction, Operand, Placeholder, Size, Statement, Template};
+
+use crate::args::{AsmArgs, Direction, OperandKind, RegSpec};
+use crate::expand::located_error;
+use crate::placeholders;
+
+/// Shifts and rotates, whose first operand is a count rather than a value of
+/// the suffix size.
+const COUNTED: &[&str] = &[
+    "rcl", "rcr", "rol", "ror", "sal", "sar", "shl", "shr", "shld", "shrd",
+];
+
+/// Type checks of the operands, as closures that are never called: the ones
+/// to place before the `asm!` invocation, where inputs are initialized, and
+/// the ones to place after it, where outputs are.
+#[derive(Default)]
+pub(crate) struct TypeChecks {
+    pub before: Vec<TokenStream>,
+    pub after: Vec<TokenStream>,
+}
+
+/// Checks every placeholder that is a direct operand of a suffixed
+/// instruction.
+pub(crate) fn check(
+    template: &Template,
+    literals: &[&LitStr],
+    args: &AsmArgs,
+) -> syn::Result<TypeChecks> {
+    let resolved = placeholders::resolve(template, args);
+    let mut typed: Vec<(usize, Size)> = Vec::new();
+    let mut wide = Vec::new();
+    for (statement_index, (location, statement)) in template.statements.iter().enumerate() {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let sizes = instruction.operand_sizes();
+        for (index, operand) in instruction.operands.iter().enumerate() {
+            let (Operand::Placeholder(placeholder), Some(&operand_index)) =
+                (operand, resolved.get(&(statement_index, index)))
+            else {
+                continue;
+            };
+            let Some(size) = expected_size(instruction, sizes, index) else {
+                continue;
+            };
+            let kind = args
+                .operands
+                .get(operand_index)
+                .map(|operand| &operand.kind);
+            let bits = match placeholder_width(placeholder, kind) {
+                Width::Unknown => continue,
+                Width::Type if size == Size::Byte => {
+                    let mut low = placeholder.clone();
+                    low.modifier = Some("l".into());
+                    let message = format!(
+                        "`{}` operates on 8-bit operands, but `{placeholder}` is a `{}` register, \
+                         which is at least 16 bits wide; use a `reg_byte` operand or `{low}`",
+                        instruction.mnemonic,
+                        class(kind).map(ToString::to_string).unwrap_or_default(),
+                    );
+                    return Err(located_error(literals, *location, message));
+                }
+                Width::Type if size == Size::Word => {
+                    let message = unmodified(instruction, placeholder, size, "32-bit or 64-bit");
+                    return Err(located_error(literals, *location, message));
+                }
+                Width::Type => {
+                    if !typed.contains(&(operand_index, size)) {
+                        typed.push((operand_index, size));
+                    }
+                    if size == Size::Long {
+                        let message = unmodified(instruction, placeholder, size, "64-bit");
+                        let span = literals[location.template].span();
+                        wide.push(quote_spanned! {span=>
+                            {
+                                #[cfg(target_arch = "x86_64")]
+                                ::core::compile_error!(#message);
+                            }
+                        });
+                    }
+                    continue;
+                }
+                Width::Known(known) if known == size => continue,
+                Width::Known(known) => known.bits(),
+            };
+            let article = if bits == 8 { "an" } else { "a" };
+            let message = format!(
+                "`{}` operates on {}-bit operands, but `{placeholder}` is {article} {bits}-bit register",
+                instruction.mnemonic,
+                size.bits(),
+            );
+            return Err(located_error(literals, *location, message));
+        }
+    }
+
+    let mut checks = TypeChecks {
+        before: wide,
+        ..TypeChecks::default()
+    };
+    for (index, size) in typed {
+        let OperandKind::Reg {
+            dir,
+            expr,
+            out_expr,
+            ..
+        } = &args.operands[index].kind
+        else {
+            continue;
+        };
+        match dir {
+            Direction::In => checks.before.push(type_check(expr, size)),
+            Direction::Out | Direction::LateOut => checks.after.extend(place_check(expr, size)),
+            Direction::InOut | Direction::InLateOut => {
+                checks.before.push(type_check(expr, size));
+                if let Some(out_expr) = out_expr {
+                    checks.after.extend(place_check(out_expr, size));
+                }
+            }
+        }
+    }
+    Ok(checks)
+}
+
+/// The error for a placeholder without a modifier, which is replaced with a
+/// register of `width` where the suffix needs one of `size`.
+fn unmodified(
+    instruction: &Instruction,
+    placeholder: &Placeholder,
+    size: Size,
+    width: &str,
+) -> String {
+    let mut sized = placeholder.clone();
+    sized.modifier = Some(if size == Size::Word { "x" } else { "e" }.into());
+    format!(
+        "`{}` operates on {}-bit operands, but `{placeholder}` is a {width} register; use `{sized}`",
+        instruction.mnemonic,
+        size.bits(),
+    )
+}
+
+/// How the width of a placeholder is known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Width {
+    /// From a template modifier such as `{x:e}`, or the `reg_byte` class.
+    Known(Size),
+    /// From the Rust type of a general-purpose register operand.
+    Type,
+    /// Not a general-purpose register operand.
+    Unknown,
+}
+
+/// The general-purpose register class of an operand, if it has one.
+fn class(operand: Option<&OperandKind>) -> Option<&Ident> {
+    match operand {
+        Some(OperandKind::Reg {
+            reg: RegSpec::Class(class),
+            ..
+        }) if class == "reg" || class == "reg_abcd" || class == "reg_byte" => Some(class),
+        _ => None,
+    }
+}
+
+/// The width of the register a placeholder is replaced with.
+pub(crate) fn placeholder_width(placeholder: &Placeholder, operand: Option<&OperandKind>) -> Width {
+    let Some(class) = class(operand) else {
+        return Width::Unknown;
+    };
+    match placeholder.modifier.as_deref() {
+        Some("l" | "h") => Width::Known(Size::Byte),
+        Some("x") => Width::Known(Size::Word),
+        Some("e") => Width::Known(Size::Long),
+        Some("r") => Width::Known(Size::Quad),
+        Some(_) => Width::Unknown,
+        None if class == "reg_byte" => Width::Known(Size::Byte),
+        None => Width::Type,
+    }
+}
+
+/// Whether the operand at `index` of `count` operands of the unsuffixed
+/// mnemonic `stem` is the count of a shift or rotate.
+pub(crate) fn is_count(stem: &str, index: usize, count: usize) -> bool {
+    index == 0 && count > 1 && COUNTED.contains(&stem)
+}
+
+/// The size the suffix selects for the operand at `index`, if any.
+fn expected_size(
+    instruction: &Instruction,
+    sizes: Option<(Size, Size)>,
+    index: usize,
+) -> Option<Size> {
+    let (source, destination) = sizes?;
+    let count = instruction.operands.len();
+    let mnemonic = instruction.mnemonic.to_ascii_lowercase();
+    if is_count(&mnemonic[..mnemonic.len() - 1], index, count) {
+        return None;
+    }
+    Some(if index + 1 == count {
+        destination
+    } else {
+        source
+    })
+}
+
+/// A closure checking the type of an initialized value.
+fn type_check(expr: &Expr, size: Size) -> TokenStream {
+    let check = check_fn(expr, size);
+    quote_spanned!(expr.span()=> || #check(&(#expr)))
+}
+
+/// A closure checking the type of an output place, unless it is `_`.
+fn place_check(expr: &Expr, size: Size) -> Option<TokenStream> {
+    match expr {
+        Expr::Infer(_) => None,
+        _ => Some(type_check(expr, size)),
+    }
+}
+
+fn check_fn(expr: &Expr, size: Size) -> TokenStream {
+    let name = Ident::new(&format!("suffix_{}", size.suffix()), expr.span());
+    quote_spanned!(expr.span()=> ::asm_att::__private::#name)
+}
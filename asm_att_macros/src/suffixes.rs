@@ -0,0 +1,181 @@
+//! Size suffix inference for `asm_att_auto!`.
+//!
+//! The assembler infers the size of an unsuffixed instruction such as `mov`
+//! from its register operands, and gives up when there are none. The suffix
+//! is inserted into the template when the size is known from an explicit
+//! register, a template modifier or the `reg_byte` class. Placeholders whose
+//! width comes from the Rust type are left to the assembler, which sees the
+//! register they are replaced with.
+
+use syn::LitStr;
+
+use asm_att_core::syntax::{Location, Operand, Size, Statement, Template};
+
+use crate::args::AsmArgs;
+use crate::expand::located_error;
+use crate::placeholders;
+use crate::sizes::{self, Width};
+
+/// Mnemonics whose size defaults to the one of the mode, even without
+/// register operands.
+const DEFAULT_SIZED: &[&str] = &["push", "pop", "call", "jmp", "ret"];
+
+/// A suffix to insert after the `occurrence`th `mnemonic` of a line.
+struct Insertion {
+    location: Location,
+    mnemonic: String,
+    occurrence: usize,
+    suffix: char,
+}
+
+/// Adds the size suffix to every instruction that accepts one and whose size
+/// is known, both in `template` and in the template literals of `args`.
+pub(crate) fn infer(template: &mut Template, args: &mut AsmArgs) -> syn::Result<()> {
+    let Some(literals) = args.template_literals() else {
+        return Ok(());
+    };
+    let literals: Vec<LitStr> = literals.into_iter().cloned().collect();
+    let literal_refs: Vec<&LitStr> = literals.iter().collect();
+    let resolved = placeholders::resolve(template, args);
+
+    let mut insertions = Vec::new();
+    let mut seen: Vec<(Location, String)> = Vec::new();
+    for (statement_index, (location, statement)) in template.statements.iter_mut().enumerate() {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let occurrence = seen
+            .iter()
+            .filter(|(l, m)| *l == *location && *m == instruction.mnemonic)
+            .count();
+        seen.push((*location, instruction.mnemonic.clone()));
+
+        let stem = instruction.mnemonic.to_ascii_lowercase();
+        if !instruction.accepts_size_suffix()
+            || DEFAULT_SIZED.contains(&stem.as_str())
+            || instruction.operands.is_empty()
+        {
+            continue;
+        }
+
+        let mut known: Vec<Size> = Vec::new();
+        let mut sized_later = false;
+        let mut memory = false;
+        let count = instruction.operands.len();
+        for (index, operand) in instruction.operands.iter().enumerate() {
+            if sizes::is_count(&stem, index, count) {
+                continue;
+            }
+            match operand {
+                Operand::Register(register) => match register.size() {
+                    Some(size) => known.push(size),
+                    None => sized_later = true,
+                },
+                Operand::Placeholder(placeholder) => {
+                    let kind = resolved
+                        .get(&(statement_index, index))
+                        .and_then(|index| args.operands.get(*index))
+                        .map(|operand| &operand.kind);
+                    match sizes::placeholder_width(placeholder, kind) {
+                        Width::Known(size) => known.push(size),
+                        Width::Type | Width::Unknown => sized_later = true,
+                    }
+                }
+                Operand::Memory(_) | Operand::Expression(_) | Operand::Indirect(_) => memory = true,
+                Operand::Immediate(_) | Operand::Decorated(..) | Operand::Decorator(_) => {}
+            }
+        }
+        known.sort();
+        known.dedup();
+
+        let message = match known[..] {
+            [size] => {
+                insertions.push(Insertion {
+                    location: *location,
+                    mnemonic: instruction.mnemonic.clone(),
+                    occurrence,
+                    suffix: size.suffix(),
+                });
+                instruction.mnemonic.push(size.suffix());
+                continue;
+            }
+            [] if sized_later || !memory => continue,
+            [] => format!(
+                "`{instruction}` is ambiguous: none of its operands has a size; \
+                 write `{stem}b`, `{stem}w`, `{stem}l` or `{stem}q`"
+            ),
+            [..] => {
+                let widths: Vec<String> = known
+                    .iter()
+                    .map(|size| format!("{}-bit", size.bits()))
+                    .collect();
+                format!("`{instruction}` mixes {} operands", widths.join(" and "))
+            }
+        };
+        return Err(located_error(&literal_refs, *location, message));
+    }
+
+    let mut values: Vec<String> = literals.iter().map(LitStr::value).collect();
+    for insertion in insertions.iter().rev() {
+        let value = &mut values[insertion.location.template];
+        if let Some(position) = find_mnemonic(value, insertion) {
+            value.insert(position, insertion.suffix);
+        }
+    }
+    for (index, (literal, value)) in literals.iter().zip(values).enumerate() {
+        if value != literal.value() {
+            args.replace_template(index, &value);
+        }
+    }
+    Ok(())
+}
+
+/// The position right after the mnemonic of an insertion in the template
+/// text, skipping comments.
+fn find_mnemonic(text: &str, insertion: &Insertion) -> Option<usize> {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(insertion.location.line)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next()?;
+    let code = blank_comments(line);
+    let bytes = code.as_bytes();
+    let mnemonic = insertion.mnemonic.as_str();
+    let found = code
+        .match_indices(mnemonic)
+        .map(|(start, _)| (start, start + mnemonic.len()))
+        .filter(|&(start, end)| {
+            let before = start.checked_sub(1).map(|i| bytes[i]);
+            let after = bytes.get(end).copied();
+            matches!(before, None | Some(b' ' | b'\t' | b';' | b':'))
+                && matches!(after, None | Some(b' ' | b'\t' | b'\r' | b';'))
+        })
+        .nth(insertion.occurrence)?;
+    Some(line_start + found.1)
+}
+
+/// The line with `#` and `/* */` comments replaced by spaces, so that
+/// positions are kept.
+fn blank_comments(line: &str) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(position) = rest.find(['#', '/']) {
+        code.push_str(&rest[..position]);
+        rest = &rest[position..];
+        if rest.starts_with('#') {
+            code.push_str(&" ".repeat(rest.len()));
+            return code;
+        }
+        if rest.starts_with("/*") {
+            let end = rest.find("*/").map_or(rest.len(), |end| end + 2);
+            code.push_str(&" ".repeat(end));
+            rest = &rest[end..];
+        } else {
+            code.push('/');
+            rest = &rest[1..];
+        }
+    }
+    code.push_str(rest);
+    code
+}
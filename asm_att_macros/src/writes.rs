@@ -0,0 +1,47 @@
+//! Rejection of templates that write operands the compiler does not expect
+//! to change: `in` operands, whose register must keep its value, and `const`,
+//! `sym` and `label` operands, which cannot be written at all.
+
+use syn::LitStr;
+
+use asm_att_core::syntax::{Operand, Statement, Template};
+
+use crate::args::{AsmArgs, Direction, OperandKind};
+use crate::expand::located_error;
+use crate::placeholders;
+
+/// Rejects the first placeholder in the destination of an instruction that
+/// refers to an operand that is not an output.
+pub(crate) fn check(template: &Template, literals: &[&LitStr], args: &AsmArgs) -> syn::Result<()> {
+    let resolved = placeholders::resolve(template, args);
+    for (statement_index, (location, statement)) in template.statements.iter().enumerate() {
+        let Statement::Instruction(instruction) = statement else {
+            continue;
+        };
+        let first_written = instruction.operands.len() - instruction.written_operands().len();
+        for (index, operand) in instruction.operands.iter().enumerate().skip(first_written) {
+            let (Operand::Placeholder(placeholder), Some(&operand_index)) = (
+                operand.undecorated(),
+                resolved.get(&(statement_index, index)),
+            ) else {
+                continue;
+            };
+            let reason = match args
+                .operands
+                .get(operand_index)
+                .map(|operand| &operand.kind)
+            {
+                Some(OperandKind::Reg {
+                    dir: Direction::In, ..
+                }) => "an `in` operand; declare it with `inout` instead",
+                Some(OperandKind::Const(_)) => "a `const` operand and cannot be written",
+                Some(OperandKind::Sym(_)) => "a `sym` operand and cannot be written",
+                Some(OperandKind::Label(_)) => "a `label` operand and cannot be written",
+                _ => continue,
+            };
+            let message = format!("`{instruction}` writes `{placeholder}`, which is {reason}");
+            return Err(located_error(literals, *location, message));
+        }
+    }
+    Ok(())
+}
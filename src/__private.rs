@@ -0,0 +1,84 @@
+//! Support code for the expansions of the macros. Not public API.
+
+pub use crate::multiversion::{detects, has_features};
+
+/// Types of 16-bit general-purpose register operands.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a 16-bit operand, as the `w` size suffix requires",
+    label = "used with a `w` size suffix"
+)]
+pub trait SuffixW {}
+
+/// Types of 32-bit general-purpose register operands.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a 32-bit operand, as the `l` size suffix requires",
+    label = "used with an `l` size suffix"
+)]
+pub trait SuffixL {}
+
+/// Types of 64-bit general-purpose register operands.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a 64-bit operand, as the `q` size suffix requires",
+    label = "used with a `q` size suffix"
+)]
+pub trait SuffixQ {}
+
+macro_rules! impl_suffix {
+    ($suffix:ident: $($ty:ty),*) => {$(
+        impl $suffix for $ty {}
+    )*};
+}
+
+impl_suffix!(SuffixW: u16, i16);
+impl_suffix!(SuffixL: u32, i32, f32);
+impl_suffix!(SuffixQ: u64, i64, f64);
+
+#[cfg(target_pointer_width = "32")]
+impl_suffix!(SuffixL: usize, isize);
+#[cfg(target_pointer_width = "64")]
+impl_suffix!(SuffixQ: usize, isize);
+
+/// Implements a suffix trait for the thin pointers: raw pointers and
+/// references to sized types, and function pointers of up to six arguments.
+macro_rules! impl_pointer_suffix {
+    ($suffix:ident) => {
+        impl<T> $suffix for *const T {}
+        impl<T> $suffix for *mut T {}
+        impl<T> $suffix for &T {}
+        impl<T> $suffix for &mut T {}
+        impl_pointer_suffix!($suffix: A B C D E F);
+    };
+    ($suffix:ident: $($arg:ident)*) => {
+        impl<R, $($arg),*> $suffix for fn($($arg),*) -> R {}
+        impl<R, $($arg),*> $suffix for unsafe fn($($arg),*) -> R {}
+        impl<R, $($arg),*> $suffix for extern "C" fn($($arg),*) -> R {}
+        impl<R, $($arg),*> $suffix for unsafe extern "C" fn($($arg),*) -> R {}
+        impl_pointer_suffix!(@fewer $suffix: $($arg)*);
+    };
+    (@fewer $suffix:ident:) => {};
+    (@fewer $suffix:ident: $first:ident $($rest:ident)*) => {
+        impl_pointer_suffix!($suffix: $($rest)*);
+    };
+}
+
+#[cfg(target_pointer_width = "32")]
+impl_pointer_suffix!(SuffixL);
+#[cfg(target_pointer_width = "64")]
+impl_pointer_suffix!(SuffixQ);
+
+/// Checks that an operand used with a `w` suffix is 16 bits wide.
+pub fn suffix_w<T: SuffixW>(_: &T) {}
+
+/// Checks that an operand used with an `l` suffix is 32 bits wide.
+pub fn suffix_l<T: SuffixL>(_: &T) {}
+
+/// Checks that an operand used with a `q` suffix is 64 bits wide.
+pub fn suffix_q<T: SuffixQ>(_: &T) {}
+
+/// Checks that the value of a `const` operand fits the immediate it is used
+/// as, in the inline `const` block of a `{name:imm}` placeholder.
+pub const fn immediate(value: i128, min: i128, max: i128, message: &str) {
+    if value < min || value > max {
+        panic!("{}", message);
+    }
+}
@@ -0,0 +1,86 @@
+//! Migrates the `asm!`, `global_asm!` and `naked_asm!` calls of Rust source
+//! files to `asm_att!`, `global_asm_att!` and `naked_asm_att!`.
+
+use std::fs;
+use std::process::ExitCode;
+
+use asm_att::cli;
+use asm_att::convert::{MigrateOptions, migrate};
+
+const USAGE: &str = "\
+Usage: asm-att-convert [--dry-run] [--intel] <PATH>...
+
+Rewrites the asm!, global_asm! and naked_asm! calls of the Rust files among
+and under PATH to the macros of asm_att.
+
+Options:
+  --dry-run   print the changes as a diff instead of writing them
+  --intel     also convert calls in Intel syntax to AT&T syntax
+  -h, --help  print this help";
+
+fn main() -> ExitCode {
+    let (flags, paths) = match cli::args(USAGE, &["--dry-run", "--intel"]) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+    let dry_run = flags.iter().any(|flag| flag == "--dry-run");
+    let options = MigrateOptions {
+        intel: flags.iter().any(|flag| flag == "--intel"),
+    };
+
+    let files = match cli::rust_files(&paths) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut changed, mut migrated, mut skipped, mut intel, mut failed) = (0, 0, 0, 0, false);
+    for path in &files {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: error: {error}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let migration = match migrate(&source, options) {
+            Ok(migration) => migration,
+            Err(error) => {
+                let location = cli::location(path, &source, error.offset());
+                eprintln!("{location}: error: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        for call in &migration.skipped {
+            let location = cli::location(path, &source, call.offset);
+            eprintln!("{location}: skipped: {}", call.reason);
+            intel += usize::from(call.reason == "uses Intel syntax");
+        }
+        skipped += migration.skipped.len();
+        if migration.edits.is_empty() {
+            continue;
+        }
+        changed += 1;
+        migrated += migration.migrated;
+        if dry_run {
+            print!("{}", cli::unified_diff(path, &source, &migration.edits));
+        } else if let Err(error) = fs::write(path, migration.apply(&source)) {
+            eprintln!("{}: error: {error}", path.display());
+            failed = true;
+        }
+    }
+
+    let verb = if dry_run { "would migrate" } else { "migrated" };
+    eprintln!("{verb} {migrated} call(s) in {changed} file(s), skipped {skipped}");
+    if intel > 0 && !options.intel {
+        eprintln!("note: pass --intel to convert the {intel} call(s) in Intel syntax");
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,81 @@
+//! Formats the templates of the `asm_att!`, `global_asm_att!` and
+//! `naked_asm_att!` calls of Rust source files.
+
+use std::fs;
+use std::process::ExitCode;
+
+use asm_att::cli;
+use asm_att::convert::format_templates;
+
+const USAGE: &str = "\
+Usage: asm-att-fmt [--check] <PATH>...
+
+Formats the templates of the asm_att!, global_asm_att! and naked_asm_att!
+calls of the Rust files among and under PATH: one statement per string
+literal, with aligned operands.
+
+Options:
+  --check     print the changes as a diff instead of writing them, and fail
+              if there are any
+  -h, --help  print this help";
+
+fn main() -> ExitCode {
+    let (flags, paths) = match cli::args(USAGE, &["--check"]) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+    let check = flags.iter().any(|flag| flag == "--check");
+
+    let files = match cli::rust_files(&paths) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut changed, mut failed) = (0, false);
+    for path in &files {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: error: {error}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let formatting = match format_templates(&source) {
+            Ok(formatting) => formatting,
+            Err(error) => {
+                let location = cli::location(path, &source, error.offset());
+                eprintln!("{location}: error: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        for call in &formatting.skipped {
+            let location = cli::location(path, &source, call.offset);
+            eprintln!("{location}: skipped: {}", call.reason);
+        }
+        if formatting.edits.is_empty() {
+            continue;
+        }
+        changed += 1;
+        if check {
+            print!("{}", cli::unified_diff(path, &source, &formatting.edits));
+        } else if let Err(error) = fs::write(path, formatting.apply(&source)) {
+            eprintln!("{}: error: {error}", path.display());
+            failed = true;
+        }
+    }
+
+    if check {
+        eprintln!("{changed} file(s) would be formatted");
+    } else {
+        eprintln!("formatted {changed} file(s)");
+    }
+    if failed || (check && changed > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
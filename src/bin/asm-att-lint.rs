@@ -0,0 +1,119 @@
+//! Reports mistakes in the `asm_att!`, `global_asm_att!` and `naked_asm_att!`
+//! calls of Rust source files.
+
+use std::fs;
+use std::process::ExitCode;
+
+use asm_att::cli;
+use asm_att::lint::lint;
+
+const USAGE: &str = "\
+Usage: asm-att-lint [--json] <PATH>...
+
+Checks the asm_att!, global_asm_att! and naked_asm_att! calls of the Rust
+files among and under PATH, and fails if it finds mistakes.
+
+Options:
+  --json      print the findings as a JSON object with `diagnostics` and
+              `skipped` arrays
+  -h, --help  print this help";
+
+/// A finding, as `(location, line, column, kind, message)`.
+type Finding = (String, usize, usize, String, String);
+
+fn main() -> ExitCode {
+    let (flags, paths) = match cli::args(USAGE, &["--json"]) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+    let json = flags.iter().any(|flag| flag == "--json");
+
+    let files = match cli::rust_files(&paths) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut diagnostics, mut skipped): (Vec<Finding>, Vec<Finding>) = (Vec::new(), Vec::new());
+    let (mut checked, mut failed) = (0, false);
+    for path in &files {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: error: {error}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let report = match lint(&source) {
+            Ok(report) => report,
+            Err(error) => {
+                let location = cli::location(path, &source, error.offset());
+                eprintln!("{location}: error: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        checked += report.checked;
+        let finding = |offset: usize, kind: String, message: String| {
+            let (line, column) = cli::line_column(&source, offset);
+            (path.display().to_string(), line, column, kind, message)
+        };
+        diagnostics.extend(report.diagnostics.into_iter().map(|diagnostic| {
+            finding(
+                diagnostic.offset,
+                diagnostic.lint.to_string(),
+                diagnostic.message,
+            )
+        }));
+        skipped.extend(
+            report
+                .skipped
+                .into_iter()
+                .map(|call| finding(call.offset, String::new(), call.reason)),
+        );
+    }
+
+    if json {
+        let diagnostic = |(file, line, column, lint, message): &Finding| {
+            format!(
+                "{{\"file\": {}, \"line\": {line}, \"column\": {column}, \"lint\": {}, \"message\": {}}}",
+                cli::json_string(file),
+                cli::json_string(lint),
+                cli::json_string(message)
+            )
+        };
+        let call = |(file, line, column, _, reason): &Finding| {
+            format!(
+                "{{\"file\": {}, \"line\": {line}, \"column\": {column}, \"reason\": {}}}",
+                cli::json_string(file),
+                cli::json_string(reason)
+            )
+        };
+        let diagnostics: Vec<String> = diagnostics.iter().map(diagnostic).collect();
+        let skipped: Vec<String> = skipped.iter().map(call).collect();
+        println!(
+            "{{\"diagnostics\": [{}], \"skipped\": [{}]}}",
+            diagnostics.join(", "),
+            skipped.join(", ")
+        );
+    } else {
+        for (file, line, column, lint, message) in &diagnostics {
+            println!("{file}:{line}:{column}: warning[{lint}]: {message}");
+        }
+        for (file, line, column, _, reason) in &skipped {
+            eprintln!("{file}:{line}:{column}: skipped: {reason}");
+        }
+        eprintln!(
+            "checked {checked} call(s), found {} problem(s), skipped {}",
+            diagnostics.len(),
+            skipped.len()
+        );
+    }
+    if failed || !diagnostics.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,241 @@
+//! Helpers shared by the command-line tools of the `cli` feature. Not public
+//! API.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::string::String;
+use std::vec::Vec;
+use std::{env, eprintln, format, io, println};
+
+use asm_att_core::convert::Edit;
+
+/// Lines of context around the changes of a diff.
+const CONTEXT: usize = 3;
+
+/// The command-line arguments: the options among `options` that were given,
+/// and the paths.
+///
+/// Prints the usage, and returns the exit code to stop with, when help is
+/// asked for, an option is unknown or no path is given.
+pub fn args(usage: &str, options: &[&str]) -> Result<(Vec<String>, Vec<PathBuf>), ExitCode> {
+    let mut given = Vec::new();
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{usage}");
+                return Err(ExitCode::SUCCESS);
+            }
+            _ if options.contains(&arg.as_str()) => given.push(arg),
+            _ if arg.starts_with('-') => {
+                eprintln!("error: unknown option `{arg}`\n\n{usage}");
+                return Err(ExitCode::from(2));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{usage}");
+        return Err(ExitCode::from(2));
+    }
+    Ok((given, paths))
+}
+
+/// The Rust files among `paths` and under the directories among them, in
+/// order, leaving out `target` and hidden directories.
+pub fn rust_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn walk(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if path.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                walk(&path, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `path:line:column` for a byte offset of the source of a file.
+pub fn location(path: &Path, source: &str, offset: usize) -> String {
+    let (line, column) = line_column(source, offset);
+    format!("{}:{line}:{column}", path.display())
+}
+
+/// The line and column of a byte offset, both counted from 1.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// A unified diff of the changes that `edits` make to `source`.
+pub fn unified_diff(path: &Path, source: &str, edits: &[Edit]) -> String {
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    for line in &lines {
+        starts.push(offset);
+        offset += line.len();
+    }
+    starts.push(offset);
+    let line_of = |offset: usize| starts.partition_point(|&start| start <= offset) - 1;
+
+    // The changed lines, with the edits that change them: edits on the same
+    // or adjacent lines are merged.
+    let mut changes: Vec<(usize, usize, Vec<&Edit>)> = Vec::new();
+    for edit in edits {
+        let first = line_of(edit.range.start);
+        let last = line_of(edit.range.end.max(edit.range.start + 1) - 1).max(first);
+        match changes.last_mut() {
+            Some((_, end, merged)) if first <= *end + 1 => {
+                *end = (*end).max(last);
+                merged.push(edit);
+            }
+            _ => changes.push((first, last, Vec::from([edit]))),
+        }
+    }
+
+    let mut diff = format!("--- a/{0}\n+++ b/{0}\n", path.display());
+    let mut delta = 0_isize;
+    let mut index = 0;
+    while index < changes.len() {
+        let mut end = index + 1;
+        while end < changes.len() && changes[end].0 - changes[end - 1].1 <= 2 * CONTEXT + 1 {
+            end += 1;
+        }
+        let hunk = &changes[index..end];
+        let start = hunk[0].0.saturating_sub(CONTEXT);
+        let stop = (hunk[hunk.len() - 1].1 + 1 + CONTEXT).min(lines.len());
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut cursor = start;
+        for (first, last, merged) in hunk {
+            for line in &lines[cursor..*first] {
+                push_line(&mut body, ' ', line);
+            }
+            for line in &lines[*first..=*last] {
+                push_line(&mut body, '-', line);
+            }
+            let span = starts[*first]..starts[*last + 1];
+            let mut text = String::new();
+            let mut offset = span.start;
+            for edit in merged {
+                text.push_str(&source[offset..edit.range.start]);
+                text.push_str(&edit.replacement);
+                offset = edit.range.end;
+            }
+            text.push_str(&source[offset..span.end]);
+            let replaced = text.split_inclusive('\n').count();
+            for line in text.split_inclusive('\n') {
+                push_line(&mut body, '+', line);
+            }
+            old_count += *first - cursor + (*last + 1 - *first);
+            new_count += *first - cursor + replaced;
+            cursor = *last + 1;
+        }
+        for line in &lines[cursor..stop] {
+            push_line(&mut body, ' ', line);
+        }
+        old_count += stop - cursor;
+        new_count += stop - cursor;
+        let new_start = start as isize + delta;
+        diff.push_str(&format!(
+            "@@ -{},{old_count} +{},{new_count} @@\n",
+            start + 1,
+            new_start + 1
+        ));
+        diff.push_str(&body);
+        delta += new_count as isize - old_count as isize;
+        index = end;
+    }
+    diff
+}
+
+/// A JSON string holding `text`.
+pub fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if u32::from(c) < 0x20 => json.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn push_line(diff: &mut String, marker: char, line: &str) {
+    diff.push(marker);
+    diff.push_str(line.strip_suffix('\n').unwrap_or(line));
+    diff.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_edits() {
+        let source = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let edit = |line: usize, replacement: &str| Edit {
+            range: line * 2..line * 2 + 1,
+            replacement: replacement.into(),
+        };
+        let diff = unified_diff(
+            Path::new("src/lib.rs"),
+            source,
+            &[edit(1, "B"), edit(2, "C1\nC2"), edit(11, "L")],
+        );
+        assert_eq!(
+            diff,
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n\
+             @@ -1,6 +1,7 @@\n a\n-b\n-c\n+B\n+C1\n+C2\n d\n e\n f\n\
+             @@ -9,5 +10,5 @@\n i\n j\n k\n-l\n+L\n m\n"
+        );
+
+        let diff = unified_diff(Path::new("x.rs"), source, &[edit(0, "A"), edit(12, "M")]);
+        assert_eq!(
+            diff,
+            "--- a/x.rs\n+++ b/x.rs\n\
+             @@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n d\n\
+             @@ -10,4 +10,4 @@\n j\n k\n l\n-m\n+M\n"
+        );
+        assert_eq!(line_column("ab\ncd", 4), (2, 2));
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(
+            json_string("`%rax` \"a\\b\"\n\u{1}é"),
+            r#""`%rax` \"a\\b\"\n\u0001é""#
+        );
+    }
+}
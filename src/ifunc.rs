@@ -0,0 +1,132 @@
+//! `global_ifunc_att!`, whose implementation is chosen by the dynamic loader.
+
+/// Defines a function with an AT&T implementation for each set of CPU
+/// features, chosen once by the dynamic loader as a GNU indirect function,
+/// instead of on the first call as by
+/// [`asm_att_multiversion!`](crate::asm_att_multiversion).
+///
+/// The function is declared with its Rust signature, which makes it an
+/// `unsafe extern "C"` function, followed by its implementations: template
+/// strings of [`global_asm_att!`](crate::global_asm_att) under the symbol
+/// they are defined as, tagged with the features they need in the form
+/// `#[target_feature(enable = "...")]` takes, or `_` for the one used when
+/// no other fits. The implementations follow the C calling convention, and
+/// their symbols are hidden from other shared objects.
+///
+/// The symbol of the function is typed `@gnu_indirect_function`, and jumps
+/// to a resolver that detects the features of the CPU with `cpuid` and
+/// returns the first implementation whose features are all there. Features
+/// that the detection does not know of are rejected at compile time.
+/// Indirect functions only exist on ELF targets whose loader resolves
+/// `IRELATIVE` relocations, such as Linux with glibc and the BSDs. The musl
+/// loader, as on `x86_64-unknown-linux-musl`, does not support them.
+///
+/// The templates are built with `concat!`, so that the `parser` feature does
+/// not check them.
+///
+/// ```rust
+/// # #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+/// # {
+/// use asm_att::global_ifunc_att;
+///
+/// global_ifunc_att! {
+///     fn add(a: u64, b: u64) -> u64;
+///     "bmi2" => add_bmi2 {
+///         "leaq (%rdi,%rsi), %rax",
+///         "ret",
+///     }
+///     _ => add_baseline {
+///         "movq %rdi, %rax",
+///         "addq %rsi, %rax",
+///         "ret",
+///     }
+/// }
+///
+/// assert_eq!(unsafe { add(40, 2) }, 42);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! global_ifunc_att {
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+        $($features:literal => $candidate:ident { $($template:expr),* $(,)? })*
+        _ => $baseline:ident { $($baseline_template:expr),* $(,)? }
+    ) => {
+        unsafe extern "C" {
+            $(#[$attr])*
+            $vis fn $name($($arg: $ty),*) $(-> $ret)?;
+        }
+
+        // `global_asm!` is only allowed among the items of a module, which an
+        // unnamed constant keeps out of the caller's scope.
+        const _: () = {
+            $($crate::__check_features!($features);)*
+
+            mod resolver {
+                extern "C" fn resolve() -> *const () {
+                    unsafe extern "C" {
+                        $(fn $candidate();)*
+                        fn $baseline();
+                    }
+                    $(
+                        if $crate::__private::has_features($features) {
+                            return $candidate as *const ();
+                        }
+                    )*
+                    $baseline as *const ()
+                }
+
+                $crate::global_asm_att!(
+                    ".pushsection .text",
+                    ::core::concat!(".globl ", ::core::stringify!($name)),
+                    ::core::concat!(".type ", ::core::stringify!($name), ", @gnu_indirect_function"),
+                    ::core::concat!(::core::stringify!($name), ":"),
+                    "jmp {resolve}",
+                    $crate::__global_ifunc_att!(size $name),
+                    $(
+                        $crate::__global_ifunc_att!(header $candidate),
+                        $($template,)*
+                        $crate::__global_ifunc_att!(size $candidate),
+                    )*
+                    $crate::__global_ifunc_att!(header $baseline),
+                    $($baseline_template,)*
+                    $crate::__global_ifunc_att!(size $baseline),
+                    ".popsection",
+                    resolve = sym resolve,
+                );
+            }
+        };
+    };
+}
+
+/// The lines that open and close an implementation of `global_ifunc_att!`,
+/// and that close the function itself.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __global_ifunc_att {
+    (header $symbol:ident) => {
+        ::core::concat!(
+            ".globl ",
+            ::core::stringify!($symbol),
+            "\n",
+            ".hidden ",
+            ::core::stringify!($symbol),
+            "\n",
+            ".type ",
+            ::core::stringify!($symbol),
+            ", @function\n",
+            ".p2align 4\n",
+            ::core::stringify!($symbol),
+            ":",
+        )
+    };
+    (size $symbol:ident) => {
+        ::core::concat!(
+            ".size ",
+            ::core::stringify!($symbol),
+            ", . - ",
+            ::core::stringify!($symbol),
+        )
+    };
+}
@@ -4,6 +4,42 @@
 //!
 //! Macros that makes the assembly writing of ATT syntax more convenient.
 //!
+//! ## Features
+//!
+//! - `parser`: replaces the `macro_rules!` implementations with a procedural
+//!   backend that parses every template string before forwarding it, so that
+//!   malformed templates are reported on the offending line instead of by the
+//!   assembler. AVX-512 decorators such as `{%k1}` no longer need their
+//!   braces doubled. Templates of `asm_att!` may use `{label:name}` local
+//!   labels, `mem!(disp, base, index, scale)` memory references and
+//!   `{name:imm}` immediates, and instructions that need a CPU feature the
+//!   crate is not compiled with get a warning. It also provides
+//!   `asm_att_auto!`, which works out the size suffixes, clobbers and options
+//!   of the template by itself, `intel_to_att!`, which rewrites Intel
+//!   templates into AT&T syntax, `asm_att_as_intel!`, which builds an AT&T
+//!   template as Intel syntax, `gcc_asm_att!`, which lowers GCC extended
+//!   `asm` statements, and `required_features!`, which lists the CPU features
+//!   of a template.
+//! - `syntax`: exposes the [`syntax`] module, a model of AT&T assembly that can
+//!   be parsed and printed in both AT&T and Intel syntax.
+//! - `convert`: exposes the [`convert`] module, which rewrites templates
+//!   between Intel and AT&T syntax and GCC `asm` statements into `asm_att!`,
+//!   migrates the `core::arch` assembly macro calls of Rust source and
+//!   formats the templates of the calls of this crate's macros.
+//! - `lint`: exposes the [`lint`] module, which checks the calls of this
+//!   crate's macros in Rust source for mistakes such as writes to `in`
+//!   operands and missing clobbers.
+//! - `isa`: exposes the [`isa`] module, a table of x86 instructions with
+//!   their AT&T spellings, operand forms, implicit registers, flags, CPU
+//!   features and privilege level, such as `isa::lookup("cmpsb")`.
+//! - `cli`: builds three tools for Rust source files. `asm-att-convert`
+//!   migrates their `asm!`, `global_asm!` and `naked_asm!` calls to the
+//!   macros of this crate, converting Intel templates with `--intel` and
+//!   printing a diff instead with `--dry-run`. `asm-att-fmt` formats the
+//!   templates of their `asm_att!`, `global_asm_att!` and `naked_asm_att!`
+//!   calls, with a `--check` mode for CI. `asm-att-lint` reports what the
+//!   [`lint`] module finds in them, as text or, with `--json`, as JSON.
+//!
 //! ## Examples
 //!
 //! ```rust
@@ -136,29 +172,43 @@
 //! }
 //! ```
 
-/// See [`core::arch::asm`] for more.
-#[macro_export]
-macro_rules! asm_att {
-    ( $($arg:tt)+ ) => {
-        ::core::arch::asm!($($arg)+, options(att_syntax));
-    };
-}
+#[cfg(not(feature = "parser"))]
+mod rules;
 
-/// See [`core::arch::global_asm`] for more.
-#[macro_export]
-macro_rules! global_asm_att {
-    ( $($arg:tt)+ ) => {
-        ::core::arch::global_asm!($($arg)+, options(att_syntax));
-    };
-}
+#[cfg(feature = "parser")]
+extern crate self as asm_att;
 
-/// See [`core::arch::naked_asm`] for more.
-#[macro_export]
-macro_rules! naked_asm_att {
-    ( $($arg:tt)+ ) => {
-        ::core::arch::naked_asm!($($arg)+, options(att_syntax));
-    };
-}
+#[doc(hidden)]
+pub mod __private;
+
+mod multiversion;
+
+mod ifunc;
+
+#[cfg(feature = "parser")]
+pub use asm_att_macros::{
+    asm_att, asm_att_as_intel, asm_att_auto, gcc_asm_att, global_asm_att, intel_to_att,
+    naked_asm_att, required_features,
+};
+
+#[cfg(feature = "syntax")]
+pub use asm_att_core::syntax;
+
+#[cfg(feature = "convert")]
+pub use asm_att_core::convert;
+
+#[cfg(feature = "lint")]
+pub use asm_att_core::lint;
+
+#[cfg(feature = "isa")]
+pub use asm_att_core::isa;
+
+#[cfg(feature = "cli")]
+extern crate std;
+
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod cli;
 
 #[cfg(all(test, target_arch = "x86_64"))]
 mod tests {
@@ -178,6 +228,35 @@ mod tests {
         fn add2(a: i32, b: i32) -> i32;
     }
 
+    // Only assembled, as the host may lack AVX-512.
+    #[cfg(feature = "parser")]
+    global_asm_att!(
+        r#"
+        .global masked_add16
+        masked_add16:
+            vpaddd (%rdi){1to16}, %zmm0, %zmm1{%k1}{z}
+            vaddps {rn-sae}, %zmm0, %zmm1, %zmm2
+            ret
+        "#
+    );
+
+    #[cfg(feature = "parser")]
+    global_asm_att!(
+        ".macro push_reg reg",
+        "pushq %\\reg",
+        ".endm",
+        ".global through_stack",
+        "through_stack:",
+        "push_reg rdi",
+        "popq %rax",
+        "ret",
+    );
+
+    #[cfg(feature = "parser")]
+    unsafe extern "C" {
+        fn through_stack(value: u64) -> u64;
+    }
+
     #[unsafe(naked)]
     #[unsafe(no_mangle)]
     unsafe extern "C" fn return_1000() -> i32 {
@@ -195,14 +274,432 @@ mod tests {
         );
     }
 
+    fn xor_swap(mut a: u64, mut b: u64) -> (u64, u64) {
+        unsafe {
+            asm_att!(
+                "xorq {b}, {a}",
+                "xorq {a}, {b}",
+                "xorq {b}, {a}",
+                a = inout(reg) a,
+                b = inout(reg) b,
+                options(att_syntax, nomem),
+                options(nostack),
+            );
+        }
+        (a, b)
+    }
+
+    fn pause_or_spin() {
+        unsafe {
+            asm_att!("pause", options(nomem, nostack); else { core::hint::spin_loop() });
+        }
+    }
+
+    // `asm_att!` is an expression, of type `!` with `options(noreturn)`.
+    #[cfg(feature = "parser")]
+    fn trap() -> ! {
+        unsafe { asm_att!("ud2", options(noreturn, nomem, nostack)) }
+    }
+
+    #[cfg(feature = "parser")]
+    fn increment(value: &mut u64) {
+        let () = unsafe { asm_att!("incq ({p})", p = in(reg) value, options(nostack)) };
+    }
+
+    #[cfg(feature = "parser")]
+    fn copy(src: &[u8], dst: &mut [u8]) {
+        assert_eq!(src.len(), dst.len());
+        unsafe {
+            asm_att_auto!(
+                "cld",
+                "movq {src}, %rsi",
+                "movq {dst}, %rdi",
+                "rep movsb",
+                src = in(reg) src.as_ptr(),
+                dst = in(reg) dst.as_mut_ptr(),
+                inout("rcx") dst.len() => _,
+            );
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    fn store_seven(value: &mut u32) {
+        unsafe {
+            asm_att_auto!("mov $7, %ecx", "mov %ecx, ({p})", p = in(reg) value);
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    fn pointer_sum(value: &u64, f: fn(), g: extern "C" fn()) -> usize {
+        let sum: usize;
+        unsafe {
+            asm_att!(
+                "movq {p}, {sum}",
+                "addq {f}, {sum}",
+                "addq {g}, {sum}",
+                p = in(reg) value,
+                f = in(reg) f,
+                g = in(reg) g,
+                sum = out(reg) sum,
+            );
+        }
+        sum
+    }
+
+    #[cfg(feature = "parser")]
+    fn second_plus_one(values: &[u32; 2]) -> u32 {
+        let value: u32;
+        unsafe {
+            asm_att!(
+                intel_to_att!("mov {v:e}, dword ptr [{p} + 4]", "add {v:e}, {n}"),
+                p = in(reg) values.as_ptr(),
+                v = out(reg) value,
+                n = const 1,
+                options(nostack, readonly),
+            );
+        }
+        value
+    }
+
+    #[cfg(feature = "parser")]
+    fn element(values: &[u32], index: usize) -> u32 {
+        assert!(index < values.len());
+        let value: u32;
+        unsafe {
+            asm_att_as_intel!(
+                "movl ({p},{i},4), {v:e}",
+                p = in(reg) values.as_ptr(),
+                i = in(reg) index,
+                v = out(reg) value,
+                options(nostack, readonly),
+            );
+        }
+        value
+    }
+
+    #[cfg(feature = "parser")]
+    fn previous(values: &[u32], index: usize) -> u32 {
+        assert!(0 < index && index < values.len());
+        let value: u32;
+        unsafe {
+            asm_att!(
+                "movl mem!(-4, {p}, {i}, 4), {v:e}",
+                p = in(reg) values.as_ptr(),
+                i = in(reg) index,
+                v = lateout(reg) value,
+                options(readonly, nostack),
+            );
+        }
+        value
+    }
+
+    #[cfg(feature = "parser")]
+    fn scaled(x: u64) -> u64 {
+        let mut x = x;
+        unsafe {
+            asm_att!(
+                "shlq {shift:imm}, {x}",
+                "addq {offset:imm}, {x}",
+                x = inout(reg) x,
+                shift = const 3,
+                offset = const -8,
+                options(nomem, nostack),
+            );
+        }
+        x
+    }
+
+    // Only called once `bmi2` is detected, which `cfg(target_feature)` does
+    // not know of.
+    #[cfg(feature = "parser")]
+    #[target_feature(enable = "bmi2")]
+    unsafe fn deposit(value: u64, mask: u64) -> u64 {
+        let result;
+        unsafe {
+            asm_att!(
+                "pdepq {mask}, {value}, {result}",
+                value = in(reg) value,
+                mask = in(reg) mask,
+                result = lateout(reg) result,
+                options(pure, nomem, nostack),
+                target_feature(enable = "bmi2"),
+            );
+        }
+        result
+    }
+
+    asm_att_multiversion! {
+        fn add_lanes(a: &[u32; 8], b: &[u32; 8]) -> ([u32; 8], &'static str) {
+            "avx2" => {
+                let mut sum = [0; 8];
+                unsafe {
+                    asm_att!(
+                        "vmovdqu ({a}), %ymm0",
+                        "vpaddd ({b}), %ymm0, %ymm0",
+                        "vmovdqu %ymm0, ({sum})",
+                        a = in(reg) a,
+                        b = in(reg) b,
+                        sum = in(reg) sum.as_mut_ptr(),
+                        out("ymm0") _,
+                        options(nostack),
+                        target_feature(enable = "avx2"),
+                    );
+                }
+                (sum, "avx2")
+            }
+            "sse2" => {
+                let mut sum = [0; 8];
+                unsafe {
+                    asm_att!(
+                        "movdqu ({a}), %xmm0",
+                        "movdqu 16({a}), %xmm1",
+                        "movdqu ({b}), %xmm2",
+                        "movdqu 16({b}), %xmm3",
+                        "paddd %xmm2, %xmm0",
+                        "paddd %xmm3, %xmm1",
+                        "movdqu %xmm0, ({sum})",
+                        "movdqu %xmm1, 16({sum})",
+                        a = in(reg) a,
+                        b = in(reg) b,
+                        sum = in(reg) sum.as_mut_ptr(),
+                        out("xmm0") _,
+                        out("xmm1") _,
+                        out("xmm2") _,
+                        out("xmm3") _,
+                        options(nostack),
+                    );
+                }
+                (sum, "sse2")
+            }
+            _ => (core::array::from_fn(|i| a[i] + b[i]), "portable"),
+        }
+    }
+
+    // Adds the index of the implementation the loader selected.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    global_ifunc_att! {
+        fn add_variant(x: u64) -> u64;
+        "avx2" => add_variant_avx2 {
+            "leaq 2(%rdi), %rax",
+            "ret",
+        }
+        "sse2" => add_variant_sse2 {
+            "leaq 1(%rdi), %rax",
+            "ret",
+        }
+        _ => add_variant_baseline {
+            "movq %rdi, %rax",
+            "ret",
+        }
+    }
+
+    // Inlined into every caller, which duplicates its labels.
+    #[cfg(feature = "parser")]
+    #[inline(always)]
+    fn triangle(n: u64) -> u64 {
+        let mut sum = 0;
+        unsafe {
+            asm_att!(
+                "testq {n}, {n}",
+                "jz {label:done}",
+                "{label:next}:",
+                "addq {n}, {sum}",
+                "decq {n}",
+                "jnz {label:next}",
+                "{label:done}:",
+                n = inout(reg) n => _,
+                sum = inout(reg) sum,
+                options(nomem, nostack),
+            );
+        }
+        sum
+    }
+
+    #[cfg(feature = "parser")]
+    fn mul_wide(a: u32, b: u32) -> (u32, u32) {
+        let (lo, hi): (u32, u32);
+        unsafe {
+            gcc_asm_att!(
+                "mull %3"
+                : "=a" (lo), "=d" (hi)
+                : "0" (a), "r" (b)
+                : "cc"
+            );
+        }
+        (lo, hi)
+    }
+
+    #[cfg(feature = "parser")]
+    fn fetch_add(counter: &mut u32, value: u32) -> u32 {
+        let mut previous = value;
+        unsafe {
+            gcc_asm_att!("lock; xaddl %0, %1" : "+r" (previous), "+m" (*counter) :: "memory");
+        }
+        previous
+    }
+
     #[test]
     fn add2_works() {
         assert_eq!(unsafe { add2(1, 5) }, 6);
         assert_eq!(unsafe { add2(-5, 5) }, 0);
     }
 
+    #[cfg(feature = "parser")]
+    #[test]
+    fn macro_arguments_are_registers() {
+        assert_eq!(unsafe { through_stack(42) }, 42);
+    }
+
     #[test]
     fn jmp2_should_work() {
         assert_eq!(unsafe { jmp2() }, 1000);
     }
+
+    #[test]
+    fn options_are_merged() {
+        assert_eq!(xor_swap(1, 2), (2, 1));
+        assert_eq!(xor_swap(u64::MAX, 0), (0, u64::MAX));
+    }
+
+    #[test]
+    fn fallback_is_ignored_on_x86() {
+        pause_or_spin();
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn expands_to_an_expression() {
+        let _: fn() -> ! = trap;
+        let mut value = 41;
+        increment(&mut value);
+        assert_eq!(value, 42);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn clobbers_are_inferred() {
+        let mut dst = [0_u8; 12];
+        copy(b"Hello World\0", &mut dst);
+        assert_eq!(&dst, b"Hello World\0");
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn suffixes_are_inferred() {
+        let mut value = 0;
+        store_seven(&mut value);
+        assert_eq!(value, 7);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn pointers_take_the_pointer_suffix() {
+        fn nothing() {}
+        extern "C" fn c_nothing() {}
+        let value = 0_u64;
+        let sum = (&raw const value as usize)
+            .wrapping_add(nothing as *const () as usize)
+            .wrapping_add(c_nothing as *const () as usize);
+        assert_eq!(pointer_sum(&value, nothing, c_nothing), sum);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn intel_templates_are_converted() {
+        assert_eq!(second_plus_one(&[1, 41]), 42);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn templates_build_as_intel() {
+        assert_eq!(element(&[3, 5, 8], 2), 8);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn memory_references_are_built() {
+        let values = [3, 1, 4, 1, 5];
+        for index in 1..values.len() {
+            assert_eq!(previous(&values, index), values[index - 1]);
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn immediates_are_prefixed() {
+        assert_eq!(scaled(5), 32);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn required_features_are_listed() {
+        const FEATURES: &[&str] = required_features!(
+            "vpaddd %ymm0, %ymm1, %ymm2",
+            "pdepq {mask}, {value}, {result}",
+            "vpxor %ymm2, %ymm2, %ymm2",
+        );
+        assert_eq!(FEATURES, ["avx2", "bmi2"]);
+        assert!(required_features!("addq $1, %rax").is_empty());
+
+        extern crate std;
+        if std::is_x86_feature_detected!("bmi2") {
+            assert_eq!(unsafe { deposit(0b101, 0xf0f0) }, 0x50);
+        }
+    }
+
+    #[test]
+    fn multiversion_selects_by_cpu() {
+        extern crate std;
+        let a = [1, 2, 3, 4, 5, 6, 7, 8];
+        let b = [10, 20, 30, 40, 50, 60, 70, 80];
+        let expected = if std::is_x86_feature_detected!("avx2") {
+            "avx2"
+        } else if std::is_x86_feature_detected!("sse2") {
+            "sse2"
+        } else {
+            "portable"
+        };
+        for _ in 0..2 {
+            assert_eq!(
+                add_lanes(&a, &b),
+                ([11, 22, 33, 44, 55, 66, 77, 88], expected)
+            );
+        }
+        assert!(__private::has_features("sse, sse2"));
+        assert!(!__private::has_features("sse2,avx9000"));
+        const { assert!(__private::detects("sse, sse2,avx512vl")) };
+        assert!(!__private::detects("sse2,avx9000"));
+        assert!(!__private::detects("sse2,"));
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[test]
+    fn ifunc_is_resolved_by_cpu() {
+        extern crate std;
+        let expected = if std::is_x86_feature_detected!("avx2") {
+            2
+        } else if std::is_x86_feature_detected!("sse2") {
+            1
+        } else {
+            0
+        };
+        assert_eq!(unsafe { add_variant(40) }, 40 + expected);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn named_labels_survive_inlining() {
+        assert_eq!(triangle(4), 10);
+        assert_eq!(triangle(0), 0);
+        assert_eq!(triangle(100), 5050);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn gcc_statements_are_lowered() {
+        assert_eq!(mul_wide(u32::MAX, 2), (u32::MAX - 1, 1));
+        let mut counter = 5;
+        assert_eq!(fetch_add(&mut counter, 3), 5);
+        assert_eq!(counter, 8);
+    }
 }
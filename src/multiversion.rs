@@ -0,0 +1,319 @@
+//! `asm_att_multiversion!`, and the run-time detection of the CPU features it
+//! selects implementations by.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Defines functions with an implementation for each set of CPU features,
+/// such as hand-written `asm_att!` for AVX2 and SSE2, and a portable Rust
+/// one.
+///
+/// Each implementation is a block tagged with the features it needs, in the
+/// form `#[target_feature(enable = "...")]` takes, and the portable one is
+/// tagged `_`. The first call detects the features of the CPU with `cpuid`,
+/// and the function pointer to the first implementation whose features are
+/// all there, or else to the portable one, is kept for the following calls.
+///
+/// The implementations are compiled with their features enabled, which their
+/// templates pass to `asm_att!` as `target_feature(enable = "...")` to avoid
+/// the warnings it gives for features the crate is not compiled with. They
+/// are left out on targets other than x86 and x86_64, where the portable
+/// implementation is always used.
+///
+/// The arguments of the function must be identifiers, and it cannot be
+/// generic. Features that the detection does not know of are rejected at
+/// compile time.
+///
+/// ```rust
+/// use asm_att::{asm_att, asm_att_multiversion};
+///
+/// asm_att_multiversion! {
+///     /// The number of bits set in `x`.
+///     pub fn count_ones(x: u64) -> u32 {
+///         "popcnt" => {
+///             let count: u64;
+///             unsafe {
+///                 asm_att!(
+///                     "popcntq {x}, {count}",
+///                     x = in(reg) x,
+///                     count = lateout(reg) count,
+///                     options(pure, nomem, nostack),
+///                     target_feature(enable = "popcnt"),
+///                 );
+///             }
+///             count as u32
+///         }
+///         _ => x.count_ones(),
+///     }
+/// }
+///
+/// assert_eq!(count_ones(0b1011), 3);
+/// ```
+#[macro_export]
+macro_rules! asm_att_multiversion {
+    () => {};
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident $params:tt -> $ret:ty { $($arms:tt)* }
+        $($rest:tt)*
+    ) => {
+        $crate::__asm_att_multiversion!([$(#[$attr])*] [$vis] $name $params $params [$ret] $($arms)*);
+        $crate::asm_att_multiversion!($($rest)*);
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident $params:tt { $($arms:tt)* }
+        $($rest:tt)*
+    ) => {
+        $crate::__asm_att_multiversion!([$(#[$attr])*] [$vis] $name $params $params [()] $($arms)*);
+        $crate::asm_att_multiversion!($($rest)*);
+    };
+}
+
+/// Defines one function of `asm_att_multiversion!`. The parameters are
+/// given twice: split into names and types for the function itself, and
+/// whole for its implementations, as they are repeated once per
+/// implementation.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __asm_att_multiversion {
+    (
+        [$($attr:tt)*] [$vis:vis] $name:ident ($($arg:ident: $ty:ty),* $(,)?) $params:tt [$ret:ty]
+        $($features:literal => $body:block $(,)?)*
+        _ => $fallback:expr $(,)?
+    ) => {
+        $($attr)*
+        $vis fn $name($($arg: $ty),*) -> $ret {
+            $($crate::__check_features!($features);)*
+            type Implementation = unsafe fn($($ty),*) -> $ret;
+            static SELECTED: ::core::sync::atomic::AtomicPtr<()> =
+                ::core::sync::atomic::AtomicPtr::new(::core::ptr::null_mut());
+
+            let mut selected = SELECTED.load(::core::sync::atomic::Ordering::Relaxed);
+            if selected.is_null() {
+                let implementation: Implementation = 'select: {
+                    $(
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        {
+                            #[target_feature(enable = $features)]
+                            fn implementation $params -> $ret $body
+
+                            if $crate::__private::has_features($features) {
+                                break 'select implementation as Implementation;
+                            }
+                        }
+                    )*
+                    fn portable $params -> $ret {
+                        $fallback
+                    }
+                    portable as Implementation
+                };
+                selected = implementation as *mut ();
+                SELECTED.store(selected, ::core::sync::atomic::Ordering::Relaxed);
+            }
+            // SAFETY: `selected` was cast from an `Implementation`, whose
+            // features the CPU has.
+            unsafe {
+                ::core::mem::transmute::<*mut (), Implementation>(selected)($($arg),*)
+            }
+        }
+    };
+}
+
+/// Fails to compile unless [`has_features`] detects every feature of a list,
+/// which it would otherwise take for missing.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __check_features {
+    ($features:literal) => {
+        const _: () = ::core::assert!(
+            $crate::__private::detects($features),
+            ::core::concat!("`", $features, "` names a CPU feature that is not detected"),
+        );
+    };
+}
+
+/// The features [`has_features`] detects, by their `#[target_feature]` name,
+/// with the `cpuid` leaf and subleaf that report them, the index of the
+/// register among `eax`, `ebx`, `ecx` and `edx`, and the bit.
+const FEATURES: &[(&str, u32, u32, usize, u32)] = &[
+    ("fxsr", 1, 0, 3, 24),
+    ("sse", 1, 0, 3, 25),
+    ("sse2", 1, 0, 3, 26),
+    ("sse3", 1, 0, 2, 0),
+    ("pclmulqdq", 1, 0, 2, 1),
+    ("ssse3", 1, 0, 2, 9),
+    ("fma", 1, 0, 2, 12),
+    ("cmpxchg16b", 1, 0, 2, 13),
+    ("sse4.1", 1, 0, 2, 19),
+    ("sse4.2", 1, 0, 2, 20),
+    ("movbe", 1, 0, 2, 22),
+    ("popcnt", 1, 0, 2, 23),
+    ("aes", 1, 0, 2, 25),
+    ("xsave", 1, 0, 2, 26),
+    ("avx", 1, 0, 2, 28),
+    ("f16c", 1, 0, 2, 29),
+    ("rdrand", 1, 0, 2, 30),
+    ("bmi1", 7, 0, 1, 3),
+    ("avx2", 7, 0, 1, 5),
+    ("bmi2", 7, 0, 1, 8),
+    ("rtm", 7, 0, 1, 11),
+    ("avx512f", 7, 0, 1, 16),
+    ("avx512dq", 7, 0, 1, 17),
+    ("rdseed", 7, 0, 1, 18),
+    ("adx", 7, 0, 1, 19),
+    ("avx512ifma", 7, 0, 1, 21),
+    ("avx512cd", 7, 0, 1, 28),
+    ("sha", 7, 0, 1, 29),
+    ("avx512bw", 7, 0, 1, 30),
+    ("avx512vl", 7, 0, 1, 31),
+    ("avx512vbmi", 7, 0, 2, 1),
+    ("avx512vbmi2", 7, 0, 2, 6),
+    ("gfni", 7, 0, 2, 8),
+    ("vaes", 7, 0, 2, 9),
+    ("vpclmulqdq", 7, 0, 2, 10),
+    ("avx512vnni", 7, 0, 2, 11),
+    ("avx512bitalg", 7, 0, 2, 12),
+    ("avx512vpopcntdq", 7, 0, 2, 14),
+    ("xsaveopt", 0xd, 1, 0, 0),
+    ("xsavec", 0xd, 1, 0, 1),
+    ("xsaves", 0xd, 1, 0, 3),
+    ("lzcnt", 0x8000_0001, 0, 2, 5),
+    ("sse4a", 0x8000_0001, 0, 2, 6),
+    ("tbm", 0x8000_0001, 0, 2, 21),
+];
+
+/// The bit of [`DETECTED`] set once the features are detected.
+const INITIALIZED: u64 = 1 << 63;
+
+const _: () = assert!(FEATURES.len() < 63);
+
+/// The features of the CPU, by their index in [`FEATURES`], with
+/// [`INITIALIZED`].
+static DETECTED: AtomicU64 = AtomicU64::new(0);
+
+/// Whether every feature of a comma-separated list such as `avx2,bmi2` is
+/// one [`has_features`] detects, which the macros check at compile time.
+pub const fn detects(features: &str) -> bool {
+    let bytes = features.as_bytes();
+    let mut start = 0;
+    while start <= bytes.len() {
+        let mut end = start;
+        while end < bytes.len() && bytes[end] != b',' {
+            end += 1;
+        }
+        let (mut first, mut last) = (start, end);
+        while first < last && bytes[first].is_ascii_whitespace() {
+            first += 1;
+        }
+        while last > first && bytes[last - 1].is_ascii_whitespace() {
+            last -= 1;
+        }
+        let (_, rest) = bytes.split_at(first);
+        let (feature, _) = rest.split_at(last - first);
+        if !is_detected(feature) {
+            return false;
+        }
+        start = end + 1;
+    }
+    true
+}
+
+/// Whether a feature is in [`FEATURES`].
+const fn is_detected(feature: &[u8]) -> bool {
+    let mut index = 0;
+    while index < FEATURES.len() {
+        let name = FEATURES[index].0.as_bytes();
+        if name.len() == feature.len() {
+            let mut byte = 0;
+            while byte < name.len() && name[byte] == feature[byte] {
+                byte += 1;
+            }
+            if byte == name.len() {
+                return true;
+            }
+        }
+        index += 1;
+    }
+    false
+}
+
+/// Whether the CPU has every feature of a comma-separated list such as
+/// `avx2,bmi2`. Features that are not detected count as missing.
+pub fn has_features(features: &str) -> bool {
+    let mut detected = DETECTED.load(Ordering::Relaxed);
+    if detected == 0 {
+        detected = detect() | INITIALIZED;
+        DETECTED.store(detected, Ordering::Relaxed);
+    }
+    features.split(',').map(str::trim).all(|feature| {
+        FEATURES
+            .iter()
+            .position(|(name, ..)| *name == feature)
+            .is_some_and(|index| detected & 1 << index != 0)
+    })
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect() -> u64 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::__cpuid_count;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::__cpuid_count;
+
+    /// Features that use the `%ymm` registers, which the OS must save.
+    const AVX: &[&str] = &["avx", "avx2", "fma", "f16c", "vaes", "vpclmulqdq"];
+
+    let cpuid = |leaf: u32, subleaf: u32| {
+        let result = __cpuid_count(leaf, subleaf);
+        [result.eax, result.ebx, result.ecx, result.edx]
+    };
+    let max = cpuid(0, 0)[0];
+    let max_extended = cpuid(0x8000_0000, 0)[0];
+    let osxsave = max >= 1 && cpuid(1, 0)[2] & 1 << 27 != 0;
+    let xcr0 = if osxsave { xcr0() } else { 0 };
+
+    let mut detected = 0;
+    for (index, &(name, leaf, subleaf, register, bit)) in FEATURES.iter().enumerate() {
+        let supported = if leaf >= 0x8000_0000 {
+            leaf <= max_extended
+        } else {
+            leaf <= max
+        };
+        // The OS must save the `%ymm` registers for AVX, and the `%zmm` and
+        // mask registers as well for AVX-512.
+        let saved = if name.starts_with("avx512") {
+            xcr0 & 0xe6 == 0xe6
+        } else if AVX.contains(&name) {
+            xcr0 & 0x6 == 0x6
+        } else {
+            true
+        };
+        if supported && saved && cpuid(leaf, subleaf)[register] & 1 << bit != 0 {
+            detected |= 1 << index;
+        }
+    }
+    detected
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn detect() -> u64 {
+    0
+}
+
+/// The state components the OS saves, from `XCR0`. Only called once `cpuid`
+/// reports `OSXSAVE`, which implies `xsave`.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn xcr0() -> u64 {
+    let (low, high): (u32, u32);
+    unsafe {
+        crate::asm_att!(
+            "xgetbv",
+            in("ecx") 0,
+            out("eax") low,
+            out("edx") high,
+            options(nomem, nostack, preserves_flags),
+            target_feature(enable = "xsave"),
+        );
+    }
+    u64::from(high) << 32 | u64::from(low)
+}
@@ -0,0 +1,184 @@
+//! `macro_rules!` implementations, used unless the `parser` feature is enabled.
+
+/// See [`core::arch::asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any, and
+/// `target_feature(enable = "...")` is ignored.
+///
+/// AT&T syntax only exists on x86 and x86_64. Code that must also build for
+/// other targets can end the arguments with `; else { ... }`: the block is
+/// used instead of the assembly on those targets, and ignored on x86.
+#[macro_export]
+macro_rules! asm_att {
+    ( $($arg:tt)+ ) => {
+        $crate::__asm_att!(@munch asm [] [] $($arg)+);
+    };
+}
+
+/// See [`core::arch::global_asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any.
+#[macro_export]
+macro_rules! global_asm_att {
+    ( $($arg:tt)+ ) => {
+        $crate::__asm_att!(@munch global_asm [] [] $($arg)+);
+    };
+}
+
+/// See [`core::arch::naked_asm`] for more.
+///
+/// `att_syntax` is merged into the `options(...)` given, if any.
+#[macro_export]
+macro_rules! naked_asm_att {
+    ( $($arg:tt)+ ) => {
+        $crate::__asm_att!(@munch naked_asm [] [] $($arg)+);
+    };
+}
+
+/// Moves every `options(...)` group to the end of the arguments and merges
+/// them into a single group that contains `att_syntax` exactly once. The
+/// `target_feature(...)` arguments, which only silence the warnings of the
+/// `parser` feature, are dropped.
+///
+/// Arguments are munched up to four tokens at a time to keep the recursion
+/// depth of long templates well below the default limit.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __asm_att {
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        , options($($o:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)*] [$($opt)* , $($o)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt , options($($o:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a] [$($opt)* , $($o)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt $b:tt , options($($o:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a $b] [$($opt)* , $($o)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt $b:tt $c:tt , options($($o:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a $b $c] [$($opt)* , $($o)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        , target_feature($($f:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)*] [$($opt)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt , target_feature($($f:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a] [$($opt)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt $b:tt , target_feature($($f:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a $b] [$($opt)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*]
+        $a:tt $b:tt $c:tt , target_feature($($f:tt)*) $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a $b $c] [$($opt)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] ; else $fallback:block) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [$fallback] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt ; else $fallback:block) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a] [$fallback] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $b:tt ; else $fallback:block) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a $b] [$fallback] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $b:tt $c:tt ; else $fallback:block) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a $b $c] [$fallback] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] ,) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt ,) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a] [] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $b:tt ,) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a $b] [] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $b:tt $c:tt ,) => {
+        $crate::__asm_att!(@filter $mac [$($arg)* $a $b $c] [] [] $($opt)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $b:tt $c:tt $d:tt $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a $b $c $d] [$($opt)*] $($rest)*);
+    };
+    (@munch $mac:ident [$($arg:tt)*] [$($opt:tt)*] $a:tt $($rest:tt)*) => {
+        $crate::__asm_att!(@munch $mac [$($arg)* $a] [$($opt)*] $($rest)*);
+    };
+
+    (@filter $mac:ident [$($arg:tt)*] [$($fallback:tt)*] [$($kept:tt)*]) => {
+        $crate::__asm_att_emit!($mac [$($arg)*] [$($kept)*] [$($fallback)*]);
+    };
+    (@filter $mac:ident [$($arg:tt)*] [$($fallback:tt)*] [$($kept:tt)*] , $($rest:tt)*) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [$($fallback)*] [$($kept)*] $($rest)*);
+    };
+    (@filter $mac:ident [$($arg:tt)*] [$($fallback:tt)*] [$($kept:tt)*] att_syntax $($rest:tt)*) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [$($fallback)*] [$($kept)*] $($rest)*);
+    };
+    (@filter $mac:ident [$($arg:tt)*] [$($fallback:tt)*] [$($kept:tt)*] intel_syntax $($rest:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "`intel_syntax` conflicts with the AT&T syntax of `",
+            ::core::stringify!($mac),
+            "_att!`"
+        ));
+    };
+    (@filter $mac:ident [$($arg:tt)*] [$($fallback:tt)*] [$($kept:tt)*] $option:tt $($rest:tt)*) => {
+        $crate::__asm_att!(@filter $mac [$($arg)*] [$($fallback)*] [$($kept)* $option] $($rest)*);
+    };
+}
+
+/// Emits the final invocation on x86, ignoring any fallback.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __asm_att_emit {
+    ($mac:ident [$($arg:tt)*] [$($opt:tt)*] []) => {
+        ::core::arch::$mac!($($arg)*, options(att_syntax $(, $opt)*));
+    };
+    (asm [$($arg:tt)*] [$($opt:tt)*] [$fallback:block]) => {
+        ::core::arch::asm!($($arg)*, options(att_syntax $(, $opt)*));
+    };
+    ($mac:ident [$($arg:tt)*] [$($opt:tt)*] [$fallback:block]) => {
+        ::core::compile_error!(::core::concat!(
+            "`; else { ... }` is only supported by `asm_att!`, not by `",
+            ::core::stringify!($mac),
+            "_att!`"
+        ));
+    };
+}
+
+/// Emits the fallback on targets without AT&T syntax, or an error if there
+/// is none.
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __asm_att_emit {
+    (asm [$($arg:tt)*] [$($opt:tt)*] []) => {
+        ::core::compile_error!(
+            "`asm_att!` uses AT&T syntax, which only exists on x86 and x86_64 targets; \
+             end the arguments with `; else { ... }` to provide a Rust fallback for other targets"
+        );
+    };
+    ($mac:ident [$($arg:tt)*] [$($opt:tt)*] []) => {
+        ::core::compile_error!(::core::concat!(
+            "`",
+            ::core::stringify!($mac),
+            "_att!` uses AT&T syntax, which only exists on x86 and x86_64 targets"
+        ));
+    };
+    (asm [$($arg:tt)*] [$($opt:tt)*] [$fallback:block]) => {
+        $fallback
+    };
+    ($mac:ident [$($arg:tt)*] [$($opt:tt)*] [$fallback:block]) => {
+        ::core::compile_error!(::core::concat!(
+            "`; else { ... }` is only supported by `asm_att!`, not by `",
+            ::core::stringify!($mac),
+            "_att!`"
+        ));
+    };
+}